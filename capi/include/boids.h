@@ -0,0 +1,147 @@
+#ifndef BOIDS_H
+#define BOIDS_H
+
+/* Generated by cbindgen from capi/src/lib.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define BOIDS_FALLOFF_LINEAR 0
+
+#define BOIDS_FALLOFF_INVERSE 1
+
+#define BOIDS_FALLOFF_INVERSE_SQUARE 2
+
+#define BOIDS_NEIGHBOURHOOD_METRIC 0
+
+// The `topological_neighbours` nearest boids.
+#define BOIDS_NEIGHBOURHOOD_TOPOLOGICAL 1
+
+#define BOIDS_NEIGHBOURHOOD_VORONOI 2
+
+// A simulation owned by the caller.
+typedef struct BoidsSimulation BoidsSimulation;
+
+// The flocking parameters of a boid, mirroring `BoidData`.
+typedef struct BoidsBoidData {
+  float separation_weight;
+  float alignment_weight;
+  float cohesion_weight;
+  float noise_weight;
+  float separation_radius;
+  float alignment_radius;
+  float cohesion_radius;
+  // One of the `BOIDS_FALLOFF_*` values.
+  int separation_falloff;
+  // One of the `BOIDS_NEIGHBOURHOOD_*` values.
+  int neighbourhood;
+  // Only used by the topological neighbourhood.
+  uintptr_t topological_neighbours;
+  float max_speed;
+  float look_ahead;
+} BoidsBoidData;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Message describing the last failure on this thread, or null if nothing
+// has failed. Valid until the next failing call on the same thread.
+const char *boids_last_error(void);
+
+// Loads the scenario file at `scenario_path` into a new simulation, or
+// returns null if it can't be loaded. Flow grid files are read from
+// `flows_dir`, which may be null if the scenario uses none.
+//
+// # Safety
+// Both paths must be null or NUL-terminated strings.
+struct BoidsSimulation *boids_simulation_new(const char *scenario_path, const char *flows_dir);
+
+// Frees a simulation. Does nothing given null.
+//
+// # Safety
+// `simulation` must be null or a handle not yet freed.
+void boids_simulation_free(struct BoidsSimulation *simulation);
+
+// Writes the `BoidData` the scenario gives `species` to `data`. A null
+// species means the scenario's first one. Returns 0, or -1 if there's no
+// such species.
+//
+// # Safety
+// `species` must be null or a NUL-terminated string, and `data` must point
+// to writable memory.
+int boids_species_boid_data(const struct BoidsSimulation *simulation,
+                            const char *species,
+                            struct BoidsBoidData *data);
+
+// Adds a boid of `species` at `(x, y)` moving at `(vx, vy)`, flocking with
+// `data` in place of its species' parameters. A null species means the
+// scenario's first one, and null data its species' parameters. Returns 0,
+// or -1 if the position or velocity isn't finite, there's no such species
+// or `data` isn't valid: a falloff or neighbourhood that isn't one of the
+// constants, a number that isn't finite, a top speed that isn't positive or
+// a negative radius.
+//
+// # Safety
+// `species` must be null or a NUL-terminated string, and `data` null or
+// pointing to a `BoidsBoidData`.
+int boids_simulation_spawn_boid(struct BoidsSimulation *simulation,
+                                const char *species,
+                                const struct BoidsBoidData *data,
+                                float x,
+                                float y,
+                                float vx,
+                                float vy);
+
+// Adds an obstacle at `(x, y)`. With a positive `radius` it's a solid
+// circle boids can't pass through, and otherwise a point they're pushed
+// away from. Either way `avoidance_weight` is how hard they're pushed.
+// Returns 0, or -1 if the position, radius or weight isn't finite.
+//
+// # Safety
+// `simulation` must be a live handle.
+int boids_simulation_add_obstacle(struct BoidsSimulation *simulation,
+                                  float x,
+                                  float y,
+                                  float radius,
+                                  float avoidance_weight);
+
+// Advances the simulation by `ticks` fixed time steps. Returns 0, or -1 if
+// a step panicked.
+//
+// # Safety
+// `simulation` must be a live handle.
+int boids_simulation_step(struct BoidsSimulation *simulation, uintptr_t ticks);
+
+// Number of live boids.
+//
+// # Safety
+// `simulation` must be a live handle.
+uintptr_t boids_simulation_boid_count(const struct BoidsSimulation *simulation);
+
+// Copies the position of up to `capacity` boids to `out` as `x, y` pairs,
+// returning how many were copied. Boids come in the same order for
+// positions and velocities until the simulation next changes.
+//
+// # Safety
+// `out` must have room for `2 * capacity` floats.
+uintptr_t boids_simulation_copy_positions(const struct BoidsSimulation *simulation,
+                                          float *out,
+                                          uintptr_t capacity);
+
+// Copies the velocity of up to `capacity` boids to `out` as `x, y` pairs,
+// returning how many were copied.
+//
+// # Safety
+// `out` must have room for `2 * capacity` floats.
+uintptr_t boids_simulation_copy_velocities(const struct BoidsSimulation *simulation,
+                                           float *out,
+                                           uintptr_t capacity);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* BOIDS_H */
@@ -0,0 +1,437 @@
+//! C API for embedding the headless simulation in other programs, declared in
+//! `include/boids.h`.
+//!
+//! Every function taking a simulation expects a live handle from
+//! `boids_simulation_new`. Functions that can fail return a negative number
+//! or null, and leave a message for `boids_last_error`. Panics are caught at
+//! the boundary and reported the same way, though the simulation that
+//! panicked may be left half-updated.
+use amethyst::{config::Config, ecs::prelude::*};
+use anyhow::{anyhow, bail, ensure, Result};
+use boids::{
+    components::{BoidData, Falloff, Neighbourhood, ObstacleShape, Position, Velocity},
+    entities::boids::{fill_boid, new_obstacle, new_solid_obstacle},
+    resources::{Scenario, SpeciesConfig, SpriteRegistry},
+    simulation::Simulation,
+    spatial::Shape,
+};
+use nalgebra::Vector2;
+use std::{
+    cell::RefCell,
+    convert::TryFrom,
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_int},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+/// A simulation owned by the caller.
+pub struct BoidsSimulation(Simulation<'static, 'static>);
+
+// Values of `BoidsBoidData::separation_falloff`. The fields are plain ints
+// rather than enums, so any value C passes can be checked.
+pub const BOIDS_FALLOFF_LINEAR: c_int = 0;
+pub const BOIDS_FALLOFF_INVERSE: c_int = 1;
+pub const BOIDS_FALLOFF_INVERSE_SQUARE: c_int = 2;
+
+// Values of `BoidsBoidData::neighbourhood`.
+pub const BOIDS_NEIGHBOURHOOD_METRIC: c_int = 0;
+/// The `topological_neighbours` nearest boids.
+pub const BOIDS_NEIGHBOURHOOD_TOPOLOGICAL: c_int = 1;
+pub const BOIDS_NEIGHBOURHOOD_VORONOI: c_int = 2;
+
+/// The flocking parameters of a boid, mirroring `BoidData`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoidsBoidData {
+    pub separation_weight: f32,
+    pub alignment_weight: f32,
+    pub cohesion_weight: f32,
+    pub noise_weight: f32,
+    pub separation_radius: f32,
+    pub alignment_radius: f32,
+    pub cohesion_radius: f32,
+    /// One of the `BOIDS_FALLOFF_*` values.
+    pub separation_falloff: c_int,
+    /// One of the `BOIDS_NEIGHBOURHOOD_*` values.
+    pub neighbourhood: c_int,
+    /// Only used by the topological neighbourhood.
+    pub topological_neighbours: usize,
+    pub max_speed: f32,
+    pub look_ahead: f32,
+}
+
+impl From<&BoidData> for BoidsBoidData {
+    fn from(data: &BoidData) -> Self {
+        let (neighbourhood, topological_neighbours) = match data.neighbourhood {
+            Neighbourhood::Metric => (BOIDS_NEIGHBOURHOOD_METRIC, 0),
+            Neighbourhood::Topological(count) => (BOIDS_NEIGHBOURHOOD_TOPOLOGICAL, count),
+            Neighbourhood::Voronoi => (BOIDS_NEIGHBOURHOOD_VORONOI, 0),
+        };
+        BoidsBoidData {
+            separation_weight: data.separation_weight,
+            alignment_weight: data.alignment_weight,
+            cohesion_weight: data.cohesion_weight,
+            noise_weight: data.noise_weight,
+            separation_radius: data.separation_radius,
+            alignment_radius: data.alignment_radius,
+            cohesion_radius: data.cohesion_radius,
+            separation_falloff: match data.separation_falloff {
+                Falloff::Linear => BOIDS_FALLOFF_LINEAR,
+                Falloff::Inverse => BOIDS_FALLOFF_INVERSE,
+                Falloff::InverseSquare => BOIDS_FALLOFF_INVERSE_SQUARE,
+            },
+            neighbourhood,
+            topological_neighbours,
+            max_speed: data.max_speed,
+            look_ahead: data.look_ahead,
+        }
+    }
+}
+
+/// Fails on unknown falloffs or neighbourhoods, and on anything
+/// `BoidData::validate` refuses.
+impl TryFrom<&BoidsBoidData> for BoidData {
+    type Error = anyhow::Error;
+
+    fn try_from(data: &BoidsBoidData) -> Result<Self> {
+        let boid_data = BoidData {
+            separation_weight: data.separation_weight,
+            alignment_weight: data.alignment_weight,
+            cohesion_weight: data.cohesion_weight,
+            noise_weight: data.noise_weight,
+            separation_radius: data.separation_radius,
+            alignment_radius: data.alignment_radius,
+            cohesion_radius: data.cohesion_radius,
+            separation_falloff: match data.separation_falloff {
+                BOIDS_FALLOFF_LINEAR => Falloff::Linear,
+                BOIDS_FALLOFF_INVERSE => Falloff::Inverse,
+                BOIDS_FALLOFF_INVERSE_SQUARE => Falloff::InverseSquare,
+                other => bail!("{} is not a BOIDS_FALLOFF value", other),
+            },
+            neighbourhood: match data.neighbourhood {
+                BOIDS_NEIGHBOURHOOD_METRIC => Neighbourhood::Metric,
+                BOIDS_NEIGHBOURHOOD_TOPOLOGICAL => {
+                    Neighbourhood::Topological(data.topological_neighbours)
+                }
+                BOIDS_NEIGHBOURHOOD_VORONOI => Neighbourhood::Voronoi,
+                other => bail!("{} is not a BOIDS_NEIGHBOURHOOD value", other),
+            },
+            max_speed: data.max_speed,
+            look_ahead: data.look_ahead,
+        };
+        boid_data.validate()?;
+        Ok(boid_data)
+    }
+}
+
+/// Message describing the last failure on this thread, or null if nothing
+/// has failed. Valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn boids_last_error() -> *const c_char {
+    guard(ptr::null(), || {
+        LAST_ERROR.with(|error| {
+            error
+                .borrow()
+                .as_ref()
+                .map_or(ptr::null(), |message| message.as_ptr())
+        })
+    })
+}
+
+/// Loads the scenario file at `scenario_path` into a new simulation, or
+/// returns null if it can't be loaded. Flow grid files are read from
+/// `flows_dir`, which may be null if the scenario uses none.
+///
+/// # Safety
+/// Both paths must be null or NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn boids_simulation_new(
+    scenario_path: *const c_char,
+    flows_dir: *const c_char,
+) -> *mut BoidsSimulation {
+    guard(ptr::null_mut(), || {
+        let simulation = (|| {
+            let scenario_path =
+                string(scenario_path)?.ok_or_else(|| anyhow!("No scenario path"))?;
+            let mut scenario = Scenario::load(scenario_path)?;
+            if let Some(flows_dir) = string(flows_dir)? {
+                scenario.flow.load_grids(flows_dir.as_ref())?;
+            }
+            Simulation::new(scenario)
+        })();
+        match simulation {
+            Ok(simulation) => Box::into_raw(Box::new(BoidsSimulation(simulation))),
+            Err(e) => {
+                set_error(e);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Frees a simulation. Does nothing given null.
+///
+/// # Safety
+/// `simulation` must be null or a handle not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn boids_simulation_free(simulation: *mut BoidsSimulation) {
+    guard((), || {
+        if !simulation.is_null() {
+            drop(Box::from_raw(simulation));
+        }
+    })
+}
+
+/// Writes the `BoidData` the scenario gives `species` to `data`. A null
+/// species means the scenario's first one. Returns 0, or -1 if there's no
+/// such species.
+///
+/// # Safety
+/// `species` must be null or a NUL-terminated string, and `data` must point
+/// to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn boids_species_boid_data(
+    simulation: *const BoidsSimulation,
+    species: *const c_char,
+    data: *mut BoidsBoidData,
+) -> c_int {
+    status(|| {
+        let scenario = (*simulation).0.world.read_resource::<Scenario>();
+        let species = species_config(&scenario, string(species)?)?;
+        *data = (&species.boid).into();
+        Ok(())
+    })
+}
+
+/// Adds a boid of `species` at `(x, y)` moving at `(vx, vy)`, flocking with
+/// `data` in place of its species' parameters. A null species means the
+/// scenario's first one, and null data its species' parameters. Returns 0,
+/// or -1 if the position or velocity isn't finite, there's no such species
+/// or `data` isn't valid: a falloff or neighbourhood that isn't one of the
+/// constants, a number that isn't finite, a top speed that isn't positive or
+/// a negative radius.
+///
+/// # Safety
+/// `species` must be null or a NUL-terminated string, and `data` null or
+/// pointing to a `BoidsBoidData`.
+#[no_mangle]
+pub unsafe extern "C" fn boids_simulation_spawn_boid(
+    simulation: *mut BoidsSimulation,
+    species: *const c_char,
+    data: *const BoidsBoidData,
+    x: f32,
+    y: f32,
+    vx: f32,
+    vy: f32,
+) -> c_int {
+    status(|| {
+        ensure!(
+            [x, y, vx, vy].iter().all(|value| value.is_finite()),
+            "The boid's position and velocity must be finite"
+        );
+        let world = &mut (*simulation).0.world;
+        let result = (|| {
+            let (entities, sprite_registry, updater, scenario) = world.system_data::<(
+                Entities,
+                Read<SpriteRegistry>,
+                Read<LazyUpdate>,
+                ReadExpect<Scenario>,
+            )>();
+            let mut species = species_config(&scenario, string(species)?)?.clone();
+            if let Some(data) = data.as_ref() {
+                species.boid = BoidData::try_from(data)?;
+            }
+            let entity = fill_boid(
+                &entities,
+                &sprite_registry,
+                &updater,
+                Vector2::new(x, y),
+                &scenario,
+                &species,
+            )?;
+            updater.insert(entity, Velocity(Vector2::new(vx, vy)));
+            Ok(())
+        })();
+        world.maintain();
+        result
+    })
+}
+
+/// Adds an obstacle at `(x, y)`. With a positive `radius` it's a solid
+/// circle boids can't pass through, and otherwise a point they're pushed
+/// away from. Either way `avoidance_weight` is how hard they're pushed.
+/// Returns 0, or -1 if the position, radius or weight isn't finite.
+///
+/// # Safety
+/// `simulation` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn boids_simulation_add_obstacle(
+    simulation: *mut BoidsSimulation,
+    x: f32,
+    y: f32,
+    radius: f32,
+    avoidance_weight: f32,
+) -> c_int {
+    status(|| {
+        ensure!(
+            [x, y, radius, avoidance_weight]
+                .iter()
+                .all(|value| value.is_finite()),
+            "The obstacle's position, radius and avoidance weight must be finite"
+        );
+        let world = &mut (*simulation).0.world;
+        let position = Vector2::new(x, y);
+        if radius > 0.0 {
+            let shape = ObstacleShape {
+                shape: Shape::Circle { radius },
+                avoidance_weight,
+            };
+            new_solid_obstacle(world, position, shape);
+        } else {
+            new_obstacle(world, position, "obstacle", avoidance_weight);
+        }
+        world.maintain();
+        Ok(())
+    })
+}
+
+/// Advances the simulation by `ticks` fixed time steps. Returns 0, or -1 if
+/// a step panicked.
+///
+/// # Safety
+/// `simulation` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn boids_simulation_step(
+    simulation: *mut BoidsSimulation,
+    ticks: usize,
+) -> c_int {
+    status(|| {
+        (*simulation).0.run(ticks);
+        Ok(())
+    })
+}
+
+/// Number of live boids.
+///
+/// # Safety
+/// `simulation` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn boids_simulation_boid_count(simulation: *const BoidsSimulation) -> usize {
+    guard(0, || boids(&(*simulation).0).len())
+}
+
+/// Copies the position of up to `capacity` boids to `out` as `x, y` pairs,
+/// returning how many were copied. Boids come in the same order for
+/// positions and velocities until the simulation next changes.
+///
+/// # Safety
+/// `out` must have room for `2 * capacity` floats.
+#[no_mangle]
+pub unsafe extern "C" fn boids_simulation_copy_positions(
+    simulation: *const BoidsSimulation,
+    out: *mut f32,
+    capacity: usize,
+) -> usize {
+    guard(0, || {
+        let boids = boids(&(*simulation).0);
+        copy(boids.iter().map(|(position, _)| *position), out, capacity)
+    })
+}
+
+/// Copies the velocity of up to `capacity` boids to `out` as `x, y` pairs,
+/// returning how many were copied.
+///
+/// # Safety
+/// `out` must have room for `2 * capacity` floats.
+#[no_mangle]
+pub unsafe extern "C" fn boids_simulation_copy_velocities(
+    simulation: *const BoidsSimulation,
+    out: *mut f32,
+    capacity: usize,
+) -> usize {
+    guard(0, || {
+        let boids = boids(&(*simulation).0);
+        copy(boids.iter().map(|(_, velocity)| *velocity), out, capacity)
+    })
+}
+
+/// Position and velocity of every boid, in entity order.
+fn boids(simulation: &Simulation) -> Vec<(Vector2<f32>, Vector2<f32>)> {
+    let (boid_datas, positions, velocities) = simulation.world.system_data::<(
+        ReadStorage<BoidData>,
+        ReadStorage<Position>,
+        ReadStorage<Velocity>,
+    )>();
+    (&boid_datas, &positions, &velocities)
+        .join()
+        .map(|(_, position, velocity)| (position.0, velocity.0))
+        .collect()
+}
+
+unsafe fn copy(
+    vectors: impl ExactSizeIterator<Item = Vector2<f32>>,
+    out: *mut f32,
+    capacity: usize,
+) -> usize {
+    let count = vectors.len().min(capacity);
+    if count == 0 {
+        return 0;
+    }
+    let out = slice::from_raw_parts_mut(out, 2 * count);
+    for (pair, vector) in out.chunks_exact_mut(2).zip(vectors) {
+        pair[0] = vector.x;
+        pair[1] = vector.y;
+    }
+    count
+}
+
+fn species_config<'a>(scenario: &'a Scenario, name: Option<&str>) -> Result<&'a SpeciesConfig> {
+    match name {
+        Some(name) => scenario.species_named(name),
+        None => scenario.default_species(),
+    }
+}
+
+unsafe fn string<'a>(string: *const c_char) -> Result<Option<&'a str>> {
+    if string.is_null() {
+        return Ok(None);
+    }
+    Ok(Some(CStr::from_ptr(string).to_str()?))
+}
+
+fn set_error(error: impl std::fmt::Display) {
+    let message =
+        CString::new(error.to_string().replace('\0', "")).expect("NUL bytes have been removed");
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+/// Runs `f`, returning 0 if it succeeds and -1 if it fails or panics.
+fn status(f: impl FnOnce() -> Result<()>) -> c_int {
+    guard(-1, || match f() {
+        Ok(()) => 0,
+        Err(e) => {
+            set_error(e);
+            -1
+        }
+    })
+}
+
+/// Runs `f`, returning `on_panic` and leaving the panic's message for
+/// `boids_last_error` if it panics, since unwinding into C is undefined
+/// behaviour.
+fn guard<T>(on_panic: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Unknown panic".to_string());
+        set_error(format!("Panicked: {}", message));
+        on_panic
+    })
+}
@@ -0,0 +1,32 @@
+use std::{env, path::Path, process::Command};
+
+/// Builds `smoke.c` against the shared library and header, and runs it.
+#[test]
+fn c_program_runs() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // Tests run from `target/<profile>/deps`, next to where the library is built
+    let test_exe = env::current_exe().unwrap();
+    let deps_dir = test_exe.parent().unwrap();
+    let library_dir = deps_dir.parent().unwrap();
+    let program = deps_dir.join("boids-capi-smoke");
+
+    let compiled = Command::new(env::var("CC").unwrap_or_else(|_| "cc".to_string()))
+        .arg(manifest_dir.join("tests").join("smoke.c"))
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg("-L")
+        .arg(library_dir)
+        .args(["-lboids_capi", "-lm", "-o"])
+        .arg(&program)
+        .status()
+        .unwrap();
+    assert!(compiled.success());
+
+    let scenario = manifest_dir.join("../resources/scenarios/default.ron");
+    let ran = Command::new(&program)
+        .arg(scenario)
+        .env("LD_LIBRARY_PATH", library_dir)
+        .status()
+        .unwrap();
+    assert!(ran.success());
+}
@@ -0,0 +1,22 @@
+use std::{fs, path::Path};
+
+/// Generates the header from the crate again, and checks it's the one that's
+/// checked in. Regenerate it with the command in `cbindgen.toml` if not.
+#[test]
+fn header_is_up_to_date() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let config = cbindgen::Config::from_file(manifest_dir.join("cbindgen.toml")).unwrap();
+    let mut generated = Vec::new();
+    cbindgen::Builder::new()
+        .with_crate(manifest_dir)
+        .with_config(config)
+        .generate()
+        .unwrap()
+        .write(&mut generated);
+
+    let checked_in = fs::read_to_string(manifest_dir.join("include").join("boids.h")).unwrap();
+    assert!(
+        String::from_utf8(generated).unwrap() == checked_in,
+        "include/boids.h is out of date with src/lib.rs"
+    );
+}
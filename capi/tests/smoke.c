@@ -0,0 +1,82 @@
+/* Drives the C API the way an embedding program would. Takes the path of a
+ * scenario file and exits non-zero on the first failed check. */
+#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "boids.h"
+
+#define CHECK(condition)                                                   \
+    do {                                                                   \
+        if (!(condition)) {                                                \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, \
+                    #condition);                                           \
+            return 1;                                                      \
+        }                                                                  \
+    } while (0)
+
+int main(int argc, char **argv) {
+    CHECK(argc == 2);
+    CHECK(boids_simulation_new("missing.ron", NULL) == NULL);
+    CHECK(boids_last_error() != NULL);
+
+    BoidsSimulation *simulation = boids_simulation_new(argv[1], NULL);
+    if (simulation == NULL) {
+        fprintf(stderr, "%s\n", boids_last_error());
+        return 1;
+    }
+    size_t initial = boids_simulation_boid_count(simulation);
+
+    BoidsBoidData data;
+    CHECK(boids_species_boid_data(simulation, NULL, &data) == 0);
+    CHECK(boids_species_boid_data(simulation, "dragon", &data) == -1);
+    data.max_speed = 50.0f;
+    data.neighbourhood = BOIDS_NEIGHBOURHOOD_TOPOLOGICAL;
+    data.topological_neighbours = 4;
+    for (int i = 0; i < 10; i++) {
+        CHECK(boids_simulation_spawn_boid(simulation, NULL, &data, 10.0f * i, 0.0f,
+                                          20.0f, 0.0f) == 0);
+    }
+    CHECK(boids_simulation_spawn_boid(simulation, "dragon", NULL, 0, 0, 0, 0) == -1);
+
+    BoidsBoidData invalid = data;
+    invalid.max_speed = 0.0f;
+    CHECK(boids_simulation_spawn_boid(simulation, NULL, &invalid, 0, 0, 0, 0) == -1);
+    invalid = data;
+    invalid.separation_falloff = 7;
+    CHECK(boids_simulation_spawn_boid(simulation, NULL, &invalid, 0, 0, 0, 0) == -1);
+    invalid = data;
+    invalid.neighbourhood = -1;
+    CHECK(boids_simulation_spawn_boid(simulation, NULL, &invalid, 0, 0, 0, 0) == -1);
+    CHECK(boids_simulation_spawn_boid(simulation, NULL, &data, NAN, 0, 0, 0) == -1);
+    CHECK(boids_simulation_spawn_boid(simulation, NULL, &data, 0, 0, 0, INFINITY) == -1);
+    CHECK(boids_last_error() != NULL);
+
+    CHECK(boids_simulation_add_obstacle(simulation, 0.0f, 100.0f, 0.0f, 0.5f) == 0);
+    CHECK(boids_simulation_add_obstacle(simulation, 0.0f, -100.0f, 20.0f, 1.0f) == 0);
+    CHECK(boids_simulation_add_obstacle(simulation, 0.0f, 0.0f, 0.0f, NAN) == -1);
+    CHECK(boids_simulation_add_obstacle(simulation, NAN, 0.0f, 0.0f, 1.0f) == -1);
+
+    size_t count = boids_simulation_boid_count(simulation);
+    CHECK(count == initial + 10);
+    float *positions = malloc(2 * count * sizeof(float));
+    float *velocities = malloc(2 * count * sizeof(float));
+    CHECK(boids_simulation_copy_positions(simulation, positions, count) == count);
+    CHECK(boids_simulation_copy_positions(simulation, positions, 3) == 3);
+    float start_x = positions[2 * (count - 1)];
+    CHECK(start_x == 90.0f);
+
+    CHECK(boids_simulation_step(simulation, 20) == 0);
+    CHECK(boids_simulation_copy_positions(simulation, positions, count) == count);
+    CHECK(boids_simulation_copy_velocities(simulation, velocities, count) == count);
+    CHECK(positions[2 * (count - 1)] != start_x);
+    for (size_t i = 0; i < 2 * count; i++) {
+        CHECK(isfinite(positions[i]) && isfinite(velocities[i]));
+    }
+
+    free(positions);
+    free(velocities);
+    boids_simulation_free(simulation);
+    boids_simulation_free(NULL);
+    return 0;
+}
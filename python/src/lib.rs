@@ -0,0 +1,237 @@
+//! Python bindings for the headless simulation, built as the `boids` extension
+//! module:
+//!
+//! ```python
+//! import boids
+//!
+//! sim = boids.Simulation.load("resources/scenarios/default.ron")
+//! sim.spawn(50, cohesion_weight=2.0)
+//! sim.step(100)
+//! positions = sim.positions()  # (n, 2) float32 array
+//! ```
+use amethyst::{config::Config, ecs::prelude::*};
+use boids::{
+    components::{BoidData, ObstacleShape, Position, Species, Velocity},
+    entities::{
+        boids::{new_obstacle, new_solid_obstacle},
+        scenario::world_extents,
+    },
+    resources::Scenario,
+    simulation::Simulation,
+    spatial::Shape,
+};
+use nalgebra::Vector2;
+use numpy::{IntoPyArray, PyArray2};
+use pyo3::{
+    exceptions::{PyKeyError, PyValueError},
+    prelude::*,
+    types::PyDict,
+};
+use std::path::Path;
+
+/// A simulation stepped from Python. Boids are always listed in the same
+/// order, so the rows of `positions`, `velocities` and `species` line up.
+#[pyclass(name = "Simulation", unsendable)]
+pub struct PySimulation {
+    simulation: Simulation<'static, 'static>,
+}
+
+#[pymethods]
+impl PySimulation {
+    /// Sets up the world described by `scenario`, a dict laid out like a
+    /// scenario file. Flow grid files are read from `flows_dir`.
+    #[new]
+    #[pyo3(signature = (scenario, flows_dir = None))]
+    fn new(py: Python, scenario: &PyDict, flows_dir: Option<&str>) -> PyResult<Self> {
+        let scenario = serde_json::from_value(to_json(py, scenario)?)
+            .map_err(|e| PyValueError::new_err(format!("Invalid scenario: {}", e)))?;
+        PySimulation::start(scenario, flows_dir)
+    }
+
+    /// Sets up the world described by the scenario file at `path`.
+    #[staticmethod]
+    #[pyo3(signature = (path, flows_dir = None))]
+    fn load(path: &str, flows_dir: Option<&str>) -> PyResult<Self> {
+        let scenario = Scenario::load(path)
+            .map_err(|e| PyValueError::new_err(format!("Invalid scenario: {}", e)))?;
+        PySimulation::start(scenario, flows_dir)
+    }
+
+    /// Places `count` boids of `species`, the scenario's first species if not
+    /// given, uniformly within `half_extents` of the origin. Keyword arguments
+    /// override fields of the species' `BoidData` for these boids only.
+    #[pyo3(signature = (count, species = None, half_extents = None, **params))]
+    fn spawn(
+        &mut self,
+        py: Python,
+        count: usize,
+        species: Option<&str>,
+        half_extents: Option<(f32, f32)>,
+        params: Option<&PyDict>,
+    ) -> PyResult<()> {
+        let mut species = {
+            let scenario = self.simulation.world.read_resource::<Scenario>();
+            match species {
+                Some(name) => scenario.species_named(name)?.clone(),
+                None => scenario.default_species()?.clone(),
+            }
+        };
+        if let Some(params) = params {
+            species.boid = with_fields(py, &species.boid, params)?;
+        }
+        let half_extents = half_extents
+            .map(|(x, y)| Vector2::new(x, y))
+            .unwrap_or_else(|| world_extents(&self.simulation.world));
+        self.simulation.spawn(&species, count, half_extents)?;
+        Ok(())
+    }
+
+    /// Places an obstacle at `(x, y)`. With a `radius` it's a solid circle
+    /// boids can't pass through, and without one a point they're pushed away
+    /// from. Either way `avoidance_weight` is how hard they're pushed.
+    #[pyo3(signature = (x, y, radius = None, avoidance_weight = 1.0))]
+    fn add_obstacle(
+        &mut self,
+        x: f32,
+        y: f32,
+        radius: Option<f32>,
+        avoidance_weight: f32,
+    ) -> PyResult<()> {
+        let valid_radius = radius.map_or(true, |radius| radius.is_finite() && radius > 0.0);
+        if !x.is_finite() || !y.is_finite() || !avoidance_weight.is_finite() || !valid_radius {
+            return Err(PyValueError::new_err(
+                "The position and avoidance weight must be finite and the radius positive",
+            ));
+        }
+        let world = &mut self.simulation.world;
+        let position = Vector2::new(x, y);
+        match radius {
+            Some(radius) => new_solid_obstacle(
+                world,
+                position,
+                ObstacleShape {
+                    shape: Shape::Circle { radius },
+                    avoidance_weight,
+                },
+            ),
+            None => new_obstacle(world, position, "obstacle", avoidance_weight),
+        };
+        world.maintain();
+        Ok(())
+    }
+
+    #[pyo3(signature = (ticks = 1))]
+    fn step(&mut self, ticks: usize) {
+        self.simulation.run(ticks);
+    }
+
+    /// Position of every boid, as an `(n, 2)` array.
+    fn positions<'py>(&self, py: Python<'py>) -> PyResult<&'py PyArray2<f32>> {
+        rows(py, self.boids().map(|(position, _, _)| position))
+    }
+
+    /// Velocity of every boid, as an `(n, 2)` array.
+    fn velocities<'py>(&self, py: Python<'py>) -> PyResult<&'py PyArray2<f32>> {
+        rows(py, self.boids().map(|(_, velocity, _)| velocity))
+    }
+
+    /// Species name of every boid.
+    fn species(&self) -> Vec<String> {
+        self.boids().map(|(_, _, species)| species).collect()
+    }
+
+    /// Live boids of the species called `species`.
+    fn count(&self, species: &str) -> usize {
+        self.simulation.count(species)
+    }
+
+    /// Flock metrics, as a dict.
+    fn metrics<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let metrics = self.simulation.metrics();
+        let dict = PyDict::new(py);
+        dict.set_item("boids", metrics.boids)?;
+        dict.set_item("polarization", metrics.polarization)?;
+        dict.set_item("milling", metrics.milling)?;
+        dict.set_item("mean_speed", metrics.mean_speed)?;
+        dict.set_item("nearest_neighbour", metrics.nearest_neighbour)?;
+        Ok(dict)
+    }
+
+    fn __len__(&self) -> usize {
+        self.boids().count()
+    }
+}
+
+impl PySimulation {
+    fn start(mut scenario: Scenario, flows_dir: Option<&str>) -> PyResult<Self> {
+        if let Some(flows_dir) = flows_dir {
+            scenario.flow.load_grids(Path::new(flows_dir))?;
+        }
+        Ok(PySimulation {
+            simulation: Simulation::new(scenario)?,
+        })
+    }
+
+    /// Position, velocity and species of every boid, in entity order.
+    fn boids(&self) -> impl Iterator<Item = (Vector2<f32>, Vector2<f32>, String)> {
+        let (boid_datas, positions, velocities, species) = self.simulation.world.system_data::<(
+            ReadStorage<BoidData>,
+            ReadStorage<Position>,
+            ReadStorage<Velocity>,
+            ReadStorage<Species>,
+        )>();
+        (&boid_datas, &positions, &velocities, species.maybe())
+            .join()
+            .map(|(_, position, velocity, species)| {
+                let name = species.map(|s| s.0.clone()).unwrap_or_default();
+                (position.0, velocity.0, name)
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+}
+
+/// `vectors` as the rows of a NumPy array.
+fn rows(py: Python, vectors: impl Iterator<Item = Vector2<f32>>) -> PyResult<&PyArray2<f32>> {
+    let flat = vectors.flat_map(|v| vec![v.x, v.y]).collect::<Vec<_>>();
+    let count = flat.len() / 2;
+    flat.into_pyarray(py).reshape([count, 2])
+}
+
+/// `boid_data` with the fields named in `fields` replaced.
+fn with_fields(py: Python, boid_data: &BoidData, fields: &PyDict) -> PyResult<BoidData> {
+    let mut value = serde_json::to_value(boid_data).expect("BoidData is serializable");
+    let object = value.as_object_mut().expect("BoidData is a struct");
+    for (name, field) in fields.iter() {
+        let name = name.extract::<String>()?;
+        if !object.contains_key(&name) {
+            return Err(PyKeyError::new_err(format!(
+                "{} is not a BoidData field",
+                name
+            )));
+        }
+        object.insert(name, to_json(py, field)?);
+    }
+    let boid_data = serde_json::from_value::<BoidData>(value)
+        .map_err(|e| PyValueError::new_err(format!("Invalid BoidData: {}", e)))?;
+    boid_data
+        .validate()
+        .map_err(|e| PyValueError::new_err(format!("Invalid BoidData: {}", e)))?;
+    Ok(boid_data)
+}
+
+/// Plain Python data as JSON, ready to deserialize.
+fn to_json(py: Python, value: &PyAny) -> PyResult<serde_json::Value> {
+    let text = py
+        .import("json")?
+        .call_method1("dumps", (value,))?
+        .extract::<String>()?;
+    serde_json::from_str(&text).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+#[pymodule]
+#[pyo3(name = "boids")]
+pub fn module(_py: Python, module: &PyModule) -> PyResult<()> {
+    module.add_class::<PySimulation>()?;
+    Ok(())
+}
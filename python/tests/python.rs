@@ -0,0 +1,43 @@
+use boids_python::module;
+use pyo3::{prelude::*, types::PyDict};
+use std::sync::Once;
+
+static REGISTER_MODULE: Once = Once::new();
+
+/// Runs `f` in an embedded interpreter with the module built in.
+fn with_module<T>(f: impl FnOnce(Python) -> T) -> T {
+    REGISTER_MODULE.call_once(|| pyo3::append_to_inittab!(module));
+    Python::with_gil(f)
+}
+
+/// Runs the functions called `tests` from `test_boids.py`.
+fn run_python_tests(tests: &[&str]) {
+    with_module(|py| {
+        let globals = PyDict::new(py);
+        globals.set_item("__name__", "test_boids").unwrap();
+        let mut code = include_str!("test_boids.py").to_string();
+        for test in tests {
+            code.push_str(&format!("\n{}()\n", test));
+        }
+        py.run(&code, Some(globals), None)
+            .map_err(|e| e.print_and_set_sys_last_vars(py))
+            .unwrap();
+    });
+}
+
+#[test]
+fn python_tests_pass() {
+    run_python_tests(&["test_spawning_and_stepping", "test_bad_input_raises"]);
+}
+
+#[test]
+fn numpy_arrays_are_returned() {
+    if let Err(e) = with_module(|py| py.import("numpy").map(|_| ())) {
+        eprintln!(
+            "SKIPPED numpy_arrays_are_returned: NumPy can't be imported: {}",
+            e
+        );
+        return;
+    }
+    run_python_tests(&["test_arrays"]);
+}
@@ -0,0 +1,109 @@
+"""Exercises the `boids` module. Run by `cargo test`, or by pytest once the
+module is installed with `maturin develop`. `test_arrays` needs NumPy, and
+`cargo test` reports it as skipped when NumPy can't be imported."""
+import boids
+
+SCENARIO = {
+    "species": [
+        {
+            "name": "boid",
+            "boid": {
+                "separation_weight": 0.1,
+                "alignment_weight": 0.02,
+                "cohesion_weight": 1.0,
+                "noise_weight": 0.0,
+                "separation_radius": 75.0,
+                "alignment_radius": 150.0,
+                "cohesion_radius": 150.0,
+                "separation_falloff": "InverseSquare",
+                "neighbourhood": "Metric",
+                "max_speed": 500.0,
+                "look_ahead": 0.4,
+            },
+        },
+        {
+            "name": "hawk",
+            "boid": {
+                "separation_weight": 0.5,
+                "alignment_weight": 0.0,
+                "cohesion_weight": 0.0,
+                "noise_weight": 0.0,
+                "separation_radius": 50.0,
+                "alignment_radius": 50.0,
+                "cohesion_radius": 50.0,
+                "separation_falloff": "Linear",
+                "neighbourhood": {"Topological": 3},
+                "max_speed": 300.0,
+                "look_ahead": 0.0,
+            },
+        },
+    ],
+    "obstacles": [
+        {
+            "Solid": {
+                "position": [200.0, 0.0],
+                "shape": {"Circle": {"radius": 30.0}},
+                "avoidance_weight": 1.0,
+            }
+        }
+    ],
+}
+
+
+def test_spawning_and_stepping():
+    sim = boids.Simulation(SCENARIO)
+    sim.spawn(20, half_extents=(100.0, 100.0))
+    sim.spawn(3, species="hawk", max_speed=100.0, neighbourhood="Metric")
+    sim.add_obstacle(-200.0, 0.0)
+    sim.add_obstacle(0.0, 300.0, radius=20.0)
+    assert len(sim) == 23
+    assert sim.count("boid") == 20
+    assert sorted(set(sim.species())) == ["boid", "hawk"]
+
+    sim.step(10)
+    metrics = sim.metrics()
+    assert metrics["boids"] == 23
+    assert 0.0 <= metrics["polarization"] <= 1.0
+
+
+def test_arrays():
+    import numpy
+
+    sim = boids.Simulation(SCENARIO)
+    sim.spawn(20, half_extents=(100.0, 100.0))
+    sim.spawn(3, species="hawk", max_speed=100.0)
+    sim.step(10)
+    positions, velocities = sim.positions(), sim.velocities()
+    assert positions.shape == velocities.shape == (23, 2)
+    assert positions.dtype == numpy.float32
+    speeds = numpy.linalg.norm(velocities, axis=1)
+    hawks = numpy.array(sim.species()) == "hawk"
+    assert (speeds[hawks] <= 100.0 + 1e-3).all()
+
+
+def test_bad_input_raises():
+    sim = boids.Simulation(SCENARIO)
+    for call in [
+        lambda: sim.spawn(1, wingspan=3.0),
+        lambda: sim.spawn(1, max_speed="fast"),
+        lambda: sim.spawn(1, max_speed=0.0),
+        lambda: sim.spawn(1, separation_radius=-1.0),
+        lambda: sim.spawn(1, species="dragon"),
+        lambda: sim.add_obstacle(0.0, 0.0, radius=-1.0),
+        lambda: sim.add_obstacle(float("nan"), 0.0),
+        lambda: sim.add_obstacle(0.0, float("inf"), radius=5.0),
+        lambda: sim.add_obstacle(0.0, 0.0, avoidance_weight=float("nan")),
+        lambda: boids.Simulation({"species": "none"}),
+    ]:
+        try:
+            call()
+        except (KeyError, ValueError, RuntimeError):
+            continue
+        raise AssertionError("expected an error")
+    assert len(sim) == 0
+
+
+if __name__ == "__main__":
+    test_spawning_and_stepping()
+    test_arrays()
+    test_bad_input_raises()
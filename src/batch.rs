@@ -0,0 +1,279 @@
+use crate::{
+    export::{FrameConfig, FrameWriter},
+    random,
+    resources::Scenario,
+    simulation::{Metrics, Simulation},
+};
+use anyhow::{anyhow, Result};
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// Settings for `boids-batch`, loaded from `resources/sweeps`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SweepConfig {
+    /// Scenario file under `resources/scenarios` every run starts from.
+    pub scenario: String,
+    /// Species the flock is made of, and whose `BoidData` is swept.
+    pub species: String,
+    pub flock_size: usize,
+    /// Half the size of the area boids are spawned in, around the origin.
+    pub spawn_extents: Vector2<f32>,
+    /// Simulation ticks before the metrics are taken.
+    pub ticks: usize,
+    pub seeds: Vec<u64>,
+    /// Values tried for `BoidData` fields, by name. Every combination of them
+    /// is run once with every seed.
+    #[serde(default)]
+    pub parameters: BTreeMap<String, Vec<f32>>,
+    /// CSV file the results are written to, relative to the working directory.
+    pub output: String,
+    /// PNG frames drawn of every run, each in a directory named after it.
+    #[serde(default)]
+    pub frames: Option<FrameConfig>,
+}
+
+/// One simulation of a sweep.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SweepRun {
+    pub seed: u64,
+    /// Value of every swept parameter, in the order of `SweepConfig::parameters`.
+    pub parameters: Vec<(String, f32)>,
+}
+
+impl SweepRun {
+    /// Name telling the run apart from the others of its sweep, such as
+    /// `seed-1_cohesion_weight-0.5`.
+    pub fn name(&self) -> String {
+        let mut name = format!("seed-{}", self.seed);
+        for (parameter, value) in self.parameters.iter() {
+            name.push_str(&format!("_{}-{}", parameter, value));
+        }
+        name
+    }
+}
+
+impl SweepConfig {
+    /// Every combination of parameter values, for every seed.
+    pub fn runs(&self) -> Vec<SweepRun> {
+        let mut combinations = vec![vec![]];
+        for (name, values) in self.parameters.iter() {
+            combinations = combinations
+                .into_iter()
+                .flat_map(|combination: Vec<(String, f32)>| {
+                    values.iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.push((name.clone(), *value));
+                        combination
+                    })
+                })
+                .collect();
+        }
+
+        combinations
+            .into_iter()
+            .flat_map(|parameters| {
+                self.seeds.iter().map(move |seed| SweepRun {
+                    seed: *seed,
+                    parameters: parameters.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Runs a single simulation of the sweep on the current thread.
+pub fn run(config: &SweepConfig, scenario: &Scenario, run: &SweepRun) -> Result<Metrics> {
+    let mut species = scenario.species_named(&config.species)?.clone();
+    for (name, value) in run.parameters.iter() {
+        species.boid.set_parameter(name, *value)?;
+    }
+
+    random::seed(run.seed);
+    let mut simulation = Simulation::new(scenario.clone())?;
+    simulation.spawn(&species, config.flock_size, config.spawn_extents)?;
+    match &config.frames {
+        Some(frame_config) => {
+            let directory = Path::new(&frame_config.directory).join(run.name());
+            let mut frames = FrameWriter::create(frame_config, &directory)?;
+            frames.capture(&simulation.world, 0)?;
+            for tick in 1..=config.ticks {
+                simulation.step();
+                frames.capture(&simulation.world, tick)?;
+            }
+        }
+        None => simulation.run(config.ticks),
+    }
+    Ok(simulation.metrics())
+}
+
+/// Runs every simulation of the sweep, spread over `threads` threads, and
+/// returns their metrics in the order of `runs`. `on_finished` is called
+/// with the index of every run as it completes.
+pub fn run_sweep(
+    config: &SweepConfig,
+    scenario: &Scenario,
+    runs: &[SweepRun],
+    threads: usize,
+    on_finished: impl Fn(usize) + Sync,
+) -> Result<Vec<Metrics>> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; runs.len()]);
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= runs.len() {
+                    break;
+                }
+                let result = run(config, scenario, &runs[index]).map_err(|e| e.to_string());
+                results.lock().unwrap()[index] = Some(result);
+                on_finished(index);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .enumerate()
+        .map(|(index, result)| match result {
+            Some(Ok(metrics)) => Ok(metrics),
+            Some(Err(e)) => Err(anyhow!("Run {} failed: {}", index, e)),
+            None => Err(anyhow!("Run {} never finished", index)),
+        })
+        .collect()
+}
+
+/// Writes a header and then one row per run, with its seed, parameters and
+/// metrics.
+pub fn write_csv<W: Write>(mut writer: W, runs: &[SweepRun], results: &[Metrics]) -> Result<()> {
+    let parameter_names = runs
+        .first()
+        .map(|run| {
+            run.parameters
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect()
+        })
+        .unwrap_or_else(Vec::new);
+    let mut header = vec!["seed"];
+    header.extend(parameter_names);
+    header.extend(&[
+        "boids",
+        "polarization",
+        "milling",
+        "mean_speed",
+        "nearest_neighbour",
+    ]);
+    writeln!(writer, "{}", header.join(","))?;
+
+    for (run, metrics) in runs.iter().zip(results) {
+        let mut row = vec![run.seed.to_string()];
+        row.extend(run.parameters.iter().map(|(_, value)| value.to_string()));
+        row.extend(vec![
+            metrics.boids.to_string(),
+            metrics.polarization.to_string(),
+            metrics.milling.to_string(),
+            metrics.mean_speed.to_string(),
+            metrics.nearest_neighbour.to_string(),
+        ]);
+        writeln!(writer, "{}", row.join(","))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst::config::Config;
+
+    fn config() -> SweepConfig {
+        let mut parameters = BTreeMap::new();
+        parameters.insert("cohesion_weight".to_string(), vec![0.5, 1.0]);
+        parameters.insert("max_speed".to_string(), vec![100.0, 200.0, 300.0]);
+        SweepConfig {
+            scenario: "default.ron".to_string(),
+            species: "boid".to_string(),
+            flock_size: 5,
+            spawn_extents: Vector2::new(100.0, 100.0),
+            ticks: 3,
+            seeds: vec![1, 2],
+            parameters,
+            output: "sweep.csv".to_string(),
+            frames: None,
+        }
+    }
+
+    #[test]
+    fn runs_cover_every_combination_and_seed() {
+        let runs = config().runs();
+        assert_eq!(runs.len(), 2 * 3 * 2);
+        assert_eq!(
+            runs[0],
+            SweepRun {
+                seed: 1,
+                parameters: vec![
+                    ("cohesion_weight".to_string(), 0.5),
+                    ("max_speed".to_string(), 100.0)
+                ],
+            }
+        );
+        assert_eq!(runs[11].seed, 2);
+        assert_eq!(runs[11].parameters[0].1, 1.0);
+        assert_eq!(runs[11].parameters[1].1, 300.0);
+        assert_eq!(runs[11].name(), "seed-2_cohesion_weight-1_max_speed-300");
+
+        let mut empty = config();
+        empty.parameters.clear();
+        assert_eq!(empty.runs().len(), 2);
+    }
+
+    #[test]
+    fn parallel_sweep_matches_sequential_runs() {
+        let scenario =
+            Scenario::load_bytes(include_bytes!("../resources/scenarios/default.ron")).unwrap();
+        let config = config();
+        let runs = config.runs()[..4].to_vec();
+
+        let finished = AtomicUsize::new(0);
+        let results = run_sweep(&config, &scenario, &runs, 3, |_| {
+            finished.fetch_add(1, Ordering::Relaxed);
+        })
+        .unwrap();
+        assert_eq!(finished.into_inner(), 4);
+        for (sweep_run, metrics) in runs.iter().zip(results.iter()) {
+            assert_eq!(&run(&config, &scenario, sweep_run).unwrap(), metrics);
+        }
+
+        let mut csv = vec![];
+        write_csv(&mut csv, &runs, &results).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            "seed,cohesion_weight,max_speed,boids,polarization,milling,mean_speed,nearest_neighbour"
+        );
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].starts_with("1,0.5,100,5,"));
+    }
+
+    #[test]
+    fn unknown_parameters_are_rejected() {
+        let mut data = crate::flocking::tests::boid_data();
+        assert!(data.set_parameter("cohesion_weight", 3.0).is_ok());
+        assert_eq!(data.cohesion_weight, 3.0);
+        assert!(data.set_parameter("neighbourhood", 3.0).is_err());
+    }
+}
@@ -0,0 +1,39 @@
+//! Runs a parameter sweep without opening a window. Building it with
+//! `--no-default-features --features empty` leaves out the graphics backend.
+//! Sweeps with `frames` set also draw every run to numbered PNGs, in software.
+use amethyst::{config::Config, utils::application_root_dir};
+use anyhow::{anyhow, Result};
+use boids::{
+    batch::{run_sweep, write_csv, SweepConfig},
+    resources::Scenario,
+};
+use log::info;
+use std::{fs::File, io::BufWriter, thread};
+
+fn main() -> Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let sweep_name = std::env::args()
+        .nth(1)
+        .ok_or_else(|| anyhow!("Usage: boids-batch <file in resources/sweeps>"))?;
+    let resources = application_root_dir()?.join("resources");
+    let config = SweepConfig::load(resources.join("sweeps").join(sweep_name))?;
+    let mut scenario = Scenario::load(resources.join("scenarios").join(&config.scenario))?;
+    scenario.flow.load_grids(&resources.join("flows"))?;
+
+    let runs = config.runs();
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    info!("Running {} simulations on {} threads", runs.len(), threads);
+    let results = run_sweep(&config, &scenario, &runs, threads, |index| {
+        info!("Finished run {} of {}", index + 1, runs.len())
+    })?;
+
+    write_csv(
+        BufWriter::new(File::create(&config.output)?),
+        &runs,
+        &results,
+    )?;
+    info!("Wrote {} rows to {}", results.len(), config.output);
+
+    Ok(())
+}
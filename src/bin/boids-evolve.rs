@@ -0,0 +1,40 @@
+//! Evolves the `BoidData` of a species without opening a window, and writes
+//! the best genome found as a species preset.
+use amethyst::{config::Config, utils::application_root_dir};
+use anyhow::{anyhow, Result};
+use boids::{
+    evolution::{evolve, EvolutionConfig},
+    resources::Scenario,
+};
+use log::info;
+
+fn main() -> Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let config_name = std::env::args()
+        .nth(1)
+        .ok_or_else(|| anyhow!("Usage: boids-evolve <file in resources/evolution>"))?;
+    let resources = application_root_dir()?.join("resources");
+    let config = EvolutionConfig::load(resources.join("evolution").join(config_name))?;
+    let mut scenario = Scenario::load(resources.join("scenarios").join(&config.scenario))?;
+    scenario.flow.load_grids(&resources.join("flows"))?;
+
+    let (genome, fitness) = evolve(&config, &scenario, |generation, fitness| {
+        info!("Generation {}: best fitness {}", generation, fitness)
+    })?;
+
+    let mut preset = scenario.species_named(&config.species)?.clone();
+    preset.boid = genome;
+    let output = resources.join(&config.output);
+    if let Some(directory) = output.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    preset.write(&output)?;
+    info!(
+        "Wrote the best genome, with fitness {}, to {}",
+        fitness,
+        output.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use crate::resources::AnimationConfig;
+use amethyst::ecs::{Component, DenseVecStorage};
+use nalgebra::Vector2;
+use std::f32::consts::PI;
+
+/// Cycles a boid's sprite through its frames, faster the faster the boid
+/// flies and the harder it turns.
+#[derive(Clone, Debug, PartialEq, Component)]
+#[storage(DenseVecStorage)]
+pub struct SpriteAnimation {
+    frames: Vec<usize>,
+    config: AnimationConfig,
+    /// How far through its cycle of frames the boid is, from 0 to 1.
+    phase: f32,
+    /// Direction the boid was heading in when last advanced, in radians.
+    heading: Option<f32>,
+}
+
+impl SpriteAnimation {
+    /// Starts `phase` of the way through `frames`, which mustn't be empty.
+    pub fn new(frames: Vec<usize>, config: AnimationConfig, phase: f32) -> SpriteAnimation {
+        SpriteAnimation {
+            frames,
+            config,
+            phase: phase.rem_euclid(1.0),
+            heading: None,
+        }
+    }
+
+    /// The frame of the sprite sheet to draw.
+    pub fn frame(&self) -> usize {
+        let index = (self.phase * self.frames.len() as f32) as usize;
+        self.frames[index.min(self.frames.len() - 1)]
+    }
+
+    /// Moves on by `delta_seconds` of flying at `velocity`, and returns the
+    /// frame to draw.
+    pub fn advance(&mut self, velocity: Vector2<f32>, max_speed: f32, delta_seconds: f32) -> usize {
+        let speed = if max_speed > 0.0 {
+            velocity.norm() / max_speed
+        } else {
+            0.0
+        };
+        // Boids standing still keep the heading they last had
+        let heading = if velocity.norm() > f32::EPSILON {
+            Some(velocity.y.atan2(velocity.x))
+        } else {
+            self.heading
+        };
+        let turn_rate = match (self.heading, heading) {
+            (Some(last), Some(heading)) if delta_seconds > 0.0 => {
+                ((heading - last + PI).rem_euclid(2.0 * PI) - PI).abs() / delta_seconds
+            }
+            _ => 0.0,
+        };
+        self.heading = heading;
+
+        let rate = self.config.speed_rate * speed + self.config.turn_rate * turn_rate;
+        self.phase = (self.phase + rate * delta_seconds).rem_euclid(1.0);
+        self.frame()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_go_by_with_speed_and_turning() {
+        let config = AnimationConfig {
+            speed_rate: 2.0,
+            turn_rate: 0.0,
+        };
+        let mut animation = SpriteAnimation::new(vec![0, 1, 2, 1], config, 0.0);
+        assert_eq!(animation.frame(), 0);
+        // Two cycles a second at top speed, so a quarter of one in 1/8 s
+        assert_eq!(animation.advance(Vector2::new(10.0, 0.0), 10.0, 0.125), 1);
+        // Half as fast at half speed
+        assert_eq!(animation.advance(Vector2::new(5.0, 0.0), 10.0, 0.25), 2);
+        // Nothing goes by while standing still
+        assert_eq!(animation.advance(Vector2::zeros(), 10.0, 1.0), 2);
+
+        let config = AnimationConfig {
+            speed_rate: 0.0,
+            turn_rate: 0.3,
+        };
+        let mut animation = SpriteAnimation::new(vec![0, 1, 2, 3], config, 0.0);
+        animation.advance(Vector2::new(0.0, 1.0), 10.0, 1.0);
+        // Turning half a circle in a second goes 0.3π of a cycle on
+        assert_eq!(animation.advance(Vector2::new(0.0, -1.0), 10.0, 1.0), 3);
+        // Across the -x axis is a small turn, not nearly a full one
+        let mut animation = SpriteAnimation::new(vec![0, 1, 2, 3], config, 0.0);
+        animation.advance(Vector2::new(-1.0, 0.1), 10.0, 1.0);
+        assert_eq!(animation.advance(Vector2::new(-1.0, -0.1), 10.0, 1.0), 0);
+
+        let animation = SpriteAnimation::new(vec![4, 5], config, 1.75);
+        assert_eq!(animation.frame(), 5);
+    }
+}
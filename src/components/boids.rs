@@ -1,6 +1,57 @@
+use crate::{
+    flocking::{CouzinParams, FlockingModel, Reynolds, VicsekParams},
+    spatial::Shape,
+};
 use amethyst::ecs::{Component, DenseVecStorage};
+use anyhow::{anyhow, ensure, Result};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Component)]
+/// Closest fraction of the radius the inverse falloffs are evaluated at, so
+/// that they stay bounded as the distance goes to zero.
+const MIN_FRACTION: f32 = 0.1;
+
+/// How strongly a repulsion acts as a function of the distance to what it is
+/// avoiding, relative to the radius it acts within. Every falloff reaches zero
+/// at the edge of the radius.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Falloff {
+    /// Decreases linearly.
+    Linear,
+    /// Inversely proportional to the distance, less what that is at the edge.
+    Inverse,
+    /// Inversely proportional to the square of the distance, less what that
+    /// is at the edge.
+    InverseSquare,
+}
+
+impl Falloff {
+    /// Strength factor for a distance expressed as a fraction of the radius.
+    /// The inverse falloffs level off within a tenth of the radius, at 9 and
+    /// 99 times the strength of the linear one at zero.
+    pub fn factor(self, fraction: f32) -> f32 {
+        let clamped = fraction.max(MIN_FRACTION);
+        match self {
+            Falloff::Linear => 1.0 - fraction,
+            Falloff::Inverse => 1.0 / clamped - 1.0,
+            Falloff::InverseSquare => 1.0 / (clamped * clamped) - 1.0,
+        }
+    }
+}
+
+/// Which other boids count as neighbours for separation, alignment and cohesion.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Neighbourhood {
+    /// Every boid within the rule's radius.
+    Metric,
+    /// The given number of nearest boids, however far away they are. Starlings
+    /// have been observed to track around seven.
+    Topological(usize),
+    /// Boids whose Voronoi cells touch this boid's, i.e. those sharing an edge
+    /// with it in the Delaunay triangulation of all boids.
+    Voronoi,
+}
+
+#[derive(Clone, Debug, PartialEq, Component, Serialize, Deserialize)]
 #[storage(DenseVecStorage)]
 pub struct BoidData {
     pub separation_weight: f32,
@@ -10,12 +61,142 @@ pub struct BoidData {
     pub separation_radius: f32,
     pub alignment_radius: f32,
     pub cohesion_radius: f32,
+    pub separation_falloff: Falloff,
+    pub neighbourhood: Neighbourhood,
     pub max_speed: f32,
+    /// How far ahead the boid looks for solid obstacles, in seconds of travel
+    /// at its current speed.
+    pub look_ahead: f32,
+}
+
+impl BoidData {
+    /// Checks every number is finite, the top speed positive and the radii
+    /// and look-ahead not negative, as spawning and steering rely on.
+    pub fn validate(&self) -> Result<()> {
+        let numbers = [
+            ("separation_weight", self.separation_weight),
+            ("alignment_weight", self.alignment_weight),
+            ("cohesion_weight", self.cohesion_weight),
+            ("noise_weight", self.noise_weight),
+            ("separation_radius", self.separation_radius),
+            ("alignment_radius", self.alignment_radius),
+            ("cohesion_radius", self.cohesion_radius),
+            ("max_speed", self.max_speed),
+            ("look_ahead", self.look_ahead),
+        ];
+        for (name, value) in numbers.iter() {
+            ensure!(
+                value.is_finite(),
+                "{} is {}, but has to be finite",
+                name,
+                value
+            );
+        }
+        ensure!(
+            self.max_speed > 0.0,
+            "max_speed is {}, but has to be positive",
+            self.max_speed
+        );
+        for (name, value) in numbers[4..].iter() {
+            ensure!(
+                *value >= 0.0,
+                "{} is {}, but can't be negative",
+                name,
+                value
+            );
+        }
+        Ok(())
+    }
+
+    /// Sets the numeric field called `name`, unless that would leave it
+    /// invalid.
+    pub fn set_parameter(&mut self, name: &str, value: f32) -> Result<()> {
+        let mut changed = self.clone();
+        let field = match name {
+            "separation_weight" => &mut changed.separation_weight,
+            "alignment_weight" => &mut changed.alignment_weight,
+            "cohesion_weight" => &mut changed.cohesion_weight,
+            "noise_weight" => &mut changed.noise_weight,
+            "separation_radius" => &mut changed.separation_radius,
+            "alignment_radius" => &mut changed.alignment_radius,
+            "cohesion_radius" => &mut changed.cohesion_radius,
+            "max_speed" => &mut changed.max_speed,
+            "look_ahead" => &mut changed.look_ahead,
+            _ => return Err(anyhow!("{} is not a numeric BoidData parameter", name)),
+        };
+        *field = value;
+        changed.validate()?;
+        *self = changed;
+        Ok(())
+    }
+}
+
+/// Flocking model a boid follows. Boids without one follow Reynolds' rules.
+#[derive(Clone, Debug, Default, PartialEq, Component, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub enum Flocking {
+    #[default]
+    Reynolds,
+    Couzin(CouzinParams),
+    Vicsek(VicsekParams),
+}
+
+impl Flocking {
+    pub fn model(&self) -> &dyn FlockingModel {
+        match self {
+            Flocking::Reynolds => &Reynolds,
+            Flocking::Couzin(params) => params,
+            Flocking::Vicsek(params) => params,
+        }
+    }
+
+    /// Checks the model's parameters, if it has any.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Flocking::Reynolds => Ok(()),
+            Flocking::Couzin(params) => params.validate(),
+            Flocking::Vicsek(params) => params.validate(),
+        }
+    }
 }
 
+/// Name of the scenario species a boid was spawned as.
+#[derive(Clone, Debug, PartialEq, Component)]
+#[storage(DenseVecStorage)]
+pub struct Species(pub String);
+
 #[derive(Debug, Component)]
 #[storage(DenseVecStorage)]
 pub struct ObstacleData {
     pub separation_weight: f32,
     pub separation_radius: f32,
+    pub separation_falloff: Falloff,
+}
+
+/// Solid obstacle that boids steer around and can never pass through.
+#[derive(Clone, Debug, Component)]
+#[storage(DenseVecStorage)]
+pub struct ObstacleShape {
+    pub shape: Shape,
+    /// Strength of the push along the surface normal once the obstacle is
+    /// within a boid's look-ahead.
+    pub avoidance_weight: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flocking::tests::boid_data;
+
+    #[test]
+    fn parameters_that_would_break_boids_are_refused() {
+        let mut data = boid_data();
+        data.validate().unwrap();
+        assert!(data.set_parameter("max_speed", 0.0).is_err());
+        assert!(data.set_parameter("cohesion_radius", -1.0).is_err());
+        assert!(data.set_parameter("noise_weight", f32::NAN).is_err());
+        assert_eq!(data, boid_data());
+
+        data.set_parameter("separation_weight", -2.0).unwrap();
+        assert_eq!(data.separation_weight, -2.0);
+    }
 }
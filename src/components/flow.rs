@@ -0,0 +1,14 @@
+use amethyst::ecs::{Component, DenseVecStorage};
+use serde::{Deserialize, Serialize};
+
+/// How a boid is affected by the `FlowField`.
+#[derive(Clone, Debug, PartialEq, Component, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub struct FlowResponse {
+    /// Fraction of the flow's velocity the boid drifts with, on top of its
+    /// own.
+    pub susceptibility: f32,
+    /// Steering to head into the flow, as birds do before landing.
+    #[serde(default)]
+    pub upwind_weight: f32,
+}
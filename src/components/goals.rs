@@ -0,0 +1,116 @@
+use amethyst::ecs::{Component, DenseVecStorage, NullStorage};
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+
+/// A point boids with `GoalData` are drawn towards.
+#[derive(Debug, Default, Component)]
+#[storage(NullStorage)]
+pub struct Attractor;
+
+/// Weights for the goal-directed steering terms of a boid.
+#[derive(Clone, Debug, PartialEq, Component, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub struct GoalData {
+    /// Pull towards the nearest attractor at full speed.
+    pub seek_weight: f32,
+    /// Pull towards the nearest attractor, slowing down once within
+    /// `slowing_radius` of it.
+    pub arrive_weight: f32,
+    pub slowing_radius: f32,
+    /// Pull towards the next waypoint of the boid's `PathFollower`.
+    pub path_weight: f32,
+}
+
+/// An ordered list of waypoints.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Path {
+    pub waypoints: Vec<Vector2<f32>>,
+    /// Whether to start over from the first waypoint after the last one,
+    /// rather than stopping there.
+    #[serde(default)]
+    pub looped: bool,
+    /// How close a boid has to get to a waypoint before moving on to the next.
+    pub waypoint_radius: f32,
+}
+
+/// Progress of a boid along a `Path`.
+#[derive(Clone, Debug, Component)]
+#[storage(DenseVecStorage)]
+pub struct PathFollower {
+    pub path: Path,
+    pub next_waypoint: usize,
+}
+
+impl PathFollower {
+    pub fn new(path: Path) -> PathFollower {
+        PathFollower {
+            path,
+            next_waypoint: 0,
+        }
+    }
+
+    /// Moves on to the following waypoint if `position` has reached the
+    /// current one.
+    pub fn advance(&mut self, position: Vector2<f32>) {
+        let waypoint = match self.path.waypoints.get(self.next_waypoint) {
+            Some(waypoint) => waypoint,
+            None => return,
+        };
+        if (waypoint - position).norm() > self.path.waypoint_radius {
+            return;
+        }
+
+        if self.next_waypoint + 1 < self.path.waypoints.len() {
+            self.next_waypoint += 1;
+        } else if self.path.looped {
+            self.next_waypoint = 0;
+        }
+    }
+
+    /// Waypoint currently being headed for, and whether the boid should come
+    /// to a stop there because it's the end of the path.
+    pub fn target(&self) -> Option<(Vector2<f32>, bool)> {
+        let is_last = !self.path.looped && self.next_waypoint + 1 == self.path.waypoints.len();
+        self.path
+            .waypoints
+            .get(self.next_waypoint)
+            .map(|waypoint| (*waypoint, is_last))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn follower(looped: bool) -> PathFollower {
+        PathFollower::new(Path {
+            waypoints: vec![Vector2::new(0.0, 0.0), Vector2::new(100.0, 0.0)],
+            looped,
+            waypoint_radius: 10.0,
+        })
+    }
+
+    #[test]
+    fn advances_only_when_waypoint_reached() {
+        let mut follower = follower(false);
+        follower.advance(Vector2::new(50.0, 0.0));
+        assert_eq!(follower.target(), Some((Vector2::new(0.0, 0.0), false)));
+
+        follower.advance(Vector2::new(5.0, 5.0));
+        assert_eq!(follower.target(), Some((Vector2::new(100.0, 0.0), true)));
+
+        // Stays on the last waypoint of an open path
+        follower.advance(Vector2::new(100.0, 0.0));
+        assert_eq!(follower.target(), Some((Vector2::new(100.0, 0.0), true)));
+    }
+
+    #[test]
+    fn looped_path_wraps_around() {
+        let mut follower = follower(true);
+        follower.advance(Vector2::new(0.0, 0.0));
+        assert_eq!(follower.target(), Some((Vector2::new(100.0, 0.0), false)));
+
+        follower.advance(Vector2::new(100.0, 0.0));
+        assert_eq!(follower.target(), Some((Vector2::new(0.0, 0.0), false)));
+    }
+}
@@ -0,0 +1,33 @@
+use amethyst::ecs::{Component, DenseVecStorage, NullStorage};
+use serde::{Deserialize, Serialize};
+
+/// Boid that followers line up behind. Leaders ignore their own follower
+/// term, steering only by their flocking model, goals and player control.
+#[derive(Debug, Default, Component)]
+#[storage(NullStorage)]
+pub struct Leader;
+
+/// Boid picked with the mouse, which leadership toggling and player control
+/// act on.
+#[derive(Debug, Default, Component)]
+#[storage(NullStorage)]
+pub struct Selected;
+
+/// How a boid follows the nearest leader.
+#[derive(Clone, Debug, PartialEq, Component, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub struct FollowerData {
+    /// Pull towards the point `behind_distance` behind the leader, slowing
+    /// down within `slowing_radius` of it.
+    pub follow_weight: f32,
+    pub behind_distance: f32,
+    pub slowing_radius: f32,
+    /// Sideways push out of the stretch of the leader's path that lies up to
+    /// `evade_distance` ahead of it, within `evade_distance / 2` either side.
+    pub evade_weight: f32,
+    pub evade_distance: f32,
+    /// Push away from other followers, on top of the flocking model's own
+    /// separation.
+    pub separation_weight: f32,
+    pub separation_radius: f32,
+}
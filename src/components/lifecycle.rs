@@ -0,0 +1,48 @@
+use amethyst::ecs::{Component, DenseVecStorage, VecStorage};
+use serde::{Deserialize, Serialize};
+
+/// Energy a boid has left. It starves once this reaches zero.
+#[derive(Debug, Component)]
+#[storage(VecStorage)]
+pub struct Energy(pub f32);
+
+/// Seconds since a boid was spawned.
+#[derive(Debug, Default, Component)]
+#[storage(VecStorage)]
+pub struct Age(pub f32);
+
+/// Seconds a boid lives before it's removed, however much energy it has.
+#[derive(Debug, Component)]
+#[storage(DenseVecStorage)]
+pub struct Lifespan(pub f32);
+
+/// How a boid spends, gains and passes on `Energy`.
+#[derive(Clone, Debug, PartialEq, Component, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub struct Metabolism {
+    /// Energy a boid is spawned with.
+    pub initial_energy: f32,
+    /// Energy spent every second just staying alive.
+    pub idle_drain: f32,
+    /// Extra energy spent every second for each unit of speed.
+    pub speed_drain: f32,
+    /// Most energy a boid can take from food every second.
+    pub eat_rate: f32,
+    /// Energy above which a boid splits off an offspring.
+    pub reproduction_threshold: f32,
+    /// Energy the parent hands over to each offspring.
+    pub offspring_energy: f32,
+}
+
+/// Energy source boids feed from by flying within `radius` of it.
+#[derive(Clone, Debug, PartialEq, Component, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub struct Food {
+    /// Energy left to be eaten.
+    pub amount: f32,
+    /// Most energy the source can hold.
+    pub capacity: f32,
+    /// Energy the source grows back every second.
+    pub regrowth: f32,
+    pub radius: f32,
+}
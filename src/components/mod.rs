@@ -1,5 +1,21 @@
+mod animation;
 mod boids;
+mod flow;
+mod goals;
+mod leaders;
+mod lifecycle;
 mod physics;
+mod scripts;
+mod trails;
 
-pub use self::boids::{BoidData, ObstacleData};
+pub use self::animation::SpriteAnimation;
+pub use self::boids::{
+    BoidData, Falloff, Flocking, Neighbourhood, ObstacleData, ObstacleShape, Species,
+};
+pub use self::flow::FlowResponse;
+pub use self::goals::{Attractor, GoalData, Path, PathFollower};
+pub use self::leaders::{FollowerData, Leader, Selected};
+pub use self::lifecycle::{Age, Energy, Food, Lifespan, Metabolism};
 pub use self::physics::{Position, Velocity};
+pub use self::scripts::{ScriptRule, ScriptedSteering};
+pub use self::trails::Trail;
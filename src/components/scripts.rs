@@ -0,0 +1,23 @@
+use amethyst::ecs::{Component, DenseVecStorage};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Steering rule defined by the `steer` function of a Rhai script in
+/// `resources/scripts`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScriptRule {
+    /// File name of the script.
+    pub script: String,
+    pub weight: f32,
+    /// Distance within which other boids are passed to the script as
+    /// neighbours.
+    pub radius: f32,
+    /// Passed to the script as its `params` map.
+    #[serde(default)]
+    pub params: BTreeMap<String, f32>,
+}
+
+/// Scripted rules a boid steers by on top of its flocking model.
+#[derive(Clone, Debug, PartialEq, Component, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub struct ScriptedSteering(pub Vec<ScriptRule>);
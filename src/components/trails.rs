@@ -0,0 +1,81 @@
+use crate::resources::{TrailSampling, TrailSettings};
+use amethyst::ecs::{Component, DenseVecStorage};
+use nalgebra::Vector2;
+use std::collections::VecDeque;
+
+/// Where a boid has recently been, oldest first, as sampled by
+/// `TrailSettings`.
+#[derive(Clone, Debug, Default, PartialEq, Component)]
+#[storage(DenseVecStorage)]
+pub struct Trail {
+    points: VecDeque<Vector2<f32>>,
+    /// Seconds since the last position was taken.
+    since_sampled: f32,
+}
+
+impl Trail {
+    pub fn points(&self) -> impl Iterator<Item = &Vector2<f32>> + '_ {
+        self.points.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Takes in `position`, `delta_seconds` after the last call, if it's due
+    /// by `settings`. The oldest positions are dropped once there are more
+    /// than `settings` allow.
+    pub fn sample(&mut self, position: Vector2<f32>, delta_seconds: f32, settings: &TrailSettings) {
+        self.since_sampled += delta_seconds;
+        let due = match (self.points.back(), settings.sampling) {
+            (None, _) => true,
+            (Some(last), TrailSampling::Distance(distance)) => (position - last).norm() >= distance,
+            (Some(_), TrailSampling::Time(interval)) => self.since_sampled >= interval,
+        };
+        if !due {
+            return;
+        }
+
+        self.points.push_back(position);
+        self.since_sampled = 0.0;
+        while self.points.len() > settings.length {
+            self.points.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trails_keep_the_latest_samples() {
+        let settings = TrailSettings {
+            enabled: true,
+            length: 3,
+            sampling: TrailSampling::Distance(10.0),
+        };
+        let mut trail = Trail::default();
+        for x in 0..10 {
+            trail.sample(Vector2::new(x as f32 * 4.0, 0.0), 0.1, &settings);
+        }
+        // Taken at 0, 12, 24 and 36, of which the first is dropped
+        let xs = trail.points().map(|p| p.x).collect::<Vec<_>>();
+        assert_eq!(xs, vec![12.0, 24.0, 36.0]);
+
+        let settings = TrailSettings {
+            sampling: TrailSampling::Time(0.25),
+            ..settings
+        };
+        let mut trail = Trail::default();
+        for step in 0..6 {
+            trail.sample(Vector2::new(step as f32, 0.0), 0.1, &settings);
+        }
+        let xs = trail.points().map(|p| p.x).collect::<Vec<_>>();
+        assert_eq!(xs, vec![0.0, 3.0]);
+    }
+}
@@ -1,78 +1,191 @@
 use crate::{
-    components::{BoidData, ObstacleData, Position, Velocity},
-    resources::{SpriteCache, SpriteKey},
+    components::{
+        Age, Energy, Falloff, Leader, Lifespan, ObstacleData, ObstacleShape, PathFollower,
+        Position, ScriptedSteering, Species, Velocity,
+    },
+    random,
+    resources::{
+        validate_half_extents, Scenario, SpawnArea, SpawnRegion, SpeciesConfig, SpriteRegistry,
+    },
 };
 use amethyst::{
     core::transform::Transform,
-    ecs::{Entities, Entity, LazyUpdate, Read},
+    ecs::{Entities, Entity, LazyUpdate, Read, ReadExpect},
     prelude::*,
-    renderer::{SpriteRender, Transparent},
+    renderer::Transparent,
 };
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use nalgebra::Vector2;
-use rand::{
-    distributions::{Distribution, Uniform},
-    thread_rng,
-};
+use rand::distributions::{Distribution, Uniform};
 
-pub fn fill_boid<'s>(
-    entities: &Entities<'s>,
-    sprite_cache: &Read<SpriteCache>,
+/// Queues a boid of `species` for creation. Boids only get a sprite when one
+/// has been loaded, so headless simulations can spawn them too.
+pub fn fill_boid(
+    entities: &Entities,
+    sprite_registry: &SpriteRegistry,
     updater: &LazyUpdate,
     start_pos: Vector2<f32>,
+    scenario: &Scenario,
+    species: &SpeciesConfig,
 ) -> Result<Entity> {
-    let boid_handle = sprite_cache.fetch(SpriteKey::Boid)?.clone();
-    const MAX_VEL: f32 = 500.;
+    let path = scenario.path(species)?;
 
-    Ok(updater
+    let mut builder = updater
         .create_entity(entities)
-        .with(SpriteRender {
-            sprite_sheet: boid_handle,
-            sprite_number: 0,
-        })
         .with(Position(start_pos))
-        .with(Velocity(get_boid_vel(MAX_VEL)))
+        .with(Velocity(get_boid_vel(species.boid.max_speed)))
         .with(Transform::default())
         .with(Transparent)
-        .with(BoidData {
-            separation_weight: 0.1,
-            alignment_weight: 0.02,
-            cohesion_weight: 1.0,
-            noise_weight: 0.1,
-            separation_radius: 75.,
-            alignment_radius: 150.,
-            cohesion_radius: 150.,
-            max_speed: MAX_VEL,
-        })
-        .build())
+        .with(species.boid.clone())
+        .with(scenario.model(species))
+        .with(Species(species.name.clone()))
+        .with(Age::default());
+    if let Some(sprite) = sprite_registry.fetch_or_default(&species.sprite) {
+        builder = builder.with(sprite.render());
+        // Boids start at random points of the animation so they don't all
+        // flap together
+        let phase = Uniform::new(0.0, 1.0).sample(&mut random::rng());
+        if let Some(animation) = sprite.animation(phase) {
+            builder = builder.with(animation);
+        }
+    }
+    if let Some(goals) = &species.goals {
+        builder = builder.with(goals.clone());
+    }
+    if let Some(path) = path {
+        builder = builder.with(PathFollower::new(path.clone()));
+    }
+    if species.leader {
+        builder = builder.with(Leader);
+    }
+    if let Some(follower) = &species.follower {
+        builder = builder.with(follower.clone());
+    }
+    if let Some(flow) = &species.flow {
+        builder = builder.with(flow.clone());
+    }
+    if !species.scripts.is_empty() {
+        builder = builder.with(ScriptedSteering(species.scripts.clone()));
+    }
+    if let Some(lifespan) = species.lifespan {
+        builder = builder.with(Lifespan(lifespan));
+    }
+    if let Some(metabolism) = &species.metabolism {
+        builder = builder
+            .with(Energy(metabolism.initial_energy))
+            .with(metabolism.clone());
+    }
+    Ok(builder.build())
 }
 
-pub fn new_obstacle(world: &mut World, start_pos: Vector2<f32>) -> Result<Entity> {
-    let obstacle_handle = {
-        let sprite_cache = world
-            .try_fetch::<SpriteCache>()
-            .ok_or_else(|| anyhow!("Failed to fetch the sprite cache while crating player."))?;
-        sprite_cache.fetch(SpriteKey::Obstacle)?.clone()
+/// Queues the boids of `region` for creation, with `world_extents` as the
+/// half extents of the whole world.
+pub fn spawn_region(
+    entities: &Entities,
+    sprite_registry: &SpriteRegistry,
+    updater: &LazyUpdate,
+    scenario: &Scenario,
+    region: &SpawnRegion,
+    world_extents: Vector2<f32>,
+) -> Result<()> {
+    let species = scenario.region_species(region)?;
+    region.validate()?;
+    if region.area == SpawnArea::World {
+        validate_half_extents(world_extents)?;
+    }
+    let mut rng = random::rng();
+    for _ in 0..region.count {
+        let (position, velocity) = region.sample(&mut rng, world_extents, species.boid.max_speed);
+        let entity = fill_boid(
+            entities,
+            sprite_registry,
+            updater,
+            position,
+            scenario,
+            species,
+        )?;
+        updater.insert(entity, Velocity(velocity));
+    }
+    Ok(())
+}
+
+/// Queues `count` boids of `species` for creation, placed uniformly within
+/// `half_extents` of the origin.
+pub fn spawn_boids(
+    world: &World,
+    species: &SpeciesConfig,
+    count: usize,
+    half_extents: Vector2<f32>,
+) -> Result<()> {
+    let (entities, lazy_update, sprite_registry, scenario) = world.system_data::<(
+        Entities,
+        Read<LazyUpdate>,
+        Read<SpriteRegistry>,
+        ReadExpect<Scenario>,
+    )>();
+    let region = SpawnRegion {
+        area: SpawnArea::World,
+        count,
+        species: Some(species.name.clone()),
+        ..SpawnRegion::default()
     };
+    spawn_region(
+        &entities,
+        &sprite_registry,
+        &lazy_update,
+        &scenario,
+        &region,
+        half_extents,
+    )
+}
 
-    Ok(world
+/// Places a point obstacle drawn with the sprite called `sprite`, or the
+/// default sprite if there's none by that name, pushing boids away with
+/// `separation_weight`.
+pub fn new_obstacle(
+    world: &mut World,
+    start_pos: Vector2<f32>,
+    sprite: &str,
+    separation_weight: f32,
+) -> Entity {
+    let render = world
+        .try_fetch::<SpriteRegistry>()
+        .and_then(|sprite_registry| {
+            sprite_registry
+                .fetch_or_default(sprite)
+                .map(|sprite| sprite.render())
+        });
+
+    let mut builder = world
         .create_entity()
-        .with(SpriteRender {
-            sprite_sheet: obstacle_handle,
-            sprite_number: 0,
-        })
         .with(Position(start_pos))
         .with(Transform::default())
         .with(Transparent)
         .with(ObstacleData {
-            separation_weight: 0.2,
+            separation_weight,
             separation_radius: 200.,
-        })
-        .build())
+            separation_falloff: Falloff::Inverse,
+        });
+    if let Some(render) = render {
+        builder = builder.with(render);
+    }
+    builder.build()
+}
+
+pub fn new_solid_obstacle(
+    world: &mut World,
+    position: Vector2<f32>,
+    obstacle_shape: ObstacleShape,
+) -> Entity {
+    world
+        .create_entity()
+        .with(Position(position))
+        .with(obstacle_shape)
+        .build()
 }
 
 fn get_boid_vel(max_vel: f32) -> Vector2<f32> {
-    let mut rng = thread_rng();
+    let mut rng = random::rng();
     let dir_dist = Uniform::new(0.0, 2. * std::f32::consts::PI);
     let vel_dist = Uniform::new(0.0, max_vel);
     let (dir, vel) = (dir_dist.sample(&mut rng), vel_dist.sample(&mut rng));
@@ -0,0 +1,165 @@
+use crate::{
+    components::{BoidData, ObstacleData, ObstacleShape, Position, Species},
+    entities::scenario::world_extents,
+    resources::SpawnArea,
+};
+use amethyst::ecs::{prelude::*, Entities, Entity, LazyUpdate, ReadStorage};
+
+/// What a despawn removes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DespawnTarget {
+    Entity(Entity),
+    /// Every boid and obstacle within the area.
+    Region(SpawnArea),
+    /// Every boid of the species with this name.
+    Species(String),
+    /// Every boid, leaving obstacles in place.
+    Boids,
+    /// Every boid and obstacle.
+    All,
+}
+
+/// Queues the removal of `target` for when the world is next maintained, so it
+/// can be called from any system mid-frame.
+pub fn despawn(updater: &LazyUpdate, target: DespawnTarget) {
+    updater.exec_mut(move |world| {
+        despawn_now(world, &target);
+    });
+}
+
+/// Removes `target` straight away, returning how many entities went.
+pub fn despawn_now(world: &mut World, target: &DespawnTarget) -> usize {
+    let doomed = {
+        let world_extents = world_extents(world);
+        let (entities, boid_datas, obstacle_datas, obstacle_shapes, positions, species) = world
+            .system_data::<(
+                Entities,
+                ReadStorage<BoidData>,
+                ReadStorage<ObstacleData>,
+                ReadStorage<ObstacleShape>,
+                ReadStorage<Position>,
+                ReadStorage<Species>,
+            )>();
+        let is_boid = |entity| boid_datas.contains(entity);
+        let is_obstacle =
+            |entity| obstacle_datas.contains(entity) || obstacle_shapes.contains(entity);
+
+        (&entities, positions.maybe(), species.maybe())
+            .join()
+            .filter(|(entity, position, boid_species)| match target {
+                DespawnTarget::Entity(target) => entity == target,
+                DespawnTarget::Region(area) => {
+                    (is_boid(*entity) || is_obstacle(*entity))
+                        && position.map_or(false, |p| area.contains(p.0, world_extents))
+                }
+                DespawnTarget::Species(name) => {
+                    is_boid(*entity) && boid_species.map_or(false, |s| &s.0 == name)
+                }
+                DespawnTarget::Boids => is_boid(*entity),
+                DespawnTarget::All => is_boid(*entity) || is_obstacle(*entity),
+            })
+            .map(|(entity, _, _)| entity)
+            .collect::<Vec<_>>()
+    };
+
+    // Entities deleted earlier in the frame can't be deleted twice
+    let doomed = doomed
+        .into_iter()
+        .filter(|entity| world.is_alive(*entity))
+        .collect::<Vec<_>>();
+    world
+        .delete_entities(&doomed)
+        .expect("only live entities are deleted");
+    doomed.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        resources::{Scenario, SpawnRegion},
+        simulation::Simulation,
+    };
+    use amethyst::config::Config;
+    use nalgebra::Vector2;
+
+    fn simulation() -> Simulation<'static, 'static> {
+        let mut scenario =
+            Scenario::load_bytes(include_bytes!("../../resources/scenarios/murmuration.ron"))
+                .unwrap();
+        scenario.spawns = vec![
+            SpawnRegion {
+                area: SpawnArea::Circle {
+                    center: Vector2::new(-300.0, 0.0),
+                    radius: 50.0,
+                },
+                count: 10,
+                ..SpawnRegion::default()
+            },
+            SpawnRegion {
+                area: SpawnArea::Circle {
+                    center: Vector2::new(300.0, 0.0),
+                    radius: 50.0,
+                },
+                count: 5,
+                species: Some("falcon".to_string()),
+                ..SpawnRegion::default()
+            },
+        ];
+        Simulation::new(scenario).unwrap()
+    }
+
+    fn obstacles(simulation: &Simulation) -> usize {
+        simulation.world.read_storage::<ObstacleShape>().count()
+    }
+
+    #[test]
+    fn despawning_by_species_and_region() {
+        let mut simulation = simulation();
+        let walls = obstacles(&simulation);
+
+        let area = SpawnArea::Circle {
+            center: Vector2::new(-300.0, 0.0),
+            radius: 60.0,
+        };
+        assert_eq!(
+            despawn_now(&mut simulation.world, &DespawnTarget::Region(area)),
+            10
+        );
+        assert_eq!(simulation.count("starling"), 0);
+
+        let falcons = DespawnTarget::Species("falcon".to_string());
+        assert_eq!(despawn_now(&mut simulation.world, &falcons), 5);
+        assert_eq!(simulation.count("falcon"), 0);
+        assert_eq!(obstacles(&simulation), walls);
+    }
+
+    #[test]
+    fn lazy_despawns_apply_on_maintain() {
+        let mut simulation = simulation();
+        let boid = {
+            let (entities, boid_datas) = simulation
+                .world
+                .system_data::<(Entities, ReadStorage<BoidData>)>();
+            (&entities, &boid_datas).join().next().unwrap().0
+        };
+
+        despawn(
+            &simulation.world.read_resource(),
+            DespawnTarget::Entity(boid),
+        );
+        assert!(simulation.world.is_alive(boid));
+        simulation.world.maintain();
+        assert!(!simulation.world.is_alive(boid));
+        assert_eq!(simulation.count("starling"), 9);
+
+        despawn(&simulation.world.read_resource(), DespawnTarget::Boids);
+        simulation.world.maintain();
+        assert_eq!(simulation.count("starling") + simulation.count("falcon"), 0);
+        assert!(obstacles(&simulation) > 0);
+
+        despawn(&simulation.world.read_resource(), DespawnTarget::All);
+        simulation.world.maintain();
+        assert_eq!(obstacles(&simulation), 0);
+    }
+}
@@ -0,0 +1,18 @@
+use crate::{
+    components::{Food, Position},
+    resources::FoodConfig,
+};
+use amethyst::{ecs::Entity, prelude::*};
+
+pub fn new_food(world: &mut World, config: &FoodConfig) -> Entity {
+    world
+        .create_entity()
+        .with(Position(config.position))
+        .with(Food {
+            amount: config.capacity,
+            capacity: config.capacity,
+            regrowth: config.regrowth,
+            radius: config.radius,
+        })
+        .build()
+}
@@ -0,0 +1,22 @@
+use crate::components::{Attractor, Position};
+use amethyst::{
+    ecs::{Entities, Entity, LazyUpdate},
+    prelude::*,
+};
+use nalgebra::Vector2;
+
+pub fn fill_attractor(entities: &Entities, updater: &LazyUpdate, pos: Vector2<f32>) -> Entity {
+    updater
+        .create_entity(entities)
+        .with(Position(pos))
+        .with(Attractor)
+        .build()
+}
+
+pub fn new_attractor(world: &mut World, pos: Vector2<f32>) -> Entity {
+    world
+        .create_entity()
+        .with(Position(pos))
+        .with(Attractor)
+        .build()
+}
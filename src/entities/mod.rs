@@ -1 +1,5 @@
 pub mod boids;
+pub mod despawn;
+pub mod food;
+pub mod goals;
+pub mod scenario;
@@ -0,0 +1,117 @@
+use crate::{
+    components::{ObstacleShape, Position},
+    entities::{boids, food, goals},
+    resources::{ObstacleConfig, Scenario, SpriteRegistry},
+};
+use amethyst::{
+    ecs::{Entities, Join, LazyUpdate, Read, ReadExpect, ReadStorage},
+    prelude::*,
+    window::ScreenDimensions,
+};
+use anyhow::Result;
+use nalgebra::Vector2;
+
+/// Half extents of the world when there's no window to measure it by, matching
+/// the size in `config/display.ron`.
+pub const DEFAULT_WORLD_EXTENTS: (f32, f32) = (885.0, 500.0);
+
+/// Half extents of the visible world.
+pub fn world_extents(world: &World) -> Vector2<f32> {
+    match world.try_fetch::<ScreenDimensions>() {
+        Some(dimensions) => Vector2::new(dimensions.width(), dimensions.height()) / 2.0,
+        None => Vector2::new(DEFAULT_WORLD_EXTENTS.0, DEFAULT_WORLD_EXTENTS.1),
+    }
+}
+
+/// Places the obstacles, attractors, food and boids of the `Scenario`
+/// resource. Boids are only queued, and show up once the world is maintained.
+pub fn load_scenario(world: &mut World) -> Result<()> {
+    place_fixtures(world);
+
+    let world_extents = world_extents(world);
+    let (entities, lazy_update, sprite_registry, scenario) = world.system_data::<(
+        Entities,
+        Read<LazyUpdate>,
+        Read<SpriteRegistry>,
+        ReadExpect<Scenario>,
+    )>();
+    for region in &scenario.spawns {
+        boids::spawn_region(
+            &entities,
+            &sprite_registry,
+            &lazy_update,
+            &scenario,
+            region,
+            world_extents,
+        )?;
+    }
+    Ok(())
+}
+
+/// Places the obstacles, attractors and food of the `Scenario` resource.
+fn place_fixtures(world: &mut World) {
+    let (obstacles, attractors, food) = {
+        let scenario = world.read_resource::<Scenario>();
+        (
+            scenario.obstacles.clone(),
+            scenario.attractors.clone(),
+            scenario.food.clone(),
+        )
+    };
+
+    for obstacle in obstacles {
+        match obstacle {
+            ObstacleConfig::Point {
+                position,
+                sprite,
+                separation_weight,
+            } => {
+                boids::new_obstacle(world, position, &sprite, separation_weight);
+            }
+            ObstacleConfig::Solid {
+                position,
+                shape,
+                avoidance_weight,
+            } => {
+                boids::new_solid_obstacle(
+                    world,
+                    position,
+                    ObstacleShape {
+                        shape,
+                        avoidance_weight,
+                    },
+                );
+            }
+        }
+    }
+
+    for position in attractors {
+        goals::new_attractor(world, position);
+    }
+
+    for config in food {
+        food::new_food(world, &config);
+    }
+}
+
+/// Removes every boid, obstacle, attractor and food source, and places the
+/// obstacles, attractors and food of `scenario` in their place. Its boids
+/// are left to the caller, as queueing them can't be done while the world is
+/// being maintained.
+pub fn replace_scenario(world: &mut World, scenario: Scenario) {
+    let doomed = {
+        let (entities, positions) = world.system_data::<(Entities, ReadStorage<Position>)>();
+        (&entities, &positions)
+            .join()
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>()
+    };
+    world
+        .delete_entities(&doomed)
+        .expect("only live entities are deleted");
+
+    world.insert(scenario.flow.clone());
+    world.insert(scenario.trails.clone());
+    world.insert(scenario);
+    place_fixtures(world);
+}
@@ -0,0 +1,261 @@
+use crate::{components::BoidData, random, resources::Scenario, simulation::Simulation};
+use anyhow::{anyhow, ensure, Context, Result};
+use nalgebra::Vector2;
+use rand::{seq::SliceRandom, Rng};
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+
+/// Spread of the Gaussian noise a genome is mutated with, relative to the
+/// value of each parameter.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Mutation {
+    /// Applied to the separation, alignment, cohesion and noise weights.
+    pub weight_sigma: f32,
+    /// Applied to the separation, alignment and cohesion radii.
+    pub radius_sigma: f32,
+    pub speed_sigma: f32,
+}
+
+impl Mutation {
+    /// Checks every sigma is finite and not negative.
+    pub fn validate(&self) -> Result<()> {
+        for (name, sigma) in [
+            ("weight_sigma", self.weight_sigma),
+            ("radius_sigma", self.radius_sigma),
+            ("speed_sigma", self.speed_sigma),
+        ] {
+            ensure!(
+                sigma.is_finite() && sigma >= 0.0,
+                "{} is {}, but has to be finite and not negative",
+                name,
+                sigma
+            );
+        }
+        Ok(())
+    }
+
+    /// Copy of `boid_data` with its heritable parameters mutated. The rest of
+    /// it, such as the neighbourhood kind, is passed on unchanged. Parameters
+    /// that would stop being finite, as they can with sigmas `validate`
+    /// refuses, are passed on unchanged too.
+    pub fn mutate<R: Rng>(&self, boid_data: &BoidData, rng: &mut R) -> BoidData {
+        let mut scale = |value: f32, sigma: f32| {
+            let factor = match Normal::new(1.0, sigma) {
+                Ok(normal) if sigma > 0.0 => normal.sample(rng),
+                _ => return value,
+            };
+            let scaled = value * factor;
+            if scaled.is_finite() {
+                scaled.max(0.0)
+            } else {
+                value
+            }
+        };
+
+        let mut mutated = boid_data.clone();
+        mutated.separation_weight = scale(mutated.separation_weight, self.weight_sigma);
+        mutated.alignment_weight = scale(mutated.alignment_weight, self.weight_sigma);
+        mutated.cohesion_weight = scale(mutated.cohesion_weight, self.weight_sigma);
+        mutated.noise_weight = scale(mutated.noise_weight, self.weight_sigma);
+        mutated.separation_radius = scale(mutated.separation_radius, self.radius_sigma);
+        mutated.alignment_radius = scale(mutated.alignment_radius, self.radius_sigma);
+        mutated.cohesion_radius = scale(mutated.cohesion_radius, self.radius_sigma);
+        mutated.max_speed = scale(mutated.max_speed, self.speed_sigma).max(1.0);
+        mutated
+    }
+}
+
+/// What a genome is scored on at the end of a run.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Fitness {
+    /// How aligned the flock's headings are, between 0 and 1.
+    Polarization,
+    /// Boids of the species alive at the end over the flock size. Only useful
+    /// for species with a metabolism, and above 1 once they breed.
+    Survival,
+}
+
+/// Settings for `boids-evolve`, loaded from `resources/evolution`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EvolutionConfig {
+    /// Scenario file under `resources/scenarios` to evolve in.
+    pub scenario: String,
+    /// Species whose `BoidData` is the starting genome.
+    pub species: String,
+    /// Boids sharing a genome in each run.
+    pub flock_size: usize,
+    /// Half the size of the area boids are spawned in, around the origin.
+    pub spawn_extents: Vector2<f32>,
+    /// Genomes scored every generation.
+    pub genomes: usize,
+    /// Best genomes carried over unchanged into the next generation, which
+    /// the rest of it is mutated from.
+    pub elites: usize,
+    pub generations: usize,
+    /// Simulation ticks every genome is scored after.
+    pub ticks: usize,
+    pub fitness: Fitness,
+    pub mutation: Mutation,
+    /// Where the best genome is written, relative to `resources`, as a
+    /// species preset.
+    pub output: String,
+}
+
+/// Runs every generation, calling `report` with the generation number and
+/// its best score. Returns the best genome found and its score.
+pub fn evolve(
+    config: &EvolutionConfig,
+    scenario: &Scenario,
+    mut report: impl FnMut(usize, f32),
+) -> Result<(BoidData, f32)> {
+    if config.genomes == 0 || config.elites == 0 || config.elites > config.genomes {
+        return Err(anyhow!(
+            "Evolution needs at least one genome and between one and {} elites",
+            config.genomes
+        ));
+    }
+    config
+        .mutation
+        .validate()
+        .context("The mutation is invalid")?;
+    let species = scenario.species_named(&config.species)?;
+    let mut rng = random::rng();
+
+    let mut genomes = vec![species.boid.clone()];
+    while genomes.len() < config.genomes {
+        genomes.push(config.mutation.mutate(&species.boid, &mut rng));
+    }
+
+    let mut best: Option<(BoidData, f32)> = None;
+    for generation in 0..config.generations {
+        let mut scored = genomes
+            .into_iter()
+            .map(|genome| {
+                let fitness = score(config, scenario, &genome)?;
+                Ok((genome, fitness))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        scored.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        let (leader, fitness) = &scored[0];
+        report(generation, *fitness);
+        if best.as_ref().map(|(_, b)| fitness > b).unwrap_or(true) {
+            best = Some((leader.clone(), *fitness));
+        }
+
+        let elites = scored
+            .into_iter()
+            .take(config.elites)
+            .map(|(genome, _)| genome)
+            .collect::<Vec<_>>();
+        genomes = elites.clone();
+        while genomes.len() < config.genomes {
+            let parent = elites.choose(&mut rng).unwrap();
+            genomes.push(config.mutation.mutate(parent, &mut rng));
+        }
+    }
+
+    best.ok_or_else(|| anyhow!("Evolution ran for no generations"))
+}
+
+/// Fitness of a flock of `genome` boids after `config.ticks` ticks.
+fn score(config: &EvolutionConfig, scenario: &Scenario, genome: &BoidData) -> Result<f32> {
+    let mut species = scenario.species_named(&config.species)?.clone();
+    species.boid = genome.clone();
+
+    let mut simulation = Simulation::new(scenario.clone())?;
+    simulation.spawn(&species, config.flock_size, config.spawn_extents)?;
+    simulation.run(config.ticks);
+
+    let fitness = match config.fitness {
+        Fitness::Polarization => simulation.polarization(),
+        Fitness::Survival => simulation.count(&species.name) as f32 / config.flock_size as f32,
+    };
+    // A flock that broke down into NaNs is the worst there is, not the best
+    Ok(if fitness.is_nan() {
+        f32::NEG_INFINITY
+    } else {
+        fitness
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flocking::tests::boid_data;
+    use amethyst::config::Config;
+
+    #[test]
+    fn mutation_keeps_parameters_usable() {
+        let mut rng = random::rng();
+        let data = boid_data();
+
+        let unchanged = Mutation {
+            weight_sigma: 0.0,
+            radius_sigma: 0.0,
+            speed_sigma: 0.0,
+        };
+        let copy = unchanged.mutate(&data, &mut rng);
+        assert_eq!(copy.separation_weight, data.separation_weight);
+        assert_eq!(copy.cohesion_radius, data.cohesion_radius);
+        assert_eq!(copy.max_speed, data.max_speed);
+
+        let wild = Mutation {
+            weight_sigma: 5.0,
+            radius_sigma: 5.0,
+            speed_sigma: 5.0,
+        };
+        let mut changed = false;
+        for _ in 0..100 {
+            let mutated = wild.mutate(&data, &mut rng);
+            assert!(mutated.separation_weight >= 0.0 && mutated.alignment_radius >= 0.0);
+            assert!(mutated.max_speed >= 1.0);
+            changed |= mutated.cohesion_weight != data.cohesion_weight;
+        }
+        assert!(changed);
+
+        let broken = Mutation {
+            weight_sigma: f32::NAN,
+            radius_sigma: f32::INFINITY,
+            speed_sigma: -1.0,
+        };
+        assert!(broken.validate().is_err());
+        broken.mutate(&data, &mut rng).validate().unwrap();
+    }
+
+    #[test]
+    fn evolves_a_genome() {
+        let scenario =
+            Scenario::load_bytes(include_bytes!("../resources/scenarios/default.ron")).unwrap();
+        let config = EvolutionConfig {
+            scenario: "default.ron".to_string(),
+            species: "boid".to_string(),
+            flock_size: 5,
+            spawn_extents: Vector2::new(100.0, 100.0),
+            genomes: 3,
+            elites: 1,
+            generations: 2,
+            ticks: 5,
+            fitness: Fitness::Polarization,
+            mutation: Mutation {
+                weight_sigma: 0.1,
+                radius_sigma: 0.1,
+                speed_sigma: 0.1,
+            },
+            output: "presets/test.ron".to_string(),
+        };
+
+        let mut reports = vec![];
+        let (_, fitness) = evolve(&config, &scenario, |generation, fitness| {
+            reports.push((generation, fitness))
+        })
+        .unwrap();
+        assert_eq!(reports.len(), 2);
+        assert!(reports.iter().all(|(_, score)| *score <= fitness));
+        assert!((0.0..=1.0 + 1e-4).contains(&fitness));
+
+        let mut config = config;
+        config.elites = 4;
+        assert!(evolve(&config, &scenario, |_, _| {}).is_err());
+    }
+}
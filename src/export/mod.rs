@@ -0,0 +1,75 @@
+//! Drawing the world without amethyst's renderer, so figures and videos can
+//! be made on machines without a GPU.
+mod raster;
+mod scene;
+mod svg;
+
+pub use self::raster::{render, Canvas, FrameConfig, FrameWriter};
+pub use self::scene::{BoidGlyph, ObstacleGlyph, Scene};
+pub use self::svg::{svg, write_svg, Colouring, SvgOptions};
+
+/// An sRGB colour with straight alpha.
+pub type Colour = [u8; 4];
+
+/// The viewer's clear colour.
+pub const BACKGROUND: Colour = [87, 92, 133, 255];
+pub const OBSTACLE: Colour = [38, 40, 52, 255];
+
+/// Colours species, clusters and the like are told apart by.
+const PALETTE: [Colour; 6] = [
+    [242, 242, 236, 255],
+    [231, 111, 81, 255],
+    [244, 196, 98, 255],
+    [42, 157, 143, 255],
+    [142, 202, 230, 255],
+    [205, 150, 220, 255],
+];
+
+/// Channels of `colour` from 0 to 1.
+pub fn rgba_f32(colour: Colour) -> [f32; 4] {
+    let mut channels = [0.0; 4];
+    for (channel, value) in channels.iter_mut().zip(colour.iter()) {
+        *channel = *value as f32 / 255.0;
+    }
+    channels
+}
+
+/// Colour telling apart whatever is numbered `index`, such as the species at
+/// `index` in the scenario's species list.
+pub fn palette_colour(index: usize) -> Colour {
+    PALETTE[index % PALETTE.len()]
+}
+
+/// Colours from blue when `value` is 0, through grey, to red once it reaches
+/// `max`.
+pub fn ramp_colour(value: f32, max: f32) -> Colour {
+    const STOPS: [[f32; 3]; 3] = [
+        [59.0, 76.0, 192.0],
+        [221.0, 221.0, 221.0],
+        [180.0, 4.0, 38.0],
+    ];
+    let fraction = (value / max.max(f32::EPSILON)).max(0.0).min(1.0) * 2.0;
+    let (low, high, t) = if fraction < 1.0 {
+        (STOPS[0], STOPS[1], fraction)
+    } else {
+        (STOPS[1], STOPS[2], fraction - 1.0)
+    };
+    let mut colour = [255; 4];
+    for (channel, (low, high)) in colour.iter_mut().zip(low.iter().zip(high.iter())) {
+        *channel = (low + (high - low) * t).round() as u8;
+    }
+    colour
+}
+
+/// Fully saturated colour around the hue wheel, starting from red at an
+/// `angle` of 0 and going through green at a third of a turn.
+pub fn hue_colour(angle: f32) -> Colour {
+    let turns = (angle / std::f32::consts::TAU).rem_euclid(1.0);
+    let mut colour = [255; 4];
+    for (channel, offset) in colour.iter_mut().zip([0.0, 2.0 / 3.0, 1.0 / 3.0].iter()) {
+        // Distance around the wheel from the channel's own hue, in sixths
+        let distance = ((turns + offset).rem_euclid(1.0) * 6.0 - 3.0).abs();
+        *channel = ((distance - 1.0).max(0.0).min(1.0) * 255.0).round() as u8;
+    }
+    colour
+}
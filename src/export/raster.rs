@@ -0,0 +1,397 @@
+use super::{palette_colour, Colour, Scene, BACKGROUND, OBSTACLE};
+use crate::spatial::Shape;
+use amethyst::ecs::World;
+use anyhow::{Context, Result};
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+/// Width of walls, in world units.
+const WALL_WIDTH: f32 = 4.0;
+/// Width of trails, in world units.
+const TRAIL_WIDTH: f32 = 2.0;
+/// Alpha of the newest end of trails, out of 255. They fade out towards the
+/// oldest end.
+const TRAIL_OPACITY: f32 = 150.0;
+
+/// How simulations are drawn to PNG frames.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FrameConfig {
+    /// Directory frames are written to, relative to the working directory.
+    pub directory: String,
+    #[serde(default = "default_width")]
+    pub width: u32,
+    #[serde(default = "default_height")]
+    pub height: u32,
+    /// Ticks between frames.
+    #[serde(default = "default_interval")]
+    pub interval: usize,
+    /// Length of the triangle drawn for each boid, in world units.
+    #[serde(default = "default_boid_length")]
+    pub boid_length: f32,
+}
+
+fn default_width() -> u32 {
+    1770
+}
+
+fn default_height() -> u32 {
+    1000
+}
+
+fn default_interval() -> usize {
+    1
+}
+
+fn default_boid_length() -> f32 {
+    32.0
+}
+
+/// An RGBA image drawn in software.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32, background: Colour) -> Canvas {
+        Canvas {
+            width,
+            height,
+            pixels: background
+                .iter()
+                .copied()
+                .cycle()
+                .take(width as usize * height as usize * 4)
+                .collect(),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Rows of pixels from the top, four bytes each.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Colour {
+        let start = (y as usize * self.width as usize + x as usize) * 4;
+        let mut colour = [0; 4];
+        colour.copy_from_slice(&self.pixels[start..start + 4]);
+        colour
+    }
+
+    /// Draws `colour` over the pixel at `x`, `y`, if it's on the canvas.
+    fn blend(&mut self, x: i64, y: i64, colour: Colour) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let start = (y as usize * self.width as usize + x as usize) * 4;
+        let pixel = &mut self.pixels[start..start + 4];
+        let alpha = colour[3] as u32;
+        for (channel, source) in pixel.iter_mut().zip(colour.iter()).take(3) {
+            *channel = ((*source as u32 * alpha + *channel as u32 * (255 - alpha)) / 255) as u8;
+        }
+        pixel[3] = (alpha + pixel[3] as u32 * (255 - alpha) / 255) as u8;
+    }
+
+    /// Fills the polygon with corners at `points`, in pixels from the top
+    /// left, covering the pixels whose centres are inside it.
+    pub fn fill_polygon(&mut self, points: &[Vector2<f32>], colour: Colour) {
+        if points.len() < 3 {
+            return;
+        }
+        let (top, bottom) = points
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(top, bottom), p| {
+                (top.min(p.y), bottom.max(p.y))
+            });
+        let first_row = (top - 0.5).ceil().max(0.0) as i64;
+        let last_row = (bottom - 0.5).floor().min(self.height as f32 - 1.0) as i64;
+
+        let mut crossings = vec![];
+        for row in first_row..=last_row {
+            let y = row as f32 + 0.5;
+            crossings.clear();
+            for (a, b) in points.iter().zip(points.iter().cycle().skip(1)) {
+                if (a.y <= y) != (b.y <= y) {
+                    crossings.push(a.x + (y - a.y) / (b.y - a.y) * (b.x - a.x));
+                }
+            }
+            crossings.sort_by(|a, b| a.partial_cmp(b).expect("crossings are finite"));
+            for span in crossings.chunks_exact(2) {
+                let start = (span[0] - 0.5).ceil().max(0.0) as i64;
+                let end = (span[1] - 0.5).ceil().min(self.width as f32) as i64;
+                for column in start..end {
+                    self.blend(column, row, colour);
+                }
+            }
+        }
+    }
+
+    pub fn fill_circle(&mut self, centre: Vector2<f32>, radius: f32, colour: Colour) {
+        let first_row = (centre.y - radius - 0.5).ceil().max(0.0) as i64;
+        let last_row = (centre.y + radius - 0.5)
+            .floor()
+            .min(self.height as f32 - 1.0) as i64;
+        for row in first_row..=last_row {
+            let dy = row as f32 + 0.5 - centre.y;
+            let half_width = (radius * radius - dy * dy).max(0.0).sqrt();
+            let start = (centre.x - half_width - 0.5).ceil().max(0.0) as i64;
+            let end = (centre.x + half_width - 0.5).ceil().min(self.width as f32) as i64;
+            for column in start..end {
+                self.blend(column, row, colour);
+            }
+        }
+    }
+
+    /// Draws a line `width` pixels wide.
+    pub fn draw_line(
+        &mut self,
+        start: Vector2<f32>,
+        end: Vector2<f32>,
+        width: f32,
+        colour: Colour,
+    ) {
+        let side = match (end - start).try_normalize(f32::EPSILON) {
+            Some(direction) => Vector2::new(-direction.y, direction.x) * width / 2.0,
+            None => return,
+        };
+        self.fill_polygon(
+            &[start + side, end + side, end - side, start - side],
+            colour,
+        );
+    }
+
+    pub fn write_png(&self, path: &Path) -> Result<()> {
+        let file =
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()?
+            .write_image_data(&self.pixels)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// Maps world coordinates onto a canvas, fitting the whole world in and
+/// turning it so up is up.
+struct View {
+    centre: Vector2<f32>,
+    scale: f32,
+}
+
+impl View {
+    fn point(&self, world: Vector2<f32>) -> Vector2<f32> {
+        self.centre + Vector2::new(world.x, -world.y) * self.scale
+    }
+}
+
+/// Draws the obstacles, trails and boids of `scene`.
+pub fn render(scene: &Scene, config: &FrameConfig) -> Canvas {
+    let mut canvas = Canvas::new(config.width, config.height, BACKGROUND);
+    let size = Vector2::new(config.width as f32, config.height as f32);
+    let view = View {
+        centre: size / 2.0,
+        scale: (size.x / (2.0 * scene.extents.x)).min(size.y / (2.0 * scene.extents.y)),
+    };
+
+    for obstacle in &scene.obstacles {
+        match &obstacle.shape {
+            Shape::Circle { radius } => {
+                canvas.fill_circle(view.point(obstacle.position), radius * view.scale, OBSTACLE);
+            }
+            Shape::Segment { start, end } => canvas.draw_line(
+                view.point(obstacle.position + start),
+                view.point(obstacle.position + end),
+                (WALL_WIDTH * view.scale).max(1.0),
+                OBSTACLE,
+            ),
+            shape => {
+                let corners = shape
+                    .edges(obstacle.position)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(start, _)| view.point(start))
+                    .collect::<Vec<_>>();
+                canvas.fill_polygon(&corners, OBSTACLE);
+            }
+        }
+    }
+
+    for boid in &scene.boids {
+        let mut colour = palette_colour(boid.species);
+        for (i, segment) in boid.trail.windows(2).enumerate() {
+            colour[3] = (TRAIL_OPACITY * (i + 1) as f32 / (boid.trail.len() - 1) as f32) as u8;
+            canvas.draw_line(
+                view.point(segment[0]),
+                view.point(segment[1]),
+                (TRAIL_WIDTH * view.scale).max(1.0),
+                colour,
+            );
+        }
+    }
+
+    for boid in &scene.boids {
+        let heading = boid
+            .velocity
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(Vector2::x);
+        let side = Vector2::new(-heading.y, heading.x) * config.boid_length / 4.0;
+        let nose = boid.position + heading * config.boid_length / 2.0;
+        let tail = boid.position - heading * config.boid_length / 2.0;
+        canvas.fill_polygon(
+            &[
+                view.point(nose),
+                view.point(tail + side),
+                view.point(tail - side),
+            ],
+            palette_colour(boid.species),
+        );
+    }
+
+    canvas
+}
+
+/// Writes every `interval`th tick of a simulation to a numbered PNG.
+pub struct FrameWriter {
+    config: FrameConfig,
+    directory: PathBuf,
+    frames: usize,
+}
+
+impl FrameWriter {
+    /// Writes frames to `directory`, creating it if it doesn't exist.
+    pub fn create(config: &FrameConfig, directory: &Path) -> Result<FrameWriter> {
+        fs::create_dir_all(directory)
+            .with_context(|| format!("Failed to create {}", directory.display()))?;
+        Ok(FrameWriter {
+            config: config.clone(),
+            directory: directory.to_path_buf(),
+            frames: 0,
+        })
+    }
+
+    /// Frames written so far.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Draws `world` as it is after `tick` ticks, if the tick falls on the
+    /// interval.
+    pub fn capture(&mut self, world: &World, tick: usize) -> Result<()> {
+        if tick % self.config.interval.max(1) != 0 {
+            return Ok(());
+        }
+        let path = self.directory.join(format!("frame-{:05}.png", self.frames));
+        render(&Scene::capture(world), &self.config).write_png(&path)?;
+        self.frames += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{export::BoidGlyph, resources::Scenario, simulation::Simulation};
+    use amethyst::config::Config;
+
+    const RED: Colour = [255, 0, 0, 255];
+
+    #[test]
+    fn shapes_cover_the_pixels_inside_them() {
+        let mut canvas = Canvas::new(10, 10, [0, 0, 0, 255]);
+        canvas.fill_polygon(
+            &[
+                Vector2::new(0.0, 0.0),
+                Vector2::new(4.0, 0.0),
+                Vector2::new(0.0, 4.0),
+            ],
+            RED,
+        );
+        assert_eq!(canvas.pixel(0, 0), RED);
+        assert_eq!(canvas.pixel(1, 1), RED);
+        assert_eq!(canvas.pixel(3, 3), [0, 0, 0, 255]);
+
+        canvas.fill_circle(Vector2::new(7.0, 7.0), 2.0, [0, 0, 255, 128]);
+        assert_eq!(canvas.pixel(7, 7), [0, 0, 128, 255]);
+        assert_eq!(canvas.pixel(9, 9), [0, 0, 0, 255]);
+
+        // Shapes partly off the canvas are clipped
+        canvas.draw_line(Vector2::new(-5.0, 9.5), Vector2::new(20.0, 9.5), 1.0, RED);
+        assert!((0..10).all(|x| canvas.pixel(x, 9) == RED));
+    }
+
+    #[test]
+    fn boids_point_where_they_head() {
+        let config = FrameConfig {
+            directory: String::new(),
+            width: 100,
+            height: 100,
+            interval: 1,
+            boid_length: 40.0,
+        };
+        let scene = Scene {
+            extents: Vector2::new(50.0, 50.0),
+            obstacles: vec![],
+            boids: vec![BoidGlyph {
+                position: Vector2::zeros(),
+                velocity: Vector2::new(0.0, 10.0),
+                species: 1,
+                max_speed: 10.0,
+                trail: vec![],
+            }],
+        };
+        let canvas = render(&scene, &config);
+        // Up in the world is up on the canvas, and the nose is narrower
+        // than the tail
+        assert_eq!(canvas.pixel(50, 35), palette_colour(1));
+        assert_eq!(canvas.pixel(45, 35), BACKGROUND);
+        assert_eq!(canvas.pixel(45, 65), palette_colour(1));
+    }
+
+    #[test]
+    fn frames_are_written_as_png() {
+        let directory = std::env::temp_dir().join(format!("boids-frames-{}", std::process::id()));
+        let config = FrameConfig {
+            directory: directory.display().to_string(),
+            width: 64,
+            height: 32,
+            interval: 2,
+            boid_length: 16.0,
+        };
+        let scenario =
+            Scenario::load_bytes(include_bytes!("../../resources/scenarios/default.ron")).unwrap();
+        let simulation = Simulation::new(scenario).unwrap();
+        let world = &simulation.world;
+        let mut frames = FrameWriter::create(&config, &directory).unwrap();
+        for tick in 0..5 {
+            frames.capture(world, tick).unwrap();
+        }
+        assert_eq!(frames.frames(), 3);
+
+        let decoder = png::Decoder::new(File::open(directory.join("frame-00002.png")).unwrap());
+        let (info, mut reader) = decoder.read_info().unwrap();
+        assert_eq!((info.width, info.height), (64, 32));
+        let mut pixels = vec![0; info.buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(pixels, render(&Scene::capture(world), &config).pixels());
+        assert!(!directory.join("frame-00003.png").exists());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{
+    components::{BoidData, ObstacleData, ObstacleShape, Position, Species, Trail, Velocity},
+    entities::scenario::world_extents,
+    resources::Scenario,
+    spatial::Shape,
+};
+use amethyst::ecs::{prelude::*, ReadExpect, ReadStorage};
+use nalgebra::Vector2;
+
+/// Radius of point obstacles, matching their sprite.
+const POINT_OBSTACLE_RADIUS: f32 = 25.0;
+
+/// A boid as the exporters draw it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoidGlyph {
+    pub position: Vector2<f32>,
+    pub velocity: Vector2<f32>,
+    /// Position of the boid's species in the scenario's species list.
+    pub species: usize,
+    pub max_speed: f32,
+    /// Recent positions from the boid's `Trail`, oldest first, ending at its
+    /// current position. Empty for boids without one.
+    pub trail: Vec<Vector2<f32>>,
+}
+
+/// An obstacle as the exporters draw it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObstacleGlyph {
+    pub position: Vector2<f32>,
+    pub shape: Shape,
+    /// Distance boids start steering away from within, for point obstacles.
+    pub separation_radius: Option<f32>,
+}
+
+/// Everything the exporters draw, taken from the world at one point in time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scene {
+    /// Half extents of the visible world, around the origin.
+    pub extents: Vector2<f32>,
+    pub obstacles: Vec<ObstacleGlyph>,
+    pub boids: Vec<BoidGlyph>,
+}
+
+impl Scene {
+    pub fn capture(world: &World) -> Scene {
+        let (
+            boid_datas,
+            obstacle_datas,
+            obstacle_shapes,
+            positions,
+            velocities,
+            species,
+            trails,
+            scenario,
+        ) = world.system_data::<(
+            ReadStorage<BoidData>,
+            ReadStorage<ObstacleData>,
+            ReadStorage<ObstacleShape>,
+            ReadStorage<Position>,
+            ReadStorage<Velocity>,
+            ReadStorage<Species>,
+            ReadStorage<Trail>,
+            ReadExpect<Scenario>,
+        )>();
+
+        let points = (&obstacle_datas, &positions)
+            .join()
+            .map(|(data, position)| ObstacleGlyph {
+                position: position.0,
+                shape: Shape::Circle {
+                    radius: POINT_OBSTACLE_RADIUS,
+                },
+                separation_radius: Some(data.separation_radius),
+            });
+        let solids = (&obstacle_shapes, &positions)
+            .join()
+            .map(|(obstacle, position)| ObstacleGlyph {
+                position: position.0,
+                shape: obstacle.shape.clone(),
+                separation_radius: None,
+            });
+        let boids = (
+            &boid_datas,
+            &positions,
+            &velocities,
+            species.maybe(),
+            trails.maybe(),
+        )
+            .join()
+            .map(
+                |(boid_data, position, velocity, species, trail)| BoidGlyph {
+                    position: position.0,
+                    velocity: velocity.0,
+                    species: species
+                        .and_then(|species| scenario.species_index(&species.0))
+                        .unwrap_or_default(),
+                    max_speed: boid_data.max_speed,
+                    trail: trail
+                        .map(|trail| trail.points().chain(Some(&position.0)).copied().collect())
+                        .unwrap_or_default(),
+                },
+            )
+            .collect();
+
+        Scene {
+            extents: world_extents(world),
+            obstacles: points.chain(solids).collect(),
+            boids,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::Simulation;
+    use amethyst::config::Config;
+
+    #[test]
+    fn captures_boids_by_species_and_obstacles() {
+        let scenario =
+            Scenario::load_bytes(include_bytes!("../../resources/scenarios/murmuration.ron"))
+                .unwrap();
+        let simulation = Simulation::new(scenario).unwrap();
+        let scene = Scene::capture(&simulation.world);
+
+        assert_eq!(scene.boids.len(), simulation.metrics().boids);
+        assert_eq!(
+            scene.boids.iter().filter(|boid| boid.species == 1).count(),
+            3
+        );
+        assert_eq!(
+            scene.obstacles.len(),
+            simulation.world.read_resource::<Scenario>().obstacles.len()
+        );
+        assert!(scene.extents.x > 0.0 && scene.extents.y > 0.0);
+    }
+}
@@ -0,0 +1,257 @@
+use super::{palette_colour, ramp_colour, BoidGlyph, Colour, Scene, BACKGROUND, OBSTACLE};
+use crate::spatial::Shape;
+use anyhow::{Context, Result};
+use nalgebra::Vector2;
+use std::{fmt::Write, fs, path::Path};
+
+/// Width of walls and outlines, in world units.
+const STROKE_WIDTH: f32 = 4.0;
+
+/// How boids are coloured.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Colouring {
+    #[default]
+    Species,
+    /// From blue when still to red at the boid's top speed.
+    Speed,
+}
+
+/// What goes into an exported SVG, and how it looks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgOptions {
+    pub colouring: Colouring,
+    /// Whether boids' recent trajectories are drawn, for boids that keep
+    /// track of them.
+    pub trails: bool,
+    /// Length of the glyph drawn for each boid, in world units.
+    pub boid_length: f32,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            colouring: Colouring::Species,
+            trails: true,
+            boid_length: 32.0,
+        }
+    }
+}
+
+/// `scene` as an SVG document in world units, with y pointing up.
+pub fn svg(scene: &Scene, options: &SvgOptions) -> String {
+    let extents = scene.extents;
+    let mut svg = String::new();
+    // Writing to a string can't fail
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}" width="{}" height="{}">"#,
+        -extents.x,
+        -extents.y,
+        2.0 * extents.x,
+        2.0 * extents.y,
+        2.0 * extents.x,
+        2.0 * extents.y,
+    );
+    let _ = writeln!(svg, r#"<g transform="scale(1 -1)">"#);
+    let _ = writeln!(
+        svg,
+        r#"<rect id="bounds" x="{}" y="{}" width="{}" height="{}" {}/>"#,
+        -extents.x,
+        -extents.y,
+        2.0 * extents.x,
+        2.0 * extents.y,
+        fill(BACKGROUND),
+    );
+
+    let _ = writeln!(svg, r#"<g id="obstacles">"#);
+    for obstacle in &scene.obstacles {
+        if let Some(radius) = obstacle.separation_radius {
+            let _ = writeln!(
+                svg,
+                r#"<circle class="separation" cx="{}" cy="{}" r="{}" fill="none" {} stroke-dasharray="{} {}"/>"#,
+                obstacle.position.x,
+                obstacle.position.y,
+                radius,
+                stroke(OBSTACLE, STROKE_WIDTH / 2.0),
+                STROKE_WIDTH * 2.0,
+                STROKE_WIDTH * 2.0,
+            );
+        }
+        let _ = match &obstacle.shape {
+            Shape::Circle { radius } => writeln!(
+                svg,
+                r#"<circle cx="{}" cy="{}" r="{}" {}/>"#,
+                obstacle.position.x,
+                obstacle.position.y,
+                radius,
+                fill(OBSTACLE),
+            ),
+            Shape::Segment { start, end } => {
+                let (start, end) = (obstacle.position + start, obstacle.position + end);
+                writeln!(
+                    svg,
+                    r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {}/>"#,
+                    start.x,
+                    start.y,
+                    end.x,
+                    end.y,
+                    stroke(OBSTACLE, STROKE_WIDTH),
+                )
+            }
+            shape => {
+                let corners = shape
+                    .edges(obstacle.position)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(start, _)| start)
+                    .collect::<Vec<_>>();
+                writeln!(
+                    svg,
+                    r#"<polygon points="{}" {}/>"#,
+                    points(&corners),
+                    fill(OBSTACLE),
+                )
+            }
+        };
+    }
+    let _ = writeln!(svg, "</g>");
+
+    if options.trails {
+        let _ = writeln!(svg, r#"<g id="trails" fill="none" stroke-opacity="0.5">"#);
+        for boid in scene.boids.iter().filter(|boid| boid.trail.len() > 1) {
+            let _ = writeln!(
+                svg,
+                r#"<polyline points="{}" {}/>"#,
+                points(&boid.trail),
+                stroke(colour(boid, options.colouring), STROKE_WIDTH / 2.0),
+            );
+        }
+        let _ = writeln!(svg, "</g>");
+    }
+
+    let _ = writeln!(svg, r#"<g id="boids">"#);
+    for boid in &scene.boids {
+        let heading = boid
+            .velocity
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(Vector2::x);
+        let side = Vector2::new(-heading.y, heading.x) * options.boid_length / 4.0;
+        let nose = boid.position + heading * options.boid_length / 2.0;
+        let tail = boid.position - heading * options.boid_length / 2.0;
+        let _ = writeln!(
+            svg,
+            r#"<polygon points="{}" {}/>"#,
+            points(&[nose, tail + side, tail - side]),
+            fill(colour(boid, options.colouring)),
+        );
+    }
+    let _ = writeln!(svg, "</g>");
+
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+pub fn write_svg(scene: &Scene, options: &SvgOptions, path: &Path) -> Result<()> {
+    fs::write(path, svg(scene, options))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn colour(boid: &BoidGlyph, colouring: Colouring) -> Colour {
+    match colouring {
+        Colouring::Species => palette_colour(boid.species),
+        Colouring::Speed => ramp_colour(boid.velocity.norm(), boid.max_speed),
+    }
+}
+
+fn hex(colour: Colour) -> String {
+    format!("#{:02x}{:02x}{:02x}", colour[0], colour[1], colour[2])
+}
+
+fn fill(colour: Colour) -> String {
+    format!(
+        r#"fill="{}" fill-opacity="{}""#,
+        hex(colour),
+        colour[3] as f32 / 255.0
+    )
+}
+
+fn stroke(colour: Colour, width: f32) -> String {
+    format!(
+        r#"stroke="{}" stroke-opacity="{}" stroke-width="{}""#,
+        hex(colour),
+        colour[3] as f32 / 255.0,
+        width
+    )
+}
+
+fn points(points: &[Vector2<f32>]) -> String {
+    points
+        .iter()
+        .map(|p| format!("{:.2},{:.2}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::ObstacleGlyph;
+
+    fn scene() -> Scene {
+        Scene {
+            extents: Vector2::new(100.0, 50.0),
+            obstacles: vec![ObstacleGlyph {
+                position: Vector2::new(10.0, 0.0),
+                shape: Shape::Circle { radius: 5.0 },
+                separation_radius: Some(30.0),
+            }],
+            boids: vec![
+                BoidGlyph {
+                    position: Vector2::zeros(),
+                    velocity: Vector2::new(0.0, 10.0),
+                    species: 0,
+                    max_speed: 10.0,
+                    trail: vec![Vector2::new(0.0, -20.0), Vector2::new(0.0, -10.0)],
+                },
+                BoidGlyph {
+                    position: Vector2::new(20.0, 20.0),
+                    velocity: Vector2::zeros(),
+                    species: 1,
+                    max_speed: 10.0,
+                    trail: vec![],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn scenes_are_drawn_in_world_units() {
+        let svg = svg(&scene(), &SvgOptions::default());
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(r#"viewBox="-100 -50 200 100""#));
+        assert!(svg.contains(r#"<circle class="separation" cx="10" cy="0" r="30""#));
+        assert!(svg.contains(r#"<polyline points="0.00,-20.00 0.00,-10.00""#));
+        // The glyph of the first boid points up
+        assert!(svg.contains(r#"<polygon points="0.00,16.00 -8.00,-16.00 8.00,-16.00""#));
+        assert!(svg.contains(&hex(palette_colour(1))));
+        assert_eq!(svg.matches("<polygon").count(), 2);
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+
+    #[test]
+    fn boids_can_be_coloured_by_speed() {
+        let options = SvgOptions {
+            colouring: Colouring::Speed,
+            trails: false,
+            ..SvgOptions::default()
+        };
+        let svg = svg(&scene(), &options);
+        assert!(svg.contains(&hex(ramp_colour(10.0, 10.0))));
+        assert!(svg.contains(&hex(ramp_colour(0.0, 10.0))));
+        assert!(!svg.contains(&hex(palette_colour(1))));
+        assert!(!svg.contains("<polyline"));
+        assert_eq!(ramp_colour(0.0, 10.0), [59, 76, 192, 255]);
+        assert_eq!(ramp_colour(5.0, 10.0), [221, 221, 221, 255]);
+        assert_eq!(ramp_colour(20.0, 10.0), [180, 4, 38, 255]);
+    }
+}
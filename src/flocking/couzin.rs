@@ -0,0 +1,221 @@
+use super::{
+    heading, stacked_direction, turn_towards, validate_parameters, FlockingModel, NeighbourQuery,
+};
+use crate::{components::BoidData, random};
+use anyhow::Result;
+use nalgebra::Vector2;
+use rand::distributions::Distribution;
+use rand_distr::Normal;
+use serde::{Deserialize, Serialize};
+
+/// Couzin et al.'s zonal model: boids move at a constant speed and turn
+/// away from anything in their zone of repulsion, or otherwise towards the
+/// heading of those in their zone of orientation and the position of those in
+/// their zone of attraction.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CouzinParams {
+    /// Radius of the zone of repulsion.
+    pub repulsion_radius: f32,
+    /// Outer radius of the zone of orientation.
+    pub orientation_radius: f32,
+    /// Outer radius of the zone of attraction.
+    pub attraction_radius: f32,
+    /// Angle the boid can see across, in radians. Neighbours in the blind
+    /// zone behind it are ignored.
+    pub field_of_view: f32,
+    /// Fastest the boid can turn, in radians per second.
+    pub turning_rate: f32,
+    /// Standard deviation of the noise added to the desired heading, in radians.
+    pub noise: f32,
+    pub speed: f32,
+}
+
+impl CouzinParams {
+    /// Checks the radii, angles, rates and speed are finite and not negative.
+    pub fn validate(&self) -> Result<()> {
+        validate_parameters(&[
+            ("repulsion_radius", self.repulsion_radius),
+            ("orientation_radius", self.orientation_radius),
+            ("attraction_radius", self.attraction_radius),
+            ("field_of_view", self.field_of_view),
+            ("turning_rate", self.turning_rate),
+            ("noise", self.noise),
+            ("speed", self.speed),
+        ])
+    }
+}
+
+impl FlockingModel for CouzinParams {
+    fn steering(
+        &self,
+        _boid_data: &BoidData,
+        position: Vector2<f32>,
+        velocity: Vector2<f32>,
+        neighbours: &NeighbourQuery,
+        delta_seconds: f32,
+    ) -> Vector2<f32> {
+        let current = if velocity.norm() > 0.0 {
+            velocity.normalize()
+        } else {
+            heading(0.0)
+        };
+
+        let mut repulsion = (0, Vector2::new(0.0, 0.0));
+        let mut orientation = (0, current);
+        let mut attraction = (0, Vector2::new(0.0, 0.0));
+        for (other, neighbour_position, neighbour_velocity) in
+            neighbours.within(self.attraction_radius)
+        {
+            let offset = neighbour_position - position;
+            let distance = offset.norm();
+            if distance == 0.0 {
+                // Right on top of each other, so there's no way to see them
+                // and no way but an arbitrary one to move away
+                repulsion = (
+                    repulsion.0 + 1,
+                    repulsion.1 + stacked_direction(neighbours.entity(), other),
+                );
+                continue;
+            }
+            if distance >= self.attraction_radius
+                || current.angle(&offset) > self.field_of_view / 2.0
+            {
+                continue;
+            }
+
+            if distance < self.repulsion_radius {
+                repulsion = (repulsion.0 + 1, repulsion.1 - offset / distance);
+            } else if distance < self.orientation_radius {
+                if neighbour_velocity.norm() > 0.0 {
+                    orientation = (
+                        orientation.0 + 1,
+                        orientation.1 + neighbour_velocity.normalize(),
+                    );
+                }
+            } else {
+                attraction = (attraction.0 + 1, attraction.1 + offset / distance);
+            }
+        }
+
+        // Repulsion takes priority, otherwise orientation and attraction are
+        // averaged if both zones have someone in them
+        let desired = match (repulsion.0, orientation.0, attraction.0) {
+            (0, 0, 0) => current,
+            (0, 0, _) => attraction.1,
+            (0, _, 0) => orientation.1,
+            (0, _, _) => (normalize_or_zero(orientation.1) + normalize_or_zero(attraction.1)) / 2.0,
+            _ => repulsion.1,
+        };
+        let mut desired_angle = if desired.norm() > 0.0 {
+            desired.y.atan2(desired.x)
+        } else {
+            current.y.atan2(current.x)
+        };
+        if self.noise > 0.0 {
+            if let Ok(noise) = Normal::new(0.0, self.noise) {
+                desired_angle += noise.sample(&mut random::rng());
+            }
+        }
+
+        let new_heading = turn_towards(
+            current,
+            heading(desired_angle),
+            self.turning_rate * delta_seconds,
+        );
+        new_heading * self.speed - velocity
+    }
+}
+
+fn normalize_or_zero(vector: Vector2<f32>) -> Vector2<f32> {
+    if vector.norm() > 0.0 {
+        vector.normalize()
+    } else {
+        vector
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flocking::tests::{assert_close, boid_data, query_first, BoidState};
+    use std::f32::consts::PI;
+
+    fn params() -> CouzinParams {
+        CouzinParams {
+            repulsion_radius: 10.0,
+            orientation_radius: 50.0,
+            attraction_radius: 100.0,
+            field_of_view: 2.0 * PI,
+            turning_rate: 2.0 * PI,
+            noise: 0.0,
+            speed: 10.0,
+        }
+    }
+
+    fn new_velocity(params: &CouzinParams, boids: &[BoidState]) -> Vector2<f32> {
+        let velocity = Vector2::new((boids[0].1).0, (boids[0].1).1);
+        query_first(boids, |query| {
+            velocity + params.steering(&boid_data(), Vector2::new(0.0, 0.0), velocity, query, 1.0)
+        })
+    }
+
+    #[test]
+    fn repulsion_overrides_other_zones() {
+        let velocity = new_velocity(
+            &params(),
+            &[
+                ((0.0, 0.0), (10.0, 0.0)),
+                ((5.0, 0.0), (0.0, 10.0)),
+                ((0.0, 30.0), (0.0, 10.0)),
+                ((0.0, 80.0), (0.0, 10.0)),
+            ],
+        );
+        assert_close(velocity, Vector2::new(-10.0, 0.0));
+    }
+
+    #[test]
+    fn orientation_includes_own_heading() {
+        let velocity = new_velocity(
+            &params(),
+            &[((0.0, 0.0), (10.0, 0.0)), ((0.0, 30.0), (0.0, 3.0))],
+        );
+        let expected = 10.0 / 2.0f32.sqrt();
+        assert_close(velocity, Vector2::new(expected, expected));
+    }
+
+    #[test]
+    fn orientation_and_attraction_are_averaged() {
+        let velocity = new_velocity(
+            &params(),
+            &[
+                ((0.0, 0.0), (10.0, 0.0)),
+                ((30.0, 0.0), (10.0, 0.0)),
+                ((0.0, 80.0), (0.0, 10.0)),
+            ],
+        );
+        let expected = 10.0 / 2.0f32.sqrt();
+        assert_close(velocity, Vector2::new(expected, expected));
+    }
+
+    #[test]
+    fn turning_rate_limits_turn() {
+        let mut params = params();
+        params.turning_rate = 0.1;
+        let velocity = new_velocity(
+            &params,
+            &[((0.0, 0.0), (10.0, 0.0)), ((0.0, 5.0), (0.0, 0.0))],
+        );
+        assert_close(velocity, heading(-0.1) * 10.0);
+    }
+
+    #[test]
+    fn blind_zone_is_ignored() {
+        let mut params = params();
+        params.field_of_view = PI;
+        let velocity = new_velocity(
+            &params,
+            &[((0.0, 0.0), (10.0, 0.0)), ((-5.0, 0.0), (0.0, 0.0))],
+        );
+        assert_close(velocity, Vector2::new(10.0, 0.0));
+    }
+}
@@ -0,0 +1,183 @@
+mod couzin;
+mod neighbours;
+mod reynolds;
+mod steering;
+mod vicsek;
+
+pub use self::couzin::CouzinParams;
+pub use self::neighbours::{Neighbour, NeighbourQuery, Neighbourhoods};
+pub use self::reynolds::Reynolds;
+pub use self::steering::{arrive, evade, follow, seek};
+pub use self::vicsek::VicsekParams;
+
+use crate::components::{BoidData, Falloff};
+use amethyst::ecs::Entity;
+use anyhow::{ensure, Result};
+use nalgebra::Vector2;
+use std::f32::consts::PI;
+
+/// A set of rules deciding how a boid reacts to its neighbours.
+pub trait FlockingModel {
+    /// Change to apply to the boid's velocity this tick. Obstacle avoidance and
+    /// the `max_speed` cap are applied on top of this by `BoidSystem`.
+    fn steering(
+        &self,
+        boid_data: &BoidData,
+        position: Vector2<f32>,
+        velocity: Vector2<f32>,
+        neighbours: &NeighbourQuery,
+        delta_seconds: f32,
+    ) -> Vector2<f32>;
+}
+
+/// Unit vector for `angle`, in radians from the x axis.
+pub(crate) fn heading(angle: f32) -> Vector2<f32> {
+    Vector2::new(angle.cos(), angle.sin())
+}
+
+/// Rotates the unit vector `current` towards `desired` by no more than `max_angle` radians.
+fn turn_towards(current: Vector2<f32>, desired: Vector2<f32>, max_angle: f32) -> Vector2<f32> {
+    let current_angle = current.y.atan2(current.x);
+    let mut turn = desired.y.atan2(desired.x) - current_angle;
+    // Take the short way around
+    if turn > PI {
+        turn -= 2.0 * PI;
+    } else if turn < -PI {
+        turn += 2.0 * PI;
+    }
+
+    heading(current_angle + turn.max(-max_angle).min(max_angle))
+}
+
+/// Vector pointing along `offset`, with a magnitude of `radius` scaled by
+/// `falloff` for how far into the radius the offset reaches. Offsets outside
+/// the radius produce no repulsion. Offsets of zero length, which have no
+/// direction, push along the unit vector `stacked` as hard as the falloff
+/// allows instead.
+pub fn repulsion(
+    offset: Vector2<f32>,
+    radius: f32,
+    falloff: Falloff,
+    stacked: Vector2<f32>,
+) -> Vector2<f32> {
+    let distance = offset.norm();
+    if distance >= radius {
+        return Vector2::new(0.0, 0.0);
+    }
+    if distance == 0.0 {
+        return stacked * radius * falloff.factor(0.0);
+    }
+
+    offset / distance * radius * falloff.factor(distance / radius)
+}
+
+/// Checks every named parameter of a model is finite and not negative.
+fn validate_parameters(parameters: &[(&str, f32)]) -> Result<()> {
+    for (name, value) in parameters {
+        ensure!(
+            value.is_finite() && *value >= 0.0,
+            "{} is {}, but has to be finite and not negative",
+            name,
+            value
+        );
+    }
+    Ok(())
+}
+
+/// Direction `entity` is pushed away from `other` in when both are at exactly
+/// the same position. It's opposite for the two of them, so they move apart.
+pub fn stacked_direction(entity: Entity, other: Entity) -> Vector2<f32> {
+    if entity.id() < other.id() {
+        -Vector2::x()
+    } else {
+        Vector2::x()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::components::Neighbourhood;
+    use amethyst::ecs::prelude::*;
+
+    pub fn assert_close(actual: Vector2<f32>, expected: Vector2<f32>) {
+        assert!(
+            (actual - expected).norm() < 1e-3,
+            "expected {:?}, got {:?}",
+            expected,
+            actual
+        );
+    }
+
+    pub fn boid_data() -> BoidData {
+        BoidData {
+            separation_weight: 1.0,
+            alignment_weight: 1.0,
+            cohesion_weight: 1.0,
+            noise_weight: 0.0,
+            separation_radius: 50.0,
+            alignment_radius: 50.0,
+            cohesion_radius: 50.0,
+            separation_falloff: Falloff::Linear,
+            neighbourhood: Neighbourhood::Metric,
+            max_speed: 1000.0,
+            look_ahead: 0.0,
+        }
+    }
+
+    /// Position and velocity of a boid.
+    pub type BoidState = ((f32, f32), (f32, f32));
+
+    /// Runs `f` with a metric neighbour query for the first of `boids`.
+    pub fn query_first<R>(boids: &[BoidState], f: impl FnOnce(&NeighbourQuery) -> R) -> R {
+        let mut world = World::new();
+        world.register::<BoidData>();
+        let all_boids = boids
+            .iter()
+            .map(|(p, v)| {
+                (
+                    world.create_entity().build(),
+                    Vector2::new(p.0, p.1),
+                    Vector2::new(v.0, v.1),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let boid_datas = world.read_storage::<BoidData>();
+        let neighbourhoods = Neighbourhoods::new(&all_boids, &boid_datas);
+        f(&neighbourhoods.query(0, Neighbourhood::Metric))
+    }
+
+    #[test]
+    fn repulsion_shrinks_with_distance() {
+        for falloff in [Falloff::Linear, Falloff::Inverse, Falloff::InverseSquare].iter() {
+            let near = repulsion(Vector2::new(10.0, 0.0), 100.0, *falloff, Vector2::x());
+            let far = repulsion(Vector2::new(90.0, 0.0), 100.0, *falloff, Vector2::x());
+            assert!(near.x > far.x, "{:?}: {:?} <= {:?}", falloff, near, far);
+            assert_eq!(
+                repulsion(Vector2::new(100.0, 0.0), 100.0, *falloff, Vector2::x()).norm(),
+                0.0
+            );
+            // No jump at the edge of the radius
+            let edge = repulsion(Vector2::new(99.9, 0.0), 100.0, *falloff, Vector2::x());
+            assert!(edge.norm() < 0.5, "{:?}: {:?}", falloff, edge);
+            // Bounded close in, and the same when exactly stacked
+            let close = repulsion(Vector2::new(0.001, 0.0), 100.0, *falloff, Vector2::x());
+            let stacked = repulsion(Vector2::zeros(), 100.0, *falloff, Vector2::x());
+            assert!(close.norm() <= 100.0 * 99.0, "{:?}: {:?}", falloff, close);
+            assert_close(stacked, close);
+        }
+    }
+
+    #[test]
+    fn turn_towards_is_clamped_and_takes_short_way() {
+        assert_close(
+            turn_towards(heading(0.0), heading(1.0), 0.25),
+            heading(0.25),
+        );
+        assert_close(
+            turn_towards(heading(3.0), heading(-3.0), 1.0),
+            heading(-3.0),
+        );
+    }
+}
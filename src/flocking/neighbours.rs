@@ -0,0 +1,114 @@
+use crate::{
+    components::{BoidData, Neighbourhood},
+    spatial::{delaunay_neighbours, SpatialGrid},
+};
+use amethyst::ecs::{Entity, ReadStorage};
+use nalgebra::Vector2;
+
+/// A boid's entity, position and velocity.
+pub type Neighbour = (Entity, Vector2<f32>, Vector2<f32>);
+
+/// Spatial lookups over every boid in the current frame, shared by all rules.
+pub struct Neighbourhoods<'a> {
+    pub all_boids: &'a [Neighbour],
+    grid: SpatialGrid,
+    delaunay: Option<Vec<Vec<usize>>>,
+}
+
+impl<'a> Neighbourhoods<'a> {
+    pub fn new(
+        all_boids: &'a [Neighbour],
+        boid_datas: &ReadStorage<BoidData>,
+    ) -> Neighbourhoods<'a> {
+        let positions = all_boids.iter().map(|(_, p, _)| *p).collect::<Vec<_>>();
+        let datas = all_boids
+            .iter()
+            .filter_map(|(e, _, _)| boid_datas.get(*e))
+            .collect::<Vec<_>>();
+
+        // Size cells to the largest radius so metric queries only touch a few of them
+        let cell_size = datas
+            .iter()
+            .map(|d| {
+                d.separation_radius
+                    .max(d.alignment_radius)
+                    .max(d.cohesion_radius)
+            })
+            .fold(0.0, f32::max);
+        // The triangulation covers every boid, so only build it when someone needs it
+        let delaunay = if datas
+            .iter()
+            .any(|d| d.neighbourhood == Neighbourhood::Voronoi)
+        {
+            Some(delaunay_neighbours(&positions))
+        } else {
+            None
+        };
+
+        Neighbourhoods {
+            all_boids,
+            grid: SpatialGrid::new(cell_size, positions),
+            delaunay,
+        }
+    }
+
+    /// Neighbours of the boid at `index` into `all_boids` (or of an arbitrary
+    /// point if `None`) under `neighbourhood`. `radius` only applies to
+    /// metric neighbourhoods.
+    pub fn neighbours(
+        &self,
+        index: Option<usize>,
+        position: Vector2<f32>,
+        radius: f32,
+        neighbourhood: Neighbourhood,
+    ) -> Vec<Neighbour> {
+        let indices = match (neighbourhood, index) {
+            (Neighbourhood::Topological(count), _) => self.grid.nearest(position, count, index),
+            (Neighbourhood::Voronoi, Some(index)) => self
+                .delaunay
+                .as_ref()
+                .map(|delaunay| delaunay[index].clone())
+                .unwrap_or_default(),
+            (Neighbourhood::Metric, _) | (Neighbourhood::Voronoi, None) => self
+                .grid
+                .within_radius(position, radius)
+                .into_iter()
+                .filter(|i| Some(*i) != index)
+                .collect(),
+        };
+
+        indices.into_iter().map(|i| self.all_boids[i]).collect()
+    }
+
+    /// Neighbour lookups on behalf of the boid at `index`, using its own
+    /// neighbourhood mode.
+    pub fn query(&self, index: usize, neighbourhood: Neighbourhood) -> NeighbourQuery {
+        NeighbourQuery {
+            neighbourhoods: self,
+            index,
+            neighbourhood,
+        }
+    }
+}
+
+/// Neighbours of a single boid, as handed to a `FlockingModel`.
+pub struct NeighbourQuery<'a> {
+    neighbourhoods: &'a Neighbourhoods<'a>,
+    index: usize,
+    neighbourhood: Neighbourhood,
+}
+
+impl<'a> NeighbourQuery<'a> {
+    /// The boid the neighbours are of.
+    pub fn entity(&self) -> Entity {
+        self.neighbourhoods.all_boids[self.index].0
+    }
+
+    /// Neighbours under the boid's neighbourhood mode, with `radius` used when
+    /// that mode is metric.
+    pub fn within(&self, radius: f32) -> Vec<Neighbour> {
+        let (_, position, _) = self.neighbourhoods.all_boids[self.index];
+        self.neighbourhoods
+            .neighbours(Some(self.index), position, radius, self.neighbourhood)
+    }
+}
@@ -0,0 +1,182 @@
+use super::{heading, repulsion, stacked_direction, FlockingModel, Neighbour, NeighbourQuery};
+use crate::{components::BoidData, random};
+use amethyst::ecs::Entity;
+use nalgebra::Vector2;
+use rand::distributions::{Distribution, Uniform};
+
+/// Reynolds' separation, alignment and cohesion rules plus a random wander,
+/// weighted and sized by the boid's own `BoidData`.
+pub struct Reynolds;
+
+impl FlockingModel for Reynolds {
+    fn steering(
+        &self,
+        boid_data: &BoidData,
+        position: Vector2<f32>,
+        velocity: Vector2<f32>,
+        neighbours: &NeighbourQuery,
+        _delta_seconds: f32,
+    ) -> Vector2<f32> {
+        let weighted_vec = boid_data.separation_weight
+            * self.separation(
+                boid_data,
+                neighbours.entity(),
+                position,
+                &neighbours.within(boid_data.separation_radius),
+            )
+            + boid_data.alignment_weight
+                * self.alignment(velocity, &neighbours.within(boid_data.alignment_radius))
+            + boid_data.cohesion_weight
+                * self.cohesion(position, &neighbours.within(boid_data.cohesion_radius))
+            + boid_data.noise_weight * self.noise(boid_data);
+
+        if weighted_vec.x.is_nan() || weighted_vec.y.is_nan() {
+            return Vector2::new(0.0, 0.0);
+        }
+        weighted_vec
+    }
+}
+
+impl Reynolds {
+    pub fn noise(&self, boid_data: &BoidData) -> Vector2<f32> {
+        let mut rng = random::rng();
+        let angle_dist = Uniform::new(0., 2. * std::f32::consts::PI);
+        let speed_dist = Uniform::new(0., 1.);
+        let angle = angle_dist.sample(&mut rng);
+        let speed = speed_dist.sample(&mut rng);
+        boid_data.max_speed * speed * heading(angle)
+    }
+
+    pub fn separation(
+        &self,
+        boid_data: &BoidData,
+        entity: Entity,
+        position: Vector2<f32>,
+        neighbours: &[Neighbour],
+    ) -> Vector2<f32> {
+        neighbours
+            .iter()
+            .fold(Vector2::new(0.0, 0.0), |prev, (other, pos, _)| {
+                prev + repulsion(
+                    position - pos,
+                    boid_data.separation_radius,
+                    boid_data.separation_falloff,
+                    stacked_direction(entity, *other),
+                )
+            })
+    }
+
+    pub fn alignment(&self, velocity: Vector2<f32>, neighbours: &[Neighbour]) -> Vector2<f32> {
+        if neighbours.is_empty() {
+            return Vector2::new(0.0, 0.0);
+        }
+
+        let avg_direction = neighbours
+            .iter()
+            .fold(Vector2::new(0.0, 0.0), |prev, (_, _, curr)| prev + curr)
+            / neighbours.len() as f32;
+        avg_direction - velocity
+    }
+
+    pub fn cohesion(&self, position: Vector2<f32>, neighbours: &[Neighbour]) -> Vector2<f32> {
+        if neighbours.is_empty() {
+            return Vector2::new(0.0, 0.0);
+        }
+
+        let avg_position = neighbours
+            .iter()
+            .fold(Vector2::new(0.0, 0.0), |prev, (_, pos, _)| prev + pos)
+            / (neighbours.len() as f32);
+        avg_position - position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        components::Falloff,
+        flocking::tests::{assert_close, boid_data},
+    };
+    use amethyst::ecs::prelude::*;
+
+    fn neighbour(world: &mut World, position: (f32, f32), velocity: (f32, f32)) -> Neighbour {
+        (
+            world.create_entity().build(),
+            Vector2::new(position.0, position.1),
+            Vector2::new(velocity.0, velocity.1),
+        )
+    }
+
+    #[test]
+    fn separation_falloffs() {
+        let mut world = World::new();
+        let entity = world.create_entity().build();
+        let neighbour = neighbour(&mut world, (25.0, 0.0), (0.0, 0.0));
+        let mut data = boid_data();
+
+        let expected = [
+            (Falloff::Linear, -25.0),
+            (Falloff::Inverse, -50.0),
+            (Falloff::InverseSquare, -150.0),
+        ];
+        for (falloff, x) in expected.iter() {
+            data.separation_falloff = *falloff;
+            let separation =
+                Reynolds.separation(&data, entity, Vector2::new(0.0, 0.0), &[neighbour]);
+            assert_close(separation, Vector2::new(*x, 0.0));
+        }
+    }
+
+    #[test]
+    fn separation_sums_neighbours_and_ignores_far_ones() {
+        let mut world = World::new();
+        let entity = world.create_entity().build();
+        let neighbours = [
+            neighbour(&mut world, (25.0, 0.0), (0.0, 0.0)),
+            neighbour(&mut world, (0.0, -40.0), (0.0, 0.0)),
+            neighbour(&mut world, (60.0, 0.0), (0.0, 0.0)),
+        ];
+
+        let separation =
+            Reynolds.separation(&boid_data(), entity, Vector2::new(0.0, 0.0), &neighbours);
+        assert_close(separation, Vector2::new(-25.0, 10.0));
+    }
+
+    #[test]
+    fn stacked_boids_are_pushed_apart() {
+        let mut world = World::new();
+        let first = world.create_entity().build();
+        let second = world.create_entity().build();
+        let data = boid_data();
+        let at_origin = |entity| (entity, Vector2::new(0.0, 0.0), Vector2::new(0.0, 0.0));
+
+        let pushes = [(first, second), (second, first)].map(|(entity, other)| {
+            Reynolds.separation(&data, entity, Vector2::new(0.0, 0.0), &[at_origin(other)])
+        });
+        assert!(pushes[0].norm() > 0.0);
+        assert_close(pushes[0], -pushes[1]);
+    }
+
+    #[test]
+    fn alignment_and_cohesion_average_neighbours() {
+        let mut world = World::new();
+        let neighbours = [
+            neighbour(&mut world, (10.0, 0.0), (0.0, 10.0)),
+            neighbour(&mut world, (0.0, 20.0), (10.0, 0.0)),
+        ];
+
+        assert_close(
+            Reynolds.alignment(Vector2::new(10.0, 0.0), &neighbours),
+            Vector2::new(-5.0, 5.0),
+        );
+        assert_close(
+            Reynolds.cohesion(Vector2::new(0.0, 0.0), &neighbours),
+            Vector2::new(5.0, 10.0),
+        );
+        assert_close(
+            Reynolds.alignment(Vector2::new(10.0, 0.0), &[]),
+            Vector2::new(0.0, 0.0),
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use nalgebra::Vector2;
+
+/// Steering that turns `velocity` into heading straight for `target` at
+/// `max_speed`.
+pub fn seek(
+    position: Vector2<f32>,
+    velocity: Vector2<f32>,
+    target: Vector2<f32>,
+    max_speed: f32,
+) -> Vector2<f32> {
+    arrive(position, velocity, target, max_speed, 0.0)
+}
+
+/// Like `seek`, but the desired speed ramps down linearly to zero over the
+/// last `slowing_radius` before `target`.
+pub fn arrive(
+    position: Vector2<f32>,
+    velocity: Vector2<f32>,
+    target: Vector2<f32>,
+    max_speed: f32,
+    slowing_radius: f32,
+) -> Vector2<f32> {
+    let offset = target - position;
+    let distance = offset.norm();
+    if distance == 0.0 {
+        return -velocity;
+    }
+
+    let speed = if distance < slowing_radius {
+        max_speed * distance / slowing_radius
+    } else {
+        max_speed
+    };
+    offset / distance * speed - velocity
+}
+
+/// Arrival at the point `behind_distance` behind a leader, along its heading.
+pub fn follow(
+    position: Vector2<f32>,
+    velocity: Vector2<f32>,
+    leader: (Vector2<f32>, Vector2<f32>),
+    behind_distance: f32,
+    max_speed: f32,
+    slowing_radius: f32,
+) -> Vector2<f32> {
+    let (leader_position, leader_velocity) = leader;
+    let behind = match leader_velocity.try_normalize(0.0) {
+        Some(heading) => leader_position - heading * behind_distance,
+        None => leader_position,
+    };
+    arrive(position, velocity, behind, max_speed, slowing_radius)
+}
+
+/// Sideways push out of the rectangle `distance` long and `distance` wide
+/// that lies ahead of a moving leader, strongest along its centre line.
+pub fn evade(
+    position: Vector2<f32>,
+    leader: (Vector2<f32>, Vector2<f32>),
+    distance: f32,
+    max_speed: f32,
+) -> Vector2<f32> {
+    let (leader_position, leader_velocity) = leader;
+    let heading = match leader_velocity.try_normalize(0.0) {
+        Some(heading) => heading,
+        None => return Vector2::new(0.0, 0.0),
+    };
+
+    let offset = position - leader_position;
+    let ahead = offset.dot(&heading);
+    let lateral = offset - heading * ahead;
+    let half_width = distance / 2.0;
+    if ahead <= 0.0 || ahead > distance || lateral.norm() >= half_width {
+        return Vector2::new(0.0, 0.0);
+    }
+
+    let side = lateral
+        .try_normalize(0.0)
+        .unwrap_or_else(|| Vector2::new(-heading.y, heading.x));
+    side * max_speed * (1.0 - lateral.norm() / half_width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flocking::tests::assert_close;
+
+    #[test]
+    fn seek_heads_for_target_at_full_speed() {
+        let steering = seek(
+            Vector2::new(0.0, 0.0),
+            Vector2::new(10.0, 0.0),
+            Vector2::new(0.0, 5.0),
+            100.0,
+        );
+        assert_close(steering, Vector2::new(-10.0, 100.0));
+    }
+
+    #[test]
+    fn arrive_slows_down_near_target() {
+        let far = arrive(
+            Vector2::new(0.0, 0.0),
+            Vector2::new(0.0, 0.0),
+            Vector2::new(300.0, 0.0),
+            100.0,
+            200.0,
+        );
+        assert_close(far, Vector2::new(100.0, 0.0));
+
+        let near = arrive(
+            Vector2::new(0.0, 0.0),
+            Vector2::new(0.0, 0.0),
+            Vector2::new(50.0, 0.0),
+            100.0,
+            200.0,
+        );
+        assert_close(near, Vector2::new(25.0, 0.0));
+
+        let there = arrive(
+            Vector2::new(0.0, 0.0),
+            Vector2::new(20.0, 0.0),
+            Vector2::new(0.0, 0.0),
+            100.0,
+            200.0,
+        );
+        assert_close(there, Vector2::new(-20.0, 0.0));
+    }
+
+    #[test]
+    fn follow_aims_behind_moving_leader() {
+        let leader = (Vector2::new(100.0, 0.0), Vector2::new(0.0, 10.0));
+        let steering = follow(
+            Vector2::new(0.0, 0.0),
+            Vector2::new(0.0, 0.0),
+            leader,
+            50.0,
+            100.0,
+            0.0,
+        );
+        assert_close(
+            steering,
+            Vector2::new(100.0, -50.0) / 125.0f32.sqrt() * 10.0,
+        );
+
+        // A stationary leader has no behind, so go straight for it
+        let steering = follow(
+            Vector2::new(0.0, 0.0),
+            Vector2::new(0.0, 0.0),
+            (Vector2::new(100.0, 0.0), Vector2::new(0.0, 0.0)),
+            50.0,
+            100.0,
+            0.0,
+        );
+        assert_close(steering, Vector2::new(100.0, 0.0));
+    }
+
+    #[test]
+    fn evade_only_ahead_of_leader() {
+        let leader = (Vector2::new(0.0, 0.0), Vector2::new(10.0, 0.0));
+        assert_close(
+            evade(Vector2::new(50.0, 25.0), leader, 100.0, 100.0),
+            Vector2::new(0.0, 50.0),
+        );
+        assert_close(
+            evade(Vector2::new(50.0, -25.0), leader, 100.0, 100.0),
+            Vector2::new(0.0, -50.0),
+        );
+        // Behind, beyond the look ahead and off to the side
+        for position in [(-50.0, 0.0), (150.0, 0.0), (50.0, 60.0)].iter() {
+            assert_eq!(
+                evade(Vector2::new(position.0, position.1), leader, 100.0, 100.0),
+                Vector2::new(0.0, 0.0)
+            );
+        }
+        // Dead ahead still picks a side
+        assert_close(
+            evade(Vector2::new(50.0, 0.0), leader, 100.0, 100.0),
+            Vector2::new(0.0, 100.0),
+        );
+    }
+}
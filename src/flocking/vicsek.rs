@@ -0,0 +1,103 @@
+use super::{heading, validate_parameters, FlockingModel, NeighbourQuery};
+use crate::{components::BoidData, random};
+use anyhow::Result;
+use nalgebra::Vector2;
+use rand::distributions::{Distribution, Uniform};
+use serde::{Deserialize, Serialize};
+
+/// Vicsek's model: every boid moves at a constant speed along the average
+/// heading of itself and its neighbours, perturbed by uniform noise.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VicsekParams {
+    pub radius: f32,
+    pub speed: f32,
+    /// Width of the uniform noise added to the heading, in radians.
+    pub noise: f32,
+}
+
+impl VicsekParams {
+    /// Checks the radius, speed and noise are finite and not negative.
+    pub fn validate(&self) -> Result<()> {
+        validate_parameters(&[
+            ("radius", self.radius),
+            ("speed", self.speed),
+            ("noise", self.noise),
+        ])
+    }
+}
+
+impl FlockingModel for VicsekParams {
+    fn steering(
+        &self,
+        _boid_data: &BoidData,
+        _position: Vector2<f32>,
+        velocity: Vector2<f32>,
+        neighbours: &NeighbourQuery,
+        _delta_seconds: f32,
+    ) -> Vector2<f32> {
+        let headings = neighbours
+            .within(self.radius)
+            .into_iter()
+            .map(|(_, _, v)| v)
+            .chain(std::iter::once(velocity))
+            .filter(|v| v.norm() > 0.0)
+            .fold(Vector2::new(0.0, 0.0), |prev, v| prev + v.normalize());
+
+        let mut angle = if headings.norm() > 0.0 {
+            headings.y.atan2(headings.x)
+        } else {
+            velocity.y.atan2(velocity.x)
+        };
+        if self.noise > 0.0 {
+            angle += Uniform::new(-self.noise / 2.0, self.noise / 2.0).sample(&mut random::rng());
+        }
+
+        heading(angle) * self.speed - velocity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flocking::tests::{assert_close, boid_data, query_first};
+
+    #[test]
+    fn aligns_with_neighbours_at_constant_speed() {
+        let params = VicsekParams {
+            radius: 50.0,
+            speed: 4.0,
+            noise: 0.0,
+        };
+        let velocity = Vector2::new(10.0, 0.0);
+        let boids = [
+            ((0.0, 0.0), (10.0, 0.0)),
+            ((20.0, 0.0), (0.0, 1.0)),
+            ((200.0, 0.0), (0.0, -1.0)),
+        ];
+
+        let new_velocity = query_first(&boids, |query| {
+            velocity + params.steering(&boid_data(), Vector2::new(0.0, 0.0), velocity, query, 1.0)
+        });
+        let expected = 4.0 / 2.0f32.sqrt();
+        assert_close(new_velocity, Vector2::new(expected, expected));
+    }
+
+    #[test]
+    fn noise_stays_within_bounds() {
+        let params = VicsekParams {
+            radius: 50.0,
+            speed: 1.0,
+            noise: 0.5,
+        };
+        let velocity = Vector2::new(1.0, 0.0);
+
+        for _ in 0..100 {
+            let new_velocity = query_first(&[((0.0, 0.0), (1.0, 0.0))], |query| {
+                velocity
+                    + params.steering(&boid_data(), Vector2::new(0.0, 0.0), velocity, query, 1.0)
+            });
+            assert!((new_velocity.norm() - 1.0).abs() < 1e-4);
+            assert!(new_velocity.y.atan2(new_velocity.x).abs() <= 0.25);
+        }
+    }
+}
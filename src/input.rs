@@ -4,11 +4,20 @@ use amethyst::input::BindingTypes;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
-pub enum AxisBinding {}
+pub enum AxisBinding {
+    Horizontal,
+    Vertical,
+}
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ActionBinding {
     Place,
+    PlaceAttractor,
+    SpawnBurst,
+    Erase,
+    Clear,
+    Select,
+    ToggleLeader,
 }
 
 impl Display for AxisBinding {
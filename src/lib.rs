@@ -0,0 +1,17 @@
+pub mod batch;
+pub mod components;
+pub mod entities;
+pub mod evolution;
+pub mod export;
+pub mod flocking;
+pub mod input;
+#[cfg(feature = "osc")]
+pub mod osc;
+pub mod random;
+pub mod resources;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod simulation;
+pub mod spatial;
+pub mod state;
+pub mod systems;
@@ -1,60 +1,334 @@
 use amethyst::{
-    core::transform::TransformBundle,
-    input::{InputBundle, StringBindings},
+    config::Config,
+    core::{transform::TransformBundle, SystemExt},
+    input::InputBundle,
     prelude::*,
     renderer::{
-        plugins::{RenderFlat2D, RenderToWindow},
-        types::DefaultBackend,
+        plugins::{RenderDebugLines, RenderFlat2D, RenderToWindow},
         RenderingBundle,
     },
+    ui::{RenderUi, UiBundle},
     utils::application_root_dir,
+    window::DisplayConfig,
 };
+use anyhow::{anyhow, ensure, Context};
+use clap::Parser;
+use log::error;
+use std::path::{Path, PathBuf};
 
-mod components;
-mod entities;
-mod input;
-mod resources;
-mod state;
-mod systems;
+#[cfg(feature = "osc")]
+use boids::osc::{OscConfig, OscReceiveSystem, OscSendSystem};
+#[cfg(feature = "server")]
+use boids::server::{FrameFormat, Server, ServerSystem, Transport};
+use boids::{
+    input, random,
+    resources::{self, SimulationState},
+    state, systems,
+};
+
+/// Backend the viewer draws with. Amethyst only defines `DefaultBackend` when
+/// exactly one is enabled, and building the whole workspace also enables
+/// `empty` for the Python and C bindings.
+#[cfg(feature = "vulkan")]
+type Backend = amethyst::renderer::rendy::vulkan::Backend;
+#[cfg(all(feature = "metal", not(feature = "vulkan")))]
+type Backend = amethyst::renderer::rendy::metal::Backend;
+#[cfg(not(any(feature = "vulkan", feature = "metal")))]
+type Backend = amethyst::renderer::types::DefaultBackend;
+
+/// Interactive boids viewer. Space pauses and resumes the simulation, T turns
+/// trails on and off, V changes what boids are coloured by, F5 saves a
+/// snapshot of every boid to `snapshot.ron` and F6 exports the world as vector
+/// graphics to `flock.svg`.
+#[derive(Parser)]
+#[command(version, about)]
+struct Args {
+    /// Scenario to load. Relative paths are looked up in
+    /// `resources/scenarios`.
+    #[arg(short, long, default_value = "default.ron")]
+    scenario: PathBuf,
+    /// Seed for every random choice, instead of one drawn from entropy.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Boids of the scenario's first species to scatter over the window on
+    /// start.
+    #[arg(short = 'n', long, default_value_t = 0)]
+    boids: usize,
+    /// Window size in pixels, as WIDTHxHEIGHT. Defaults to the size in
+    /// `config/display.ron`.
+    #[arg(short, long, value_parser = parse_size)]
+    window: Option<(u32, u32)>,
+    /// Simulated seconds that pass per second of real time.
+    #[arg(short, long, default_value_t = 1.0)]
+    time_scale: f32,
+    /// CSV file to record the position and velocity of every boid to each
+    /// frame.
+    #[arg(short, long)]
+    record: Option<PathBuf>,
+    /// Start with the simulation paused.
+    #[arg(short, long)]
+    paused: bool,
+    /// Snapshot of boids to restore on start, as saved with F5.
+    #[arg(long)]
+    snapshot: Option<PathBuf>,
+    /// Address to stream boid states to and take commands from, such as
+    /// `127.0.0.1:7878`.
+    #[cfg(feature = "server")]
+    #[arg(long)]
+    serve: Option<String>,
+    /// Encoding of the streamed boid states, `json` or `binary`.
+    #[cfg(feature = "server")]
+    #[arg(long, default_value = "json", value_parser = parse_format)]
+    serve_format: FrameFormat,
+    /// How clients connect to the server, `tcp` or `websocket`.
+    #[cfg(feature = "server")]
+    #[arg(long, default_value = "tcp", value_parser = parse_transport)]
+    serve_transport: Transport,
+    /// Send boid states and metrics over OSC and take parameter changes from
+    /// it, as set up in `config/osc.ron`.
+    #[cfg(feature = "osc")]
+    #[arg(long)]
+    osc: bool,
+}
+
+fn parse_size(size: &str) -> anyhow::Result<(u32, u32)> {
+    let (width, height) = size
+        .split_once('x')
+        .ok_or_else(|| anyhow!("Expected WIDTHxHEIGHT, got {}", size))?;
+    let (width, height) = (width.trim().parse()?, height.trim().parse()?);
+    ensure!(
+        width > 0 && height > 0,
+        "The window has to be at least 1x1, got {}",
+        size
+    );
+    Ok((width, height))
+}
+
+#[cfg(feature = "server")]
+fn parse_format(format: &str) -> anyhow::Result<FrameFormat> {
+    match format {
+        "json" => Ok(FrameFormat::Json),
+        "binary" => Ok(FrameFormat::Binary),
+        _ => Err(anyhow!("Expected json or binary, got {}", format)),
+    }
+}
+
+#[cfg(feature = "server")]
+fn parse_transport(transport: &str) -> anyhow::Result<Transport> {
+    match transport {
+        "tcp" => Ok(Transport::Tcp),
+        "websocket" => Ok(Transport::WebSocket),
+        _ => Err(anyhow!("Expected tcp or websocket, got {}", transport)),
+    }
+}
 
-fn main() -> amethyst::Result<()> {
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
     amethyst::start_logger(Default::default());
+    ensure!(
+        args.time_scale.is_finite() && args.time_scale > 0.0,
+        "The time scale has to be positive and finite"
+    );
+
+    if let Some(seed) = args.seed {
+        random::seed_all(seed);
+    }
 
     let app_root = application_root_dir()?;
 
     let resources = app_root.join("resources");
     let config = app_root.join("config");
-    let display_config = config.join("display.ron");
-    let key_bindings_path = config.join("input.ron");
+    let scenario_path = resources.join("scenarios").join(&args.scenario);
+    let mut scenario = resources::Scenario::load(&scenario_path)
+        .with_context(|| format!("Failed to load scenario {}", scenario_path.display()))?;
+    scenario.flow.load_grids(&resources.join("flows"))?;
+    scenario.validate()?;
+
+    let manifest_path = resources.join("sprites").join("manifest.ron");
+    let sprites = resources::SpriteManifest::load(&manifest_path)
+        .with_context(|| format!("Failed to load sprites {}", manifest_path.display()))?;
+    sprites.validate()?;
+    sprites.validate_frames(&resources.join("sprites"))?;
+    for name in sprites.missing(&scenario) {
+        error!(
+            "The scenario uses sprite {:?}, which is not in {}; drawing it as {:?}",
+            name,
+            manifest_path.display(),
+            sprites.default
+        );
+    }
+
+    let snapshot = match &args.snapshot {
+        Some(path) => {
+            let snapshot = resources::Snapshot::load(path)
+                .with_context(|| format!("Failed to load snapshot {}", path.display()))?;
+            snapshot.validate(&scenario)?;
+            Some(snapshot)
+        }
+        None => None,
+    };
+
+    let mut display_config = DisplayConfig::load(config.join("display.ron"))?;
+    if let Some(dimensions) = args.window {
+        display_config.dimensions = Some(dimensions);
+        display_config.min_dimensions = Some(dimensions);
+        display_config.max_dimensions = Some(dimensions);
+    }
+
+    let recording = args
+        .record
+        .as_deref()
+        .map(systems::RecordingSystem::create)
+        .transpose()?;
+    #[cfg(feature = "server")]
+    let server = args
+        .serve
+        .as_deref()
+        .map(|address| Server::bind(address, args.serve_transport, args.serve_format, &resources))
+        .transpose()?;
+    #[cfg(feature = "osc")]
+    let osc = if args.osc {
+        let osc_config = OscConfig::load(config.join("osc.ron"))?;
+        osc_config.validate()?;
+        (
+            OscSendSystem::create(&osc_config)?,
+            OscReceiveSystem::create(&osc_config)?,
+        )
+    } else {
+        (None, None)
+    };
+
+    let state = state::MyState {
+        initial_boids: args.boids,
+        time_scale: args.time_scale,
+        paused: args.paused,
+        snapshot,
+        sprites,
+    };
+    run(
+        &app_root,
+        display_config,
+        scenario,
+        recording,
+        #[cfg(feature = "server")]
+        server,
+        #[cfg(feature = "osc")]
+        osc,
+        state,
+    )
+    .map_err(|e| anyhow!("Failed to run the viewer: {}", e))
+}
 
-    let game_data = GameDataBuilder::default()
+fn run(
+    app_root: &Path,
+    display_config: DisplayConfig,
+    scenario: resources::Scenario,
+    recording: Option<systems::RecordingSystem>,
+    #[cfg(feature = "server")] server: Option<Server>,
+    #[cfg(feature = "osc")] osc: (Option<OscSendSystem>, Option<OscReceiveSystem>),
+    state: state::MyState,
+) -> amethyst::Result<()> {
+    let key_bindings_path = app_root.join("config").join("input.ron");
+    let flow = scenario.flow.clone();
+    let trails = scenario.trails.clone();
+
+    let mut game_data = GameDataBuilder::default()
         .with_bundle(TransformBundle::new())?
         .with_bundle(
-            RenderingBundle::<DefaultBackend>::new()
+            RenderingBundle::<Backend>::new()
                 .with_plugin(
-                    RenderToWindow::from_config_path(display_config)?
-                        .with_clear([0.34, 0.36, 0.52, 1.0]),
+                    RenderToWindow::from_config(display_config).with_clear([0.34, 0.36, 0.52, 1.0]),
                 )
-                .with_plugin(RenderFlat2D::default()),
+                .with_plugin(RenderFlat2D::default())
+                .with_plugin(RenderDebugLines::default())
+                .with_plugin(RenderUi::default()),
         )?
         .with_bundle(
             InputBundle::<input::ControlBindingTypes>::new()
-                .with_bindings_from_file(&key_bindings_path)?,
+                .with_bindings_from_file(key_bindings_path)?,
         )?
-        .with(systems::BoidSystem, "boid_system", &[])
-        .with(systems::PhysicsSystem, "physics_system", &["boid_system"])
+        .with_bundle(UiBundle::<input::ControlBindingTypes>::new())?
+        .with(
+            systems::BoidSystem.pausable(SimulationState::Running),
+            "boid_system",
+            &[],
+        )
+        .with(
+            systems::LeaderControlSystem::default().pausable(SimulationState::Running),
+            "leader_control_system",
+            &["boid_system"],
+        )
+        .with(
+            systems::PhysicsSystem.pausable(SimulationState::Running),
+            "physics_system",
+            &["boid_system", "leader_control_system"],
+        )
+        .with(
+            systems::LifecycleSystem.pausable(SimulationState::Running),
+            "lifecycle_system",
+            &["physics_system"],
+        )
+        .with(
+            systems::TrailSystem.pausable(SimulationState::Running),
+            "trail_system",
+            &["physics_system"],
+        )
+        .with(
+            systems::SpriteAnimationSystem.pausable(SimulationState::Running),
+            "sprite_animation_system",
+            &["physics_system"],
+        )
         .with(
             systems::PositionSystem,
             "position_system",
             &["physics_system"],
         )
+        .with(systems::ObstacleDrawSystem, "obstacle_draw_system", &[])
+        .with(systems::GoalDrawSystem, "goal_draw_system", &[])
+        .with(systems::FlowDrawSystem, "flow_draw_system", &[])
+        .with(systems::FoodDrawSystem, "food_draw_system", &[])
+        .with(systems::SelectionDrawSystem, "selection_draw_system", &[])
+        .with(systems::TrailDrawSystem, "trail_draw_system", &[])
+        .with(systems::TintSystem, "tint_system", &["physics_system"])
+        .with(systems::LegendSystem::default(), "legend_system", &[])
         .with(
             systems::MouseInputSystem::default(),
             "mouse_input_system",
             &["position_system"],
         );
+    if let Some(recording) = recording {
+        game_data = game_data.with(
+            recording.pausable(SimulationState::Running),
+            "recording_system",
+            &["physics_system"],
+        );
+    }
+
+    #[cfg(feature = "server")]
+    if server.is_some() {
+        game_data = game_data.with(ServerSystem, "server_system", &["lifecycle_system"]);
+    }
+    #[cfg(feature = "osc")]
+    {
+        let (sender, receiver) = osc;
+        if let Some(sender) = sender {
+            game_data = game_data.with(sender, "osc_send_system", &["physics_system"]);
+        }
+        if let Some(receiver) = receiver {
+            game_data = game_data.with(receiver, "osc_receive_system", &[]);
+        }
+    }
 
-    let mut game = Application::new(resources, state::MyState, game_data)?;
+    let builder = Application::build(app_root.join("resources"), state)?
+        .with_resource(scenario)
+        .with_resource(flow)
+        .with_resource(trails);
+    #[cfg(feature = "server")]
+    let builder = match server {
+        Some(server) => builder.with_resource(server),
+        None => builder,
+    };
+    let mut game = builder.build(game_data)?;
     game.run();
 
     Ok(())
@@ -0,0 +1,152 @@
+//! Open Sound Control output and input, for driving sound installations from
+//! the flock and tweaking it live from a controller. Settings are read from
+//! `config/osc.ron`.
+mod receiver;
+mod sender;
+
+pub use self::receiver::OscReceiveSystem;
+pub use self::sender::OscSendSystem;
+
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+
+/// Where OSC goes to and comes from, and what it carries.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OscConfig {
+    /// Where boid states and metrics are sent, as `host:port`. Nothing is sent
+    /// without it.
+    #[serde(default)]
+    pub send_to: Option<String>,
+    /// Address parameter changes are received on. Nothing is received
+    /// without it.
+    #[serde(default)]
+    pub listen_on: Option<String>,
+    /// Times a second states and metrics are sent.
+    #[serde(default = "default_rate")]
+    pub rate: f32,
+    /// Whether every boid's state is sent along with the metrics.
+    #[serde(default)]
+    pub per_boid: bool,
+    /// Boids closer than this belong to the same cluster.
+    #[serde(default = "default_cluster_distance")]
+    pub cluster_distance: f32,
+    /// Addresses that set a `BoidData` parameter from a controller.
+    #[serde(default)]
+    pub mappings: Vec<OscMapping>,
+}
+
+impl Default for OscConfig {
+    fn default() -> Self {
+        OscConfig {
+            send_to: None,
+            listen_on: None,
+            rate: default_rate(),
+            per_boid: false,
+            cluster_distance: default_cluster_distance(),
+            mappings: vec![],
+        }
+    }
+}
+
+impl OscConfig {
+    /// Checks the rate, cluster distance and mapping ranges are usable, so
+    /// mistakes are reported on start rather than as NaNs sent to the
+    /// installation.
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            self.rate.is_finite() && self.rate > 0.0,
+            "The OSC rate is {}, but has to be positive and finite",
+            self.rate
+        );
+        ensure!(
+            self.cluster_distance.is_finite() && self.cluster_distance >= 0.0,
+            "The cluster distance is {}, but has to be finite and not negative",
+            self.cluster_distance
+        );
+        for mapping in &self.mappings {
+            let (min, max) = mapping.range;
+            ensure!(
+                min.is_finite() && max.is_finite(),
+                "{} maps to ({}, {}), but the range has to be finite",
+                mapping.address,
+                min,
+                max
+            );
+        }
+        Ok(())
+    }
+}
+
+fn default_rate() -> f32 {
+    30.0
+}
+
+fn default_cluster_distance() -> f32 {
+    50.0
+}
+
+/// An address whose argument, from 0 to 1 as controllers send, sets a
+/// `BoidData` parameter to somewhere in `range`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OscMapping {
+    pub address: String,
+    /// Species whose boids change. Every species without it.
+    #[serde(default)]
+    pub species: Option<String>,
+    /// Name of a numeric `BoidData` field.
+    pub parameter: String,
+    pub range: (f32, f32),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_and_ranges_are_validated() {
+        assert!(OscConfig::default().validate().is_ok());
+
+        let mapping = |range| OscMapping {
+            address: "/1/fader1".to_string(),
+            species: None,
+            parameter: "cohesion_weight".to_string(),
+            range,
+        };
+        let invalid = [
+            OscConfig {
+                rate: 0.0,
+                ..OscConfig::default()
+            },
+            OscConfig {
+                rate: f32::INFINITY,
+                ..OscConfig::default()
+            },
+            OscConfig {
+                rate: f32::NAN,
+                ..OscConfig::default()
+            },
+            OscConfig {
+                cluster_distance: -1.0,
+                ..OscConfig::default()
+            },
+            OscConfig {
+                mappings: vec![mapping((0.0, f32::NAN))],
+                ..OscConfig::default()
+            },
+            OscConfig {
+                mappings: vec![mapping((f32::NEG_INFINITY, 1.0))],
+                ..OscConfig::default()
+            },
+        ];
+        for config in &invalid {
+            assert!(config.validate().is_err(), "{:?}", config);
+        }
+
+        // Faders may run either way
+        let reversed = OscConfig {
+            mappings: vec![mapping((2.0, 0.0))],
+            ..OscConfig::default()
+        };
+        assert!(reversed.validate().is_ok());
+    }
+}
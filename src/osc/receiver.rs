@@ -0,0 +1,250 @@
+use super::{OscConfig, OscMapping};
+use crate::{
+    components::{BoidData, Species},
+    resources::Scenario,
+};
+use amethyst::ecs::{prelude::*, ReadStorage, System, WriteExpect, WriteStorage};
+use anyhow::{anyhow, Context, Result};
+use log::warn;
+use rosc::{decoder, OscMessage, OscPacket, OscType};
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+};
+
+/// Sets `BoidData` parameters from OSC messages, both for live boids and for
+/// those spawned later. Addresses of the configured mappings scale their
+/// argument into the mapping's range, and `/boids/<species>/<parameter>`
+/// sets a parameter of one species as is.
+pub struct OscReceiveSystem {
+    socket: UdpSocket,
+    mappings: Vec<OscMapping>,
+}
+
+impl OscReceiveSystem {
+    /// Receiver for `config`, or `None` if it doesn't listen anywhere.
+    pub fn create(config: &OscConfig) -> Result<Option<OscReceiveSystem>> {
+        let listen_on = match &config.listen_on {
+            Some(listen_on) => listen_on,
+            None => return Ok(None),
+        };
+        let socket = UdpSocket::bind(listen_on)
+            .with_context(|| format!("Failed to listen for OSC on {}", listen_on))?;
+        socket.set_nonblocking(true)?;
+        Ok(Some(OscReceiveSystem {
+            socket,
+            mappings: config.mappings.clone(),
+        }))
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// Every message that has arrived since the last call.
+    fn receive(&self) -> Vec<OscMessage> {
+        let mut messages = vec![];
+        let mut buffer = [0; 65536];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((read, from)) => match decoder::decode_udp(&buffer[..read]) {
+                    Ok((_, packet)) => unbundle(packet, &mut messages),
+                    Err(e) => warn!("Invalid OSC packet from {}: {:?}", from, e),
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    warn!("Failed to receive OSC: {}", e);
+                    break;
+                }
+            }
+        }
+        messages
+    }
+
+    /// Species and parameter `message` sets, and the value it sets it to.
+    fn parameter(&self, message: &OscMessage) -> Result<(Option<String>, String, f32)> {
+        let value = match message.args.first() {
+            Some(OscType::Float(value)) => *value,
+            Some(OscType::Double(value)) => *value as f32,
+            Some(OscType::Int(value)) => *value as f32,
+            _ => return Err(anyhow!("{} needs a number", message.addr)),
+        };
+
+        if let Some(mapping) = self.mappings.iter().find(|m| m.address == message.addr) {
+            let (min, max) = mapping.range;
+            let value = min + value.max(0.0).min(1.0) * (max - min);
+            return Ok((mapping.species.clone(), mapping.parameter.clone(), value));
+        }
+        match message.addr.split('/').collect::<Vec<_>>().as_slice() {
+            ["", "boids", species, parameter] => {
+                Ok((Some(species.to_string()), parameter.to_string(), value))
+            }
+            _ => Err(anyhow!("{} is not mapped to a parameter", message.addr)),
+        }
+    }
+}
+
+impl<'s> System<'s> for OscReceiveSystem {
+    type SystemData = (
+        ReadStorage<'s, Species>,
+        WriteStorage<'s, BoidData>,
+        WriteExpect<'s, Scenario>,
+    );
+
+    fn run(&mut self, (species, mut boid_datas, mut scenario): Self::SystemData) {
+        for message in self.receive() {
+            let applied = self
+                .parameter(&message)
+                .and_then(|(name, parameter, value)| {
+                    let changes =
+                        |config_name: &str| name.as_deref().map_or(true, |n| n == config_name);
+                    // Every species and boid is given the value before any
+                    // are changed, so a value one of them can't take leaves
+                    // them all as they were
+                    let mut changed = vec![];
+                    for config in scenario.species.iter_mut().filter(|c| changes(&c.name)) {
+                        let mut boid = config.boid.clone();
+                        boid.set_parameter(&parameter, value)?;
+                        changed.push((&mut config.boid, boid));
+                    }
+                    if changed.is_empty() {
+                        return Err(anyhow!(
+                            "The scenario does not define species {}",
+                            name.unwrap_or_default()
+                        ));
+                    }
+                    for (species, boid_data) in (&species, &mut boid_datas).join() {
+                        if changes(&species.0) {
+                            let mut boid = boid_data.clone();
+                            boid.set_parameter(&parameter, value)?;
+                            changed.push((boid_data, boid));
+                        }
+                    }
+                    for (boid_data, boid) in changed {
+                        *boid_data = boid;
+                    }
+                    Ok(())
+                });
+            if let Err(e) = applied {
+                warn!("Ignoring OSC message: {}", e);
+            }
+        }
+    }
+}
+
+fn unbundle(packet: OscPacket, messages: &mut Vec<OscMessage>) {
+    match packet {
+        OscPacket::Message(message) => messages.push(message),
+        OscPacket::Bundle(bundle) => {
+            for packet in bundle.content {
+                unbundle(packet, messages);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::Simulation;
+    use amethyst::{config::Config, ecs::RunNow};
+    use rosc::encoder;
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn messages_set_parameters() {
+        let scenario =
+            Scenario::load_bytes(include_bytes!("../../resources/scenarios/murmuration.ron"))
+                .unwrap();
+        let simulation = Simulation::new(scenario).unwrap();
+        let config = OscConfig {
+            listen_on: Some("127.0.0.1:0".to_string()),
+            mappings: vec![OscMapping {
+                address: "/1/fader1".to_string(),
+                species: None,
+                parameter: "cohesion_weight".to_string(),
+                range: (1.0, 3.0),
+            }],
+            ..OscConfig::default()
+        };
+        let mut receiver = OscReceiveSystem::create(&config).unwrap().unwrap();
+
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let send = |address: &str, value: f32| {
+            let packet = OscPacket::Message(OscMessage {
+                addr: address.to_string(),
+                args: vec![OscType::Float(value)],
+            });
+            let bytes = encoder::encode(&packet).unwrap();
+            sender
+                .send_to(&bytes, receiver.local_addr().unwrap())
+                .unwrap();
+        };
+        send("/1/fader1", 0.5);
+        send("/boids/falcon/max_speed", 42.0);
+        // Boids can't be spawned without a top speed
+        send("/boids/falcon/max_speed", 0.0);
+        send("/boids/falcon/neighbourhood", 1.0);
+        send("/unmapped", 1.0);
+        thread::sleep(Duration::from_millis(50));
+        receiver.run_now(&simulation.world);
+
+        let (species, boid_datas) = simulation
+            .world
+            .system_data::<(ReadStorage<Species>, ReadStorage<BoidData>)>();
+        for (species, boid_data) in (&species, &boid_datas).join() {
+            assert_eq!(boid_data.cohesion_weight, 2.0);
+            assert_eq!(boid_data.max_speed == 42.0, species.0 == "falcon");
+        }
+        let scenario = simulation.world.read_resource::<Scenario>();
+        assert_eq!(
+            scenario.species_named("falcon").unwrap().boid.max_speed,
+            42.0
+        );
+    }
+    #[test]
+    fn messages_change_every_target_or_none() {
+        let scenario =
+            Scenario::load_bytes(include_bytes!("../../resources/scenarios/murmuration.ron"))
+                .unwrap();
+        let simulation = Simulation::new(scenario).unwrap();
+        // A falcon with data it can't keep, as if it had been broken since
+        // it was spawned
+        {
+            let (species, mut boid_datas) = simulation
+                .world
+                .system_data::<(ReadStorage<Species>, WriteStorage<BoidData>)>();
+            let (_, boid_data) = (&species, &mut boid_datas)
+                .join()
+                .find(|(species, _)| species.0 == "falcon")
+                .unwrap();
+            boid_data.noise_weight = f32::NAN;
+        }
+        let config = OscConfig {
+            listen_on: Some("127.0.0.1:0".to_string()),
+            ..OscConfig::default()
+        };
+        let mut receiver = OscReceiveSystem::create(&config).unwrap().unwrap();
+        let packet = OscPacket::Message(OscMessage {
+            addr: "/boids/falcon/max_speed".to_string(),
+            args: vec![OscType::Float(42.0)],
+        });
+        UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .send_to(
+                &encoder::encode(&packet).unwrap(),
+                receiver.local_addr().unwrap(),
+            )
+            .unwrap();
+        thread::sleep(Duration::from_millis(50));
+        receiver.run_now(&simulation.world);
+
+        let boid_datas = simulation.world.read_storage::<BoidData>();
+        assert!(boid_datas.join().all(|data| data.max_speed != 42.0));
+        let scenario = simulation.world.read_resource::<Scenario>();
+        assert_ne!(
+            scenario.species_named("falcon").unwrap().boid.max_speed,
+            42.0
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use super::OscConfig;
+use crate::{
+    components::{BoidData, Position, Species, Velocity},
+    spatial::SpatialGrid,
+};
+use amethyst::{
+    core::Time,
+    ecs::{prelude::*, Entities, Read, ReadStorage, System},
+};
+use anyhow::{anyhow, Context, Result};
+use log::warn;
+use nalgebra::Vector2;
+use rosc::{encoder, OscBundle, OscMessage, OscPacket, OscType};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+/// Most messages in one bundle, keeping each datagram well under the size
+/// UDP allows.
+const MESSAGES_PER_BUNDLE: usize = 64;
+
+/// Sends the flock's metrics, and optionally every boid's state, over OSC at
+/// a fixed rate:
+///
+/// - `/boids/count i`
+/// - `/boids/centroid f f`
+/// - `/boids/polarization f`, from 0 when boids head every which way to 1
+///   when they all head the same way
+/// - `/boids/clusters i`
+/// - `/boids/boid i s f f f f` for each boid: entity id, species, position
+///   and velocity
+pub struct OscSendSystem {
+    socket: UdpSocket,
+    target: SocketAddr,
+    /// Seconds between sends.
+    interval: f32,
+    since_sent: f32,
+    per_boid: bool,
+    cluster_distance: f32,
+    /// Set once sending fails, so the error is only reported once.
+    failed: bool,
+}
+
+impl OscSendSystem {
+    /// Sender for `config`, or `None` if it doesn't send anywhere.
+    pub fn create(config: &OscConfig) -> Result<Option<OscSendSystem>> {
+        let send_to = match &config.send_to {
+            Some(send_to) => send_to,
+            None => return Ok(None),
+        };
+        let target = send_to
+            .to_socket_addrs()
+            .with_context(|| format!("Failed to resolve OSC target {}", send_to))?
+            .next()
+            .ok_or_else(|| anyhow!("OSC target {} has no address", send_to))?;
+        let bind_to = if target.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        Ok(Some(OscSendSystem {
+            socket: UdpSocket::bind(bind_to).context("Failed to open an OSC socket")?,
+            target,
+            interval: 1.0 / config.rate.max(f32::EPSILON),
+            // Send straight away
+            since_sent: f32::INFINITY,
+            per_boid: config.per_boid,
+            cluster_distance: config.cluster_distance,
+            failed: false,
+        }))
+    }
+
+    fn send(&mut self, messages: Vec<OscMessage>) {
+        for chunk in messages.chunks(MESSAGES_PER_BUNDLE) {
+            let bundle = OscPacket::Bundle(OscBundle {
+                // Immediately
+                timetag: (0, 1).into(),
+                content: chunk.iter().cloned().map(OscPacket::Message).collect(),
+            });
+            let sent = encoder::encode(&bundle)
+                .map_err(|e| anyhow!("{:?}", e))
+                .and_then(|bytes| Ok(self.socket.send_to(&bytes, self.target)?));
+            if let Err(e) = sent {
+                if !self.failed {
+                    warn!("Failed to send OSC to {}: {}", self.target, e);
+                    self.failed = true;
+                }
+                return;
+            }
+        }
+        self.failed = false;
+    }
+}
+
+impl<'s> System<'s> for OscSendSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, BoidData>,
+        ReadStorage<'s, Species>,
+        ReadStorage<'s, Position>,
+        ReadStorage<'s, Velocity>,
+        Read<'s, Time>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, boid_datas, species, positions, velocities, time): Self::SystemData,
+    ) {
+        self.since_sent += time.delta_real_seconds();
+        if self.since_sent < self.interval {
+            return;
+        }
+        self.since_sent = 0.0;
+
+        let boids = (&entities, &boid_datas, &species, &positions, &velocities)
+            .join()
+            .map(|(entity, _, species, position, velocity)| {
+                (entity.id(), species.0.clone(), position.0, velocity.0)
+            })
+            .collect::<Vec<_>>();
+        let count = boids.len().max(1) as f32;
+        let centroid = boids.iter().map(|boid| boid.2).sum::<Vector2<f32>>() / count;
+        let headings = boids
+            .iter()
+            .map(|boid| boid.3.try_normalize(0.0).unwrap_or_else(Vector2::zeros))
+            .sum::<Vector2<f32>>();
+        let grid = SpatialGrid::new(
+            self.cluster_distance,
+            boids.iter().map(|boid| boid.2).collect(),
+        );
+
+        let mut messages = vec![
+            message("/boids/count", vec![OscType::Int(boids.len() as i32)]),
+            message(
+                "/boids/centroid",
+                vec![OscType::Float(centroid.x), OscType::Float(centroid.y)],
+            ),
+            message(
+                "/boids/polarization",
+                vec![OscType::Float((headings / count).norm())],
+            ),
+            message(
+                "/boids/clusters",
+                vec![OscType::Int(grid.clusters(self.cluster_distance) as i32)],
+            ),
+        ];
+        if self.per_boid {
+            messages.extend(boids.into_iter().map(|(id, species, position, velocity)| {
+                message(
+                    "/boids/boid",
+                    vec![
+                        OscType::Int(id as i32),
+                        OscType::String(species),
+                        OscType::Float(position.x),
+                        OscType::Float(position.y),
+                        OscType::Float(velocity.x),
+                        OscType::Float(velocity.y),
+                    ],
+                )
+            }));
+        }
+        self.send(messages);
+    }
+}
+
+fn message(address: &str, args: Vec<OscType>) -> OscMessage {
+    OscMessage {
+        addr: address.to_string(),
+        args,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resources::Scenario, simulation::Simulation};
+    use amethyst::{config::Config, ecs::RunNow};
+    use rosc::decoder;
+    use std::time::Duration;
+
+    #[test]
+    fn metrics_and_boids_are_sent() {
+        let scenario =
+            Scenario::load_bytes(include_bytes!("../../resources/scenarios/murmuration.ron"))
+                .unwrap();
+        let simulation = Simulation::new(scenario).unwrap();
+        let boids = simulation.metrics().boids;
+
+        let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+        listener
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let config = OscConfig {
+            send_to: Some(listener.local_addr().unwrap().to_string()),
+            per_boid: true,
+            ..OscConfig::default()
+        };
+        let mut sender = OscSendSystem::create(&config).unwrap().unwrap();
+        sender.run_now(&simulation.world);
+
+        let mut messages = vec![];
+        let mut buffer = [0; 65536];
+        while messages.len() < 4 + boids {
+            let (read, _) = listener.recv_from(&mut buffer).unwrap();
+            match decoder::decode_udp(&buffer[..read]).unwrap().1 {
+                OscPacket::Bundle(bundle) => {
+                    assert!(bundle.content.len() <= MESSAGES_PER_BUNDLE);
+                    messages.extend(
+                        bundle
+                            .content
+                            .into_iter()
+                            .filter_map(|packet| match packet {
+                                OscPacket::Message(message) => Some(message),
+                                OscPacket::Bundle(_) => None,
+                            }),
+                    );
+                }
+                OscPacket::Message(_) => panic!("messages are always bundled"),
+            }
+        }
+
+        assert_eq!(messages[0].addr, "/boids/count");
+        assert_eq!(messages[0].args, vec![OscType::Int(boids as i32)]);
+        assert_eq!(messages[3].addr, "/boids/clusters");
+        assert!(messages[3].args[0].clone().int().unwrap() >= 1);
+        assert!(messages[4..].iter().all(|m| m.addr == "/boids/boid"));
+    }
+}
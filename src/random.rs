@@ -0,0 +1,88 @@
+use rand::{rngs::StdRng, Error, RngCore, SeedableRng};
+use std::{
+    cell::RefCell,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+/// Seed set by `seed_all` for threads that haven't drawn any numbers yet.
+static BASE_SEED: Mutex<Option<u64>> = Mutex::new(None);
+/// Threads that have seeded themselves from `BASE_SEED` so far.
+static SEEDED_THREADS: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static GENERATOR: RefCell<StdRng> = RefCell::new(new_generator());
+}
+
+fn new_generator() -> StdRng {
+    match *BASE_SEED.lock().unwrap() {
+        Some(base) => {
+            let thread = SEEDED_THREADS.fetch_add(1, Ordering::Relaxed) + 1;
+            StdRng::seed_from_u64(base.wrapping_add(thread))
+        }
+        None => StdRng::from_entropy(),
+    }
+}
+
+/// Handle on the random number generator of the current thread, which every
+/// random choice in the simulation is drawn from. It starts from entropy, and
+/// `seed` makes whatever runs on the thread afterwards reproducible.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SimRng;
+
+pub fn rng() -> SimRng {
+    SimRng
+}
+
+/// Restarts the current thread's generator from `seed`.
+pub fn seed(seed: u64) {
+    GENERATOR.with(|generator| *generator.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+/// Restarts the current thread's generator from `seed`, and has every thread
+/// that first draws a number afterwards, like the workers of a dispatcher
+/// built later, start from a seed derived from it. Which worker gets which
+/// seed still depends on scheduling, so only sequential runs repeat exactly.
+pub fn seed_all(seed: u64) {
+    *BASE_SEED.lock().unwrap() = Some(seed);
+    self::seed(seed);
+}
+
+impl RngCore for SimRng {
+    fn next_u32(&mut self) -> u32 {
+        GENERATOR.with(|generator| generator.borrow_mut().next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        GENERATOR.with(|generator| generator.borrow_mut().next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        GENERATOR.with(|generator| generator.borrow_mut().fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        GENERATOR.with(|generator| generator.borrow_mut().try_fill_bytes(dest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn seeding_repeats_the_sequence() {
+        seed(42);
+        let first = (0..5).map(|_| rng().gen::<u32>()).collect::<Vec<_>>();
+        seed(42);
+        let second = (0..5).map(|_| rng().gen::<u32>()).collect::<Vec<_>>();
+        seed(43);
+        let other = (0..5).map(|_| rng().gen::<u32>()).collect::<Vec<_>>();
+
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{
+    export::{hue_colour, palette_colour, ramp_colour, Colour},
+    resources::Scenario,
+};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{FRAC_PI_2, PI};
+
+/// Neighbours within its cohesion radius at which a boid is coloured as
+/// crowded as can be.
+pub const CROWDED_NEIGHBOURS: usize = 20;
+/// Boids closer than this to any boid of a cluster belong to it.
+pub const CLUSTER_DISTANCE: f32 = 50.0;
+
+/// Colour of the legend's heading, and of lines without a colour of their
+/// own.
+const LEGEND_TEXT: Colour = [255, 255, 255, 255];
+
+/// What boids' sprites are tinted by in the viewer. V switches to the next
+/// one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColourMode {
+    /// The sprite's own colours.
+    #[default]
+    Plain,
+    Species,
+    /// From blue when still to red at the boid's top speed.
+    Speed,
+    /// Around the hue wheel, from red for boids heading right.
+    Heading,
+    /// From blue for boids alone within their cohesion radius to red for
+    /// those with `CROWDED_NEIGHBOURS` or more.
+    Neighbours,
+    /// A colour for each cluster, as told apart by `CLUSTER_DISTANCE`.
+    Cluster,
+}
+
+impl ColourMode {
+    pub fn next(self) -> ColourMode {
+        match self {
+            ColourMode::Plain => ColourMode::Species,
+            ColourMode::Species => ColourMode::Speed,
+            ColourMode::Speed => ColourMode::Heading,
+            ColourMode::Heading => ColourMode::Neighbours,
+            ColourMode::Neighbours => ColourMode::Cluster,
+            ColourMode::Cluster => ColourMode::Plain,
+        }
+    }
+
+    /// Lines explaining what the colours mean, each in the colour it
+    /// explains, after a heading naming the mode.
+    pub fn legend(self, scenario: &Scenario) -> Vec<(String, Colour)> {
+        let name = match self {
+            ColourMode::Plain => "sprite",
+            ColourMode::Species => "species",
+            ColourMode::Speed => "speed",
+            ColourMode::Heading => "heading",
+            ColourMode::Neighbours => "neighbours",
+            ColourMode::Cluster => "cluster",
+        };
+        let mut legend = vec![(format!("Colour: {} (V to change)", name), LEGEND_TEXT)];
+        match self {
+            ColourMode::Plain => {}
+            ColourMode::Species => legend.extend(
+                scenario
+                    .species
+                    .iter()
+                    .enumerate()
+                    .map(|(index, species)| (species.name.clone(), palette_colour(index))),
+            ),
+            ColourMode::Speed => legend.extend(vec![
+                ("Still".to_string(), ramp_colour(0.0, 1.0)),
+                ("Half speed".to_string(), ramp_colour(0.5, 1.0)),
+                ("Top speed".to_string(), ramp_colour(1.0, 1.0)),
+            ]),
+            ColourMode::Heading => legend.extend(vec![
+                ("Right".to_string(), hue_colour(0.0)),
+                ("Up".to_string(), hue_colour(FRAC_PI_2)),
+                ("Left".to_string(), hue_colour(PI)),
+                ("Down".to_string(), hue_colour(PI + FRAC_PI_2)),
+            ]),
+            ColourMode::Neighbours => {
+                let crowded = CROWDED_NEIGHBOURS as f32;
+                legend.extend(vec![
+                    ("No neighbours".to_string(), ramp_colour(0.0, crowded)),
+                    (
+                        format!("{} neighbours", CROWDED_NEIGHBOURS / 2),
+                        ramp_colour(crowded / 2.0, crowded),
+                    ),
+                    (
+                        format!("{} or more neighbours", CROWDED_NEIGHBOURS),
+                        ramp_colour(crowded, crowded),
+                    ),
+                ]);
+            }
+            ColourMode::Cluster => legend.push((
+                format!("A colour for each group within {} units", CLUSTER_DISTANCE),
+                LEGEND_TEXT,
+            )),
+        }
+        legend
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst::config::Config;
+
+    #[test]
+    fn modes_cycle_and_explain_their_colours() {
+        let mut mode = ColourMode::default();
+        for _ in 0..6 {
+            mode = mode.next();
+        }
+        assert_eq!(mode, ColourMode::Plain);
+        assert_eq!(mode.next(), ColourMode::Species);
+
+        let scenario =
+            Scenario::load_bytes(include_bytes!("../../resources/scenarios/murmuration.ron"))
+                .unwrap();
+        assert_eq!(ColourMode::Plain.legend(&scenario).len(), 1);
+        let legend = ColourMode::Species.legend(&scenario);
+        assert_eq!(legend[2], ("falcon".to_string(), palette_colour(1)));
+
+        let legend = ColourMode::Heading.legend(&scenario);
+        assert_eq!(legend[1].1, [255, 0, 0, 255]);
+        assert_eq!(legend[3].1, [0, 255, 255, 255]);
+    }
+}
@@ -0,0 +1,416 @@
+use crate::spatial::perlin;
+use amethyst::config::Config;
+use anyhow::{anyhow, ensure, Context, Result};
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Step used to differentiate the noise potential of a `CurlNoise` layer, in
+/// noise space.
+const CURL_EPSILON: f32 = 0.01;
+
+/// Air or water velocity over the whole world, as the sum of its layers.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FlowField {
+    #[serde(default)]
+    pub layers: Vec<FlowLayer>,
+    /// Periodic strengthening and weakening of the whole field.
+    #[serde(default)]
+    pub gust: Option<Gust>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum FlowLayer {
+    /// The same wind everywhere.
+    Uniform {
+        velocity: Vector2<f32>,
+    },
+    /// Rankine vortex turning anticlockwise for a positive `strength`, which
+    /// is the speed at `radius`. Speed grows linearly up to the radius and
+    /// falls off inversely beyond it.
+    Vortex {
+        center: Vector2<f32>,
+        strength: f32,
+        radius: f32,
+    },
+    /// Divergence-free turbulence taken from the curl of Perlin noise.
+    /// `scale` is the size of a noise cell in world units and `evolution` how
+    /// many noise cells per second the pattern moves through in time.
+    CurlNoise {
+        scale: f32,
+        strength: f32,
+        #[serde(default)]
+        evolution: f32,
+        #[serde(default)]
+        seed: u32,
+    },
+    Grid(FlowGrid),
+    /// A `FlowGrid` stored in its own file under `resources/flows`, replaced
+    /// by the loaded grid through `FlowField::load_grids`. Contributes nothing
+    /// until then.
+    GridFile(String),
+}
+
+/// Sine wave scaling of the flow's strength by `1 + amplitude * sin(..)`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gust {
+    pub amplitude: f32,
+    /// Seconds between two gusts.
+    pub period: f32,
+}
+
+/// Velocities sampled on a regular grid, stored row by row from `origin`
+/// upwards, and bilinearly interpolated between samples. Nothing flows
+/// outside of the grid.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FlowGrid {
+    pub origin: Vector2<f32>,
+    pub cell_size: f32,
+    pub columns: usize,
+    pub velocities: Vec<Vector2<f32>>,
+}
+
+impl FlowField {
+    /// Velocity of the flow at `position`, `time` seconds into the simulation.
+    pub fn sample(&self, position: Vector2<f32>, time: f32) -> Vector2<f32> {
+        let velocity = self
+            .layers
+            .iter()
+            .fold(Vector2::new(0.0, 0.0), |sum, layer| {
+                sum + layer.sample(position, time)
+            });
+
+        match &self.gust {
+            Some(gust) if gust.period > 0.0 => {
+                let phase = 2.0 * std::f32::consts::PI * time / gust.period;
+                velocity * (1.0 + gust.amplitude * phase.sin())
+            }
+            _ => velocity,
+        }
+    }
+
+    /// Checks every layer and the gust give a finite flow everywhere.
+    pub fn validate(&self) -> Result<()> {
+        for (index, layer) in self.layers.iter().enumerate() {
+            layer
+                .validate()
+                .with_context(|| format!("Flow layer {} is invalid", index))?;
+        }
+        if let Some(gust) = &self.gust {
+            ensure!(
+                gust.amplitude.is_finite() && gust.period.is_finite(),
+                "The gust's amplitude and period have to be finite"
+            );
+        }
+        Ok(())
+    }
+
+    /// Replaces every `GridFile` layer with the grid loaded from `directory`.
+    pub fn load_grids(&mut self, directory: &Path) -> Result<()> {
+        for layer in self.layers.iter_mut() {
+            if let FlowLayer::GridFile(file) = layer {
+                let grid = FlowGrid::load(directory.join(&file))
+                    .map_err(|e| anyhow!("Failed to load flow grid {}: {}", file, e))?;
+                *layer = FlowLayer::Grid(grid);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FlowLayer {
+    fn validate(&self) -> Result<()> {
+        match self {
+            FlowLayer::Uniform { velocity } => {
+                ensure!(finite(*velocity), "The velocity has to be finite");
+            }
+            FlowLayer::Vortex {
+                center,
+                strength,
+                radius,
+            } => {
+                ensure!(
+                    finite(*center) && strength.is_finite(),
+                    "The vortex's center and strength have to be finite"
+                );
+                ensure!(
+                    radius.is_finite() && *radius >= 0.0,
+                    "The vortex's radius is {}, but has to be zero or more",
+                    radius
+                );
+            }
+            FlowLayer::CurlNoise {
+                scale,
+                strength,
+                evolution,
+                ..
+            } => {
+                ensure!(
+                    scale.is_finite() && *scale > 0.0,
+                    "The noise scale is {}, but has to be positive",
+                    scale
+                );
+                ensure!(
+                    strength.is_finite() && evolution.is_finite(),
+                    "The noise's strength and evolution have to be finite"
+                );
+            }
+            FlowLayer::Grid(grid) => grid.validate()?,
+            FlowLayer::GridFile(_) => {}
+        }
+        Ok(())
+    }
+
+    fn sample(&self, position: Vector2<f32>, time: f32) -> Vector2<f32> {
+        match self {
+            FlowLayer::Uniform { velocity } => *velocity,
+            FlowLayer::Vortex {
+                center,
+                strength,
+                radius,
+            } => {
+                let offset = position - center;
+                let distance = offset.norm();
+                if distance == 0.0 {
+                    return Vector2::new(0.0, 0.0);
+                }
+
+                let tangent = Vector2::new(-offset.y, offset.x) / distance;
+                let speed = if distance < *radius {
+                    strength * distance / radius
+                } else {
+                    strength * radius / distance
+                };
+                tangent * speed
+            }
+            FlowLayer::CurlNoise {
+                scale,
+                strength,
+                evolution,
+                seed,
+            } => {
+                let (x, y, z) = (position.x / scale, position.y / scale, time * evolution);
+                let potential = |x, y| perlin(*seed, x, y, z);
+                let d_dx = (potential(x + CURL_EPSILON, y) - potential(x - CURL_EPSILON, y))
+                    / (2.0 * CURL_EPSILON);
+                let d_dy = (potential(x, y + CURL_EPSILON) - potential(x, y - CURL_EPSILON))
+                    / (2.0 * CURL_EPSILON);
+                Vector2::new(d_dy, -d_dx) * *strength
+            }
+            FlowLayer::Grid(grid) => grid.sample(position),
+            FlowLayer::GridFile(_) => Vector2::new(0.0, 0.0),
+        }
+    }
+}
+
+impl FlowGrid {
+    fn validate(&self) -> Result<()> {
+        ensure!(
+            self.cell_size.is_finite() && self.cell_size > 0.0,
+            "The grid's cell size is {}, but has to be positive",
+            self.cell_size
+        );
+        ensure!(
+            finite(self.origin) && self.velocities.iter().copied().all(finite),
+            "The grid's origin and velocities have to be finite"
+        );
+        Ok(())
+    }
+
+    fn rows(&self) -> usize {
+        if self.columns == 0 {
+            0
+        } else {
+            self.velocities.len() / self.columns
+        }
+    }
+
+    fn sample(&self, position: Vector2<f32>) -> Vector2<f32> {
+        let (columns, rows) = (self.columns, self.rows());
+        let cell = (position - self.origin) / self.cell_size;
+        if columns == 0
+            || rows == 0
+            || cell.x < 0.0
+            || cell.y < 0.0
+            || cell.x > (columns - 1) as f32
+            || cell.y > (rows - 1) as f32
+        {
+            return Vector2::new(0.0, 0.0);
+        }
+
+        let (column, row) = (cell.x.floor() as usize, cell.y.floor() as usize);
+        let (next_column, next_row) = ((column + 1).min(columns - 1), (row + 1).min(rows - 1));
+        let (tx, ty) = (cell.x - column as f32, cell.y - row as f32);
+        let at = |column: usize, row: usize| self.velocities[row * columns + column];
+
+        let bottom = at(column, row) * (1.0 - tx) + at(next_column, row) * tx;
+        let top = at(column, next_row) * (1.0 - tx) + at(next_column, next_row) * tx;
+        bottom * (1.0 - ty) + top * ty
+    }
+}
+
+fn finite(vector: Vector2<f32>) -> bool {
+    vector.x.is_finite() && vector.y.is_finite()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flocking::tests::assert_close;
+
+    fn field(layers: Vec<FlowLayer>) -> FlowField {
+        FlowField { layers, gust: None }
+    }
+
+    #[test]
+    fn layers_add_up() {
+        let field = field(vec![
+            FlowLayer::Uniform {
+                velocity: Vector2::new(10.0, 0.0),
+            },
+            FlowLayer::Vortex {
+                center: Vector2::new(0.0, 0.0),
+                strength: 20.0,
+                radius: 100.0,
+            },
+        ]);
+
+        // Inside the core, then outside of it
+        assert_close(
+            field.sample(Vector2::new(50.0, 0.0), 0.0),
+            Vector2::new(10.0, 10.0),
+        );
+        assert_close(
+            field.sample(Vector2::new(0.0, 200.0), 0.0),
+            Vector2::new(0.0, 0.0),
+        );
+        assert_close(
+            field.sample(Vector2::new(0.0, 0.0), 0.0),
+            Vector2::new(10.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn gusts_vary_over_time() {
+        let mut field = field(vec![FlowLayer::Uniform {
+            velocity: Vector2::new(10.0, 0.0),
+        }]);
+        field.gust = Some(Gust {
+            amplitude: 0.5,
+            period: 4.0,
+        });
+
+        let position = Vector2::new(0.0, 0.0);
+        assert_close(field.sample(position, 0.0), Vector2::new(10.0, 0.0));
+        assert_close(field.sample(position, 1.0), Vector2::new(15.0, 0.0));
+        assert_close(field.sample(position, 3.0), Vector2::new(5.0, 0.0));
+    }
+
+    #[test]
+    fn grid_interpolates_inside_and_is_still_outside() {
+        let field = field(vec![FlowLayer::Grid(FlowGrid {
+            origin: Vector2::new(-100.0, 0.0),
+            cell_size: 100.0,
+            columns: 2,
+            velocities: vec![
+                Vector2::new(0.0, 0.0),
+                Vector2::new(10.0, 0.0),
+                Vector2::new(0.0, 10.0),
+                Vector2::new(10.0, 10.0),
+            ],
+        })]);
+
+        assert_close(
+            field.sample(Vector2::new(-50.0, 25.0), 0.0),
+            Vector2::new(5.0, 2.5),
+        );
+        assert_close(
+            field.sample(Vector2::new(0.0, 100.0), 0.0),
+            Vector2::new(10.0, 10.0),
+        );
+        assert_eq!(
+            field.sample(Vector2::new(-150.0, 50.0), 0.0),
+            Vector2::new(0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn curl_noise_has_no_divergence() {
+        let field = field(vec![FlowLayer::CurlNoise {
+            scale: 100.0,
+            strength: 50.0,
+            evolution: 0.1,
+            seed: 3,
+        }]);
+
+        let step = 1.0;
+        let mut moving = false;
+        for i in 0..50 {
+            let position = Vector2::new(i as f32 * 37.0, i as f32 * -23.0 + 11.0);
+            let time = i as f32 * 0.5;
+            let dx = field.sample(position + Vector2::new(step, 0.0), time)
+                - field.sample(position - Vector2::new(step, 0.0), time);
+            let dy = field.sample(position + Vector2::new(0.0, step), time)
+                - field.sample(position - Vector2::new(0.0, step), time);
+            let divergence = (dx.x + dy.y) / (2.0 * step);
+            assert!(divergence.abs() < 0.05, "divergence {}", divergence);
+            moving |= field.sample(position, time).norm() > 1.0;
+        }
+        assert!(moving);
+    }
+
+    #[test]
+    fn grid_files_load() {
+        let mut field = field(vec![FlowLayer::GridFile("river.ron".to_string())]);
+        field
+            .load_grids(&Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/flows"))
+            .unwrap();
+        assert!(matches!(field.layers[0], FlowLayer::Grid(_)));
+
+        let mut missing = FlowField {
+            layers: vec![FlowLayer::GridFile("missing.ron".to_string())],
+            gust: None,
+        };
+        assert!(missing.load_grids(Path::new("/nonexistent")).is_err());
+    }
+
+    #[test]
+    fn flows_that_would_be_nan_are_rejected() {
+        let grid = FlowGrid {
+            origin: Vector2::new(0.0, 0.0),
+            cell_size: 100.0,
+            columns: 1,
+            velocities: vec![Vector2::new(1.0, 0.0)],
+        };
+        field(vec![FlowLayer::Grid(grid.clone())])
+            .validate()
+            .unwrap();
+
+        let invalid = [
+            FlowLayer::Grid(FlowGrid {
+                cell_size: 0.0,
+                ..grid.clone()
+            }),
+            FlowLayer::Grid(FlowGrid {
+                velocities: vec![Vector2::new(f32::NAN, 0.0)],
+                ..grid
+            }),
+            FlowLayer::CurlNoise {
+                scale: 0.0,
+                strength: 1.0,
+                evolution: 0.0,
+                seed: 0,
+            },
+            FlowLayer::Uniform {
+                velocity: Vector2::new(f32::INFINITY, 0.0),
+            },
+        ];
+        for layer in invalid.iter() {
+            assert!(
+                field(vec![layer.clone()]).validate().is_err(),
+                "{:?}",
+                layer
+            );
+        }
+    }
+}
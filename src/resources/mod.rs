@@ -1,3 +1,25 @@
-mod sprite_cache;
+mod colouring;
+mod flow;
+mod pause;
+mod population;
+mod scenario;
+mod scripts;
+mod snapshot;
+mod spawn;
+mod sprite_registry;
+mod trails;
 
-pub use self::sprite_cache::{SpriteCache, SpriteKey};
+pub use self::colouring::{ColourMode, CLUSTER_DISTANCE, CROWDED_NEIGHBOURS};
+pub use self::flow::{FlowField, FlowGrid, FlowLayer, Gust};
+pub use self::pause::SimulationState;
+pub use self::population::{PopulationHistory, PopulationSample};
+pub use self::scenario::{FoodConfig, ObstacleConfig, Scenario, SpeciesConfig};
+pub use self::scripts::{ScriptBoid, ScriptLibrary};
+pub use self::snapshot::{BoidSnapshot, Snapshot};
+pub use self::spawn::{
+    validate_half_extents, HeadingDistribution, SpawnArea, SpawnRegion, SpeedDistribution,
+};
+pub use self::sprite_registry::{
+    AnimationConfig, SheetConfig, Sprite, SpriteConfig, SpriteManifest, SpriteRegistry,
+};
+pub use self::trails::{TrailSampling, TrailSettings};
@@ -0,0 +1,17 @@
+/// Whether the simulation systems run. Drawing and input carry on while it's
+/// paused, so boids can still be placed and selected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SimulationState {
+    #[default]
+    Running,
+    Paused,
+}
+
+impl SimulationState {
+    pub fn toggled(self) -> SimulationState {
+        match self {
+            SimulationState::Running => SimulationState::Paused,
+            SimulationState::Paused => SimulationState::Running,
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use std::collections::BTreeMap;
+
+/// Live boids of every species at one point in time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PopulationSample {
+    /// Seconds since the simulation started.
+    pub time: f32,
+    pub counts: BTreeMap<String, usize>,
+}
+
+/// Population of every species over the course of the simulation.
+#[derive(Debug, Default)]
+pub struct PopulationHistory {
+    pub samples: Vec<PopulationSample>,
+}
+
+impl PopulationHistory {
+    pub fn latest(&self) -> Option<&PopulationSample> {
+        self.samples.last()
+    }
+
+    /// Whether at least `interval` seconds have gone by since the last sample
+    /// at `time`.
+    pub fn is_due(&self, time: f32, interval: f32) -> bool {
+        self.latest()
+            .map(|sample| time - sample.time >= interval)
+            .unwrap_or(true)
+    }
+}
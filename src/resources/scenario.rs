@@ -0,0 +1,353 @@
+use crate::{
+    components::{
+        BoidData, Flocking, FlowResponse, FollowerData, GoalData, Metabolism, Path, ScriptRule,
+    },
+    evolution::Mutation,
+    resources::{
+        sprite_registry::{default_boid_sprite, default_obstacle_sprite},
+        FlowField, SpawnRegion, TrailSettings,
+    },
+    spatial::Shape,
+};
+use anyhow::{anyhow, ensure, Context, Result};
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A kind of boid that can be spawned in a scenario.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpeciesConfig {
+    pub name: String,
+    pub boid: BoidData,
+    /// Flocking model for this species, overriding the scenario's.
+    #[serde(default)]
+    pub model: Option<Flocking>,
+    /// Attractor and path steering. Species without it ignore both.
+    #[serde(default)]
+    pub goals: Option<GoalData>,
+    /// Name of the scenario path boids of this species follow.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Whether boids of this species spawn as leaders.
+    #[serde(default)]
+    pub leader: bool,
+    /// How boids of this species follow leaders. Species without it ignore
+    /// them.
+    #[serde(default)]
+    pub follower: Option<FollowerData>,
+    /// How boids of this species are carried by the flow. Species without it
+    /// ignore the flow.
+    #[serde(default)]
+    pub flow: Option<FlowResponse>,
+    /// Energy use, feeding and reproduction. Species without it never starve
+    /// or breed.
+    #[serde(default)]
+    pub metabolism: Option<Metabolism>,
+    /// Steering rules from scripts in `resources/scripts`, applied on top of
+    /// the flocking model.
+    #[serde(default)]
+    pub scripts: Vec<ScriptRule>,
+    /// Seconds boids of this species live for. They live until they starve
+    /// or are removed without it.
+    #[serde(default)]
+    pub lifespan: Option<f32>,
+    /// How much offspring differ from the parent whose `BoidData` they
+    /// inherit. Offspring are exact copies without it.
+    #[serde(default)]
+    pub mutation: Option<Mutation>,
+    /// Name of the sprite in `resources/sprites/manifest.ron` boids of this
+    /// species are drawn with.
+    #[serde(default = "default_boid_sprite")]
+    pub sprite: String,
+}
+
+impl SpeciesConfig {
+    /// Checks the species' boid data, flocking model, lifespan and mutation.
+    pub fn validate(&self) -> Result<()> {
+        self.boid.validate()?;
+        if let Some(lifespan) = self.lifespan {
+            ensure!(
+                lifespan.is_finite() && lifespan >= 0.0,
+                "Its lifespan is {}, but has to be finite and not negative",
+                lifespan
+            );
+        }
+        if let Some(model) = &self.model {
+            model.validate().context("Its flocking model is invalid")?;
+        }
+        if let Some(mutation) = &self.mutation {
+            mutation.validate().context("Its mutation is invalid")?;
+        }
+        Ok(())
+    }
+}
+
+/// An obstacle placed when the scenario starts.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ObstacleConfig {
+    /// Drawn with the named sprite, pushing nearby boids away radially.
+    Point {
+        position: Vector2<f32>,
+        #[serde(default = "default_obstacle_sprite")]
+        sprite: String,
+        /// How hard boids are pushed away.
+        #[serde(default = "default_obstacle_weight")]
+        separation_weight: f32,
+    },
+    /// Solid shape that boids steer around and can never pass through.
+    Solid {
+        position: Vector2<f32>,
+        shape: Shape,
+        avoidance_weight: f32,
+    },
+}
+
+fn default_obstacle_weight() -> f32 {
+    0.2
+}
+
+/// A food source placed full when the scenario starts.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FoodConfig {
+    pub position: Vector2<f32>,
+    pub capacity: f32,
+    pub regrowth: f32,
+    pub radius: f32,
+}
+
+/// World setup loaded from `resources/scenarios`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Scenario {
+    /// Flocking model used by every species that doesn't set its own.
+    #[serde(default)]
+    pub model: Flocking,
+    /// Species that can be spawned. The first one is placed by the mouse.
+    pub species: Vec<SpeciesConfig>,
+    #[serde(default)]
+    pub obstacles: Vec<ObstacleConfig>,
+    /// Positions of attractors placed when the scenario starts.
+    #[serde(default)]
+    pub attractors: Vec<Vector2<f32>>,
+    /// Named paths that species can follow.
+    #[serde(default)]
+    pub paths: HashMap<String, Path>,
+    /// Wind or current the boids move through.
+    #[serde(default)]
+    pub flow: FlowField,
+    #[serde(default)]
+    pub food: Vec<FoodConfig>,
+    /// Boids placed when the scenario starts.
+    #[serde(default)]
+    pub spawns: Vec<SpawnRegion>,
+    /// Most boids alive at once. The oldest are removed once there are more.
+    #[serde(default)]
+    pub max_population: Option<usize>,
+    /// Boids dropped around the cursor by a spawn burst. Its area is moved to
+    /// wherever the cursor is.
+    #[serde(default)]
+    pub burst: SpawnRegion,
+    /// Trails of where boids have recently been.
+    #[serde(default)]
+    pub trails: TrailSettings,
+}
+
+impl Scenario {
+    /// Checks the scenario can be played out, so mistakes in it are reported
+    /// when it's loaded rather than when it's used.
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            !self.species.is_empty(),
+            "The scenario does not define any species"
+        );
+        self.model
+            .validate()
+            .context("The flocking model is invalid")?;
+        self.flow.validate().context("The flow is invalid")?;
+        ensure!(
+            self.max_population != Some(0),
+            "The maximum population has to be at least one boid"
+        );
+        for species in &self.species {
+            species
+                .validate()
+                .with_context(|| format!("Species {} is invalid", species.name))?;
+        }
+        for (index, region) in self.spawns.iter().enumerate() {
+            region
+                .validate()
+                .with_context(|| format!("Spawn region {} is invalid", index))?;
+        }
+        self.burst
+            .validate()
+            .context("The spawn burst is invalid")?;
+        for (index, obstacle) in self.obstacles.iter().enumerate() {
+            let weight = match obstacle {
+                ObstacleConfig::Point {
+                    separation_weight, ..
+                } => separation_weight,
+                ObstacleConfig::Solid {
+                    avoidance_weight, ..
+                } => avoidance_weight,
+            };
+            ensure!(
+                weight.is_finite(),
+                "Obstacle {} has a weight of {}, which is not finite",
+                index,
+                weight
+            );
+        }
+        Ok(())
+    }
+
+    pub fn default_species(&self) -> Result<&SpeciesConfig> {
+        self.species
+            .first()
+            .ok_or_else(|| anyhow!("The scenario does not define any species"))
+    }
+
+    pub fn species_named(&self, name: &str) -> Result<&SpeciesConfig> {
+        self.species
+            .iter()
+            .find(|species| species.name == name)
+            .ok_or_else(|| anyhow!("The scenario does not define species {}", name))
+    }
+
+    /// Position of the species called `name` in the species list.
+    pub fn species_index(&self, name: &str) -> Option<usize> {
+        self.species.iter().position(|species| species.name == name)
+    }
+
+    /// Species spawned by `region`.
+    pub fn region_species(&self, region: &SpawnRegion) -> Result<&SpeciesConfig> {
+        match &region.species {
+            Some(name) => self.species_named(name),
+            None => self.default_species(),
+        }
+    }
+
+    /// Flocking model followed by `species`.
+    pub fn model(&self, species: &SpeciesConfig) -> Flocking {
+        species.model.clone().unwrap_or_else(|| self.model.clone())
+    }
+
+    /// Path followed by `species`, if it has one.
+    pub fn path(&self, species: &SpeciesConfig) -> Result<Option<&Path>> {
+        species
+            .path
+            .as_ref()
+            .map(|name| {
+                self.paths.get(name).ok_or_else(|| {
+                    anyhow!("Species {} follows unknown path {}", species.name, name)
+                })
+            })
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst::config::Config;
+
+    fn default_scenario() -> Scenario {
+        Scenario::load_bytes(include_bytes!("../../resources/scenarios/default.ron")).unwrap()
+    }
+
+    #[test]
+    fn default_scenario_file_loads() {
+        let scenario = default_scenario();
+        let species = scenario.default_species().unwrap();
+        assert_eq!(species.name, "boid");
+        assert_eq!(scenario.model(species), Flocking::Reynolds);
+        assert!(!scenario.obstacles.is_empty());
+    }
+
+    #[test]
+    fn species_model_overrides_scenario() {
+        let mut scenario = default_scenario();
+        let mut other = scenario.species[0].clone();
+        other.name = "other".to_string();
+        other.model = Some(Flocking::Vicsek(crate::flocking::VicsekParams {
+            radius: 10.0,
+            speed: 1.0,
+            noise: 0.0,
+        }));
+        scenario.species.push(other);
+
+        assert_eq!(scenario.model(&scenario.species[0]), Flocking::Reynolds);
+        assert_ne!(
+            scenario.model(scenario.species.last().unwrap()),
+            Flocking::Reynolds
+        );
+    }
+
+    #[test]
+    fn species_paths_are_looked_up_by_name() {
+        let mut scenario = default_scenario();
+        let mut species = scenario.species[0].clone();
+        assert!(scenario.path(&species).unwrap().is_none());
+
+        species.path = Some("circuit".to_string());
+        assert!(scenario.path(&species).unwrap().is_some());
+
+        scenario.paths.clear();
+        assert!(scenario.path(&species).is_err());
+    }
+
+    #[test]
+    fn models_and_species_are_validated() {
+        let mut scenario = default_scenario();
+        scenario.model = Flocking::Vicsek(crate::flocking::VicsekParams {
+            radius: 10.0,
+            speed: 1.0,
+            noise: f32::INFINITY,
+        });
+        assert!(scenario.validate().is_err());
+
+        let mut scenario = default_scenario();
+        scenario.species[0].model = Some(Flocking::Couzin(crate::flocking::CouzinParams {
+            repulsion_radius: -1.0,
+            orientation_radius: 10.0,
+            attraction_radius: 20.0,
+            field_of_view: 4.0,
+            turning_rate: 1.0,
+            noise: 0.0,
+            speed: 1.0,
+        }));
+        assert!(scenario.validate().is_err());
+
+        let mut scenario = default_scenario();
+        scenario.species.clear();
+        assert!(scenario.validate().is_err());
+
+        for lifespan in [f32::NAN, f32::INFINITY, -1.0] {
+            let mut scenario = default_scenario();
+            scenario.species[0].lifespan = Some(lifespan);
+            assert!(scenario.validate().is_err(), "{}", lifespan);
+        }
+
+        let mut scenario = default_scenario();
+        scenario.max_population = Some(0);
+        assert!(scenario.validate().is_err());
+    }
+
+    #[test]
+    fn obstacle_weights_must_be_finite() {
+        let mut scenario = default_scenario();
+        scenario.validate().unwrap();
+        let weight = scenario
+            .obstacles
+            .iter_mut()
+            .find_map(|obstacle| match obstacle {
+                ObstacleConfig::Point {
+                    separation_weight, ..
+                } => Some(separation_weight),
+                ObstacleConfig::Solid { .. } => None,
+            });
+        let weight = weight.unwrap();
+        assert_eq!(*weight, 0.2);
+
+        *weight = f32::NAN;
+        assert!(scenario.validate().is_err());
+    }
+}
@@ -0,0 +1,369 @@
+use crate::{components::BoidData, flocking::Neighbour};
+use amethyst::utils::application_root_dir;
+use log::{info, warn};
+use nalgebra::Vector2;
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST, FLOAT};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Component, Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// How often scripts are checked for changes on disk.
+const RELOAD_INTERVAL: Duration = Duration::from_millis(500);
+/// Most Rhai operations a single call may take, so a runaway loop in a script
+/// stalls one frame rather than hanging the simulation.
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// A script as last read from disk.
+struct LoadedScript {
+    source: String,
+    /// `None` when the script failed to compile.
+    ast: Option<AST>,
+    /// Set once a failure has been logged, so a broken script only reports
+    /// once until it changes.
+    failed: bool,
+}
+
+/// Compiled steering scripts, loaded from a directory the first time a boid
+/// uses them and reloaded whenever they change on disk. A script that fails
+/// to load or run steers nothing, and the error is logged rather than
+/// stopping the simulation.
+pub struct ScriptLibrary {
+    engine: Engine,
+    directory: PathBuf,
+    scripts: HashMap<String, LoadedScript>,
+    last_checked: Option<Instant>,
+}
+
+impl Default for ScriptLibrary {
+    /// Library reading from `resources/scripts`.
+    fn default() -> Self {
+        let root = application_root_dir().unwrap_or_default();
+        ScriptLibrary::new(root.join("resources").join("scripts"))
+    }
+}
+
+impl ScriptLibrary {
+    pub fn new(directory: impl Into<PathBuf>) -> ScriptLibrary {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        ScriptLibrary {
+            engine,
+            directory: directory.into(),
+            scripts: HashMap::new(),
+            last_checked: None,
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Recompiles every loaded script whose file changed, at most once every
+    /// `RELOAD_INTERVAL`.
+    pub fn reload_changed(&mut self) {
+        let now = Instant::now();
+        if let Some(last_checked) = self.last_checked {
+            if now.duration_since(last_checked) < RELOAD_INTERVAL {
+                return;
+            }
+        }
+        self.last_checked = Some(now);
+
+        let names = self.scripts.keys().cloned().collect::<Vec<_>>();
+        for name in names {
+            let source = self.read(&name);
+            if source.as_deref() != Some(self.scripts[&name].source.as_str()) {
+                info!("Reloading script {}", name);
+                self.load(&name);
+            }
+        }
+    }
+
+    /// Steering from the `steer(boid, neighbours, params)` function of the
+    /// script called `name`, or nothing if the script is broken.
+    pub fn steer(
+        &mut self,
+        name: &str,
+        boid: ScriptBoid,
+        neighbours: &[Neighbour],
+        params: &BTreeMap<String, f32>,
+    ) -> Vector2<f32> {
+        if !self.scripts.contains_key(name) {
+            self.load(name);
+        }
+        let script = self.scripts.get_mut(name).unwrap();
+        let ast = match &script.ast {
+            Some(ast) => ast,
+            None => return Vector2::new(0.0, 0.0),
+        };
+
+        let neighbours = neighbours
+            .iter()
+            .map(|(_, position, velocity)| {
+                let mut neighbour = Map::new();
+                neighbour.insert("position".into(), vector(*position));
+                neighbour.insert("velocity".into(), vector(*velocity));
+                neighbour.insert(
+                    "distance".into(),
+                    Dynamic::from_float((position - boid.position).norm() as FLOAT),
+                );
+                Dynamic::from_map(neighbour)
+            })
+            .collect::<Array>();
+        let params = params
+            .iter()
+            .map(|(key, value)| (key.into(), Dynamic::from_float(*value as FLOAT)))
+            .collect::<Map>();
+
+        let result = self
+            .engine
+            .call_fn::<Dynamic>(
+                &mut Scope::new(),
+                ast,
+                "steer",
+                (boid.into_map(), neighbours, params),
+            )
+            .map_err(|e| e.to_string())
+            .and_then(from_vector);
+        match result {
+            Ok(steering) => steering,
+            Err(e) => {
+                if !script.failed {
+                    warn!("Script {} failed: {}", name, e);
+                    script.failed = true;
+                }
+                Vector2::new(0.0, 0.0)
+            }
+        }
+    }
+
+    /// Where the script called `name` is. Names can't be absolute or go up
+    /// with `..`, so scripts are only ever read from the library's directory.
+    fn path(&self, name: &str) -> Result<PathBuf, String> {
+        let contained = Path::new(name)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if contained {
+            Ok(self.directory.join(name))
+        } else {
+            Err(format!("{} is outside of the script directory", name))
+        }
+    }
+
+    fn read(&self, name: &str) -> Option<String> {
+        fs::read_to_string(self.path(name).ok()?).ok()
+    }
+
+    fn load(&mut self, name: &str) {
+        let source = self
+            .path(name)
+            .and_then(|path| fs::read_to_string(path).map_err(|e| e.to_string()));
+        let (source, ast) = match source {
+            Ok(source) => {
+                let ast = self.engine.compile(&source).map_err(|e| e.to_string());
+                (source, ast)
+            }
+            Err(e) => (String::new(), Err(e)),
+        };
+        if let Err(e) = &ast {
+            warn!("Failed to load script {}: {}", name, e);
+        }
+        self.scripts.insert(
+            name.to_string(),
+            LoadedScript {
+                source,
+                failed: ast.is_err(),
+                ast: ast.ok(),
+            },
+        );
+    }
+}
+
+/// What a script is told about the boid it steers.
+pub struct ScriptBoid<'a> {
+    pub position: Vector2<f32>,
+    pub velocity: Vector2<f32>,
+    pub data: &'a BoidData,
+    pub delta_seconds: f32,
+}
+
+impl<'a> ScriptBoid<'a> {
+    fn into_map(self) -> Map {
+        let float = |value: f32| Dynamic::from_float(value as FLOAT);
+        let mut map = Map::new();
+        map.insert("position".into(), vector(self.position));
+        map.insert("velocity".into(), vector(self.velocity));
+        map.insert("max_speed".into(), float(self.data.max_speed));
+        map.insert(
+            "separation_radius".into(),
+            float(self.data.separation_radius),
+        );
+        map.insert("alignment_radius".into(), float(self.data.alignment_radius));
+        map.insert("cohesion_radius".into(), float(self.data.cohesion_radius));
+        map.insert("dt".into(), float(self.delta_seconds));
+        map
+    }
+}
+
+fn vector(v: Vector2<f32>) -> Dynamic {
+    Dynamic::from_array(vec![
+        Dynamic::from_float(v.x as FLOAT),
+        Dynamic::from_float(v.y as FLOAT),
+    ])
+}
+
+fn from_vector(value: Dynamic) -> Result<Vector2<f32>, String> {
+    let type_name = value.type_name();
+    let components = value
+        .into_array()
+        .ok()
+        .filter(|array| array.len() == 2)
+        .ok_or_else(|| format!("steer returned {} instead of [x, y]", type_name))?;
+    let component = |value: &Dynamic| {
+        value
+            .as_float()
+            .or_else(|_| value.as_int().map(|int| int as FLOAT))
+            .map(|float| float as f32)
+            .map_err(|_| format!("steer returned a {} component", value.type_name()))
+            .and_then(|float| {
+                if float.is_finite() {
+                    Ok(float)
+                } else {
+                    Err(format!("steer returned a component of {}", float))
+                }
+            })
+    };
+    Ok(Vector2::new(
+        component(&components[0])?,
+        component(&components[1])?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flocking::tests::boid_data;
+    use amethyst::ecs::prelude::*;
+
+    /// Library reading from a fresh directory holding `scripts`, which the
+    /// test removes once it's done.
+    fn library_with(test: &str, scripts: &[(&str, &str)]) -> ScriptLibrary {
+        let directory =
+            std::env::temp_dir().join(format!("boids-scripts-{}-{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        for (name, source) in scripts {
+            fs::write(directory.join(name), source).unwrap();
+        }
+        ScriptLibrary::new(directory)
+    }
+
+    fn steer(library: &mut ScriptLibrary, name: &str) -> Vector2<f32> {
+        let data = boid_data();
+        let boid = ScriptBoid {
+            position: Vector2::new(0.0, 0.0),
+            velocity: Vector2::new(1.0, 0.0),
+            data: &data,
+            delta_seconds: 0.1,
+        };
+        let mut world = World::new();
+        let neighbour = (
+            world.create_entity().build(),
+            Vector2::new(3.0, 4.0),
+            Vector2::new(0.0, 2.0),
+        );
+        let params = vec![("gain".to_string(), 2.0)].into_iter().collect();
+        library.steer(name, boid, &[neighbour], &params)
+    }
+
+    #[test]
+    fn scripts_see_the_boid_neighbours_and_params() {
+        let mut library = library_with(
+            "inputs",
+            &[(
+                "towards.rhai",
+                "fn steer(boid, neighbours, params) {
+                    let n = neighbours[0];
+                    [n.position[0] * params.gain, n.distance + boid.velocity[0]]
+                }",
+            )],
+        );
+        assert_eq!(steer(&mut library, "towards.rhai"), Vector2::new(6.0, 6.0));
+        fs::remove_dir_all(library.directory()).unwrap();
+    }
+
+    #[test]
+    fn broken_scripts_steer_nothing() {
+        let mut library = library_with(
+            "broken",
+            &[
+                ("syntax.rhai", "fn steer(boid, neighbours, params) { [1.0, "),
+                (
+                    "wrong_type.rhai",
+                    "fn steer(boid, neighbours, params) { \"up\" }",
+                ),
+                (
+                    "endless.rhai",
+                    "fn steer(boid, neighbours, params) { loop {} }",
+                ),
+                (
+                    "infinite.rhai",
+                    "fn steer(boid, neighbours, params) { [1e300 * 1e300, 0.0] }",
+                ),
+                (
+                    "too_big.rhai",
+                    "fn steer(boid, neighbours, params) { [0.0, 1e39] }",
+                ),
+            ],
+        );
+        for name in &[
+            "syntax.rhai",
+            "wrong_type.rhai",
+            "endless.rhai",
+            "infinite.rhai",
+            "too_big.rhai",
+            "missing.rhai",
+        ] {
+            assert_eq!(steer(&mut library, name), Vector2::new(0.0, 0.0));
+        }
+        fs::remove_dir_all(library.directory()).unwrap();
+    }
+
+    #[test]
+    fn scripts_outside_the_directory_are_not_read() {
+        let outer = library_with(
+            "outside",
+            &[("rule.rhai", "fn steer(boid, neighbours, params) { [1, 0] }")],
+        );
+        let directory = outer.directory().join("inner");
+        fs::create_dir_all(&directory).unwrap();
+        let mut inner = ScriptLibrary::new(&directory);
+
+        let absolute = outer.directory().join("rule.rhai");
+        for name in &["../rule.rhai", absolute.to_str().unwrap()] {
+            assert_eq!(steer(&mut inner, name), Vector2::new(0.0, 0.0));
+        }
+        fs::remove_dir_all(outer.directory()).unwrap();
+    }
+
+    #[test]
+    fn changed_scripts_are_reloaded() {
+        let mut library = library_with(
+            "reload",
+            &[("rule.rhai", "fn steer(boid, neighbours, params) { [1, 0] }")],
+        );
+        assert_eq!(steer(&mut library, "rule.rhai"), Vector2::new(1.0, 0.0));
+
+        fs::write(
+            library.directory().join("rule.rhai"),
+            "fn steer(boid, neighbours, params) { [0, 1] }",
+        )
+        .unwrap();
+        library.reload_changed();
+        assert_eq!(steer(&mut library, "rule.rhai"), Vector2::new(0.0, 1.0));
+        fs::remove_dir_all(library.directory()).unwrap();
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{
+    components::{BoidData, Energy, Position, Species, Velocity},
+    entities::boids::fill_boid,
+    resources::{Scenario, SpriteRegistry},
+};
+use amethyst::ecs::{prelude::*, LazyUpdate};
+use anyhow::Result;
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+
+/// State of a single boid when its snapshot was taken.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BoidSnapshot {
+    pub species: String,
+    pub position: Vector2<f32>,
+    pub velocity: Vector2<f32>,
+    /// Kept separately from the species since offspring may have mutated.
+    pub boid: BoidData,
+    #[serde(default)]
+    pub energy: Option<f32>,
+}
+
+/// Every boid in the world at one moment. The viewer saves one with F5 and
+/// restores it with `--snapshot`; everything else comes from the scenario.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub boids: Vec<BoidSnapshot>,
+}
+
+impl Snapshot {
+    pub fn capture(world: &World) -> Snapshot {
+        let (species, positions, velocities, boid_datas, energies) = world.system_data::<(
+            ReadStorage<Species>,
+            ReadStorage<Position>,
+            ReadStorage<Velocity>,
+            ReadStorage<BoidData>,
+            ReadStorage<Energy>,
+        )>();
+        let boids = (
+            &species,
+            &positions,
+            &velocities,
+            &boid_datas,
+            energies.maybe(),
+        )
+            .join()
+            .map(|(species, position, velocity, boid, energy)| BoidSnapshot {
+                species: species.0.clone(),
+                position: position.0,
+                velocity: velocity.0,
+                boid: boid.clone(),
+                energy: energy.map(|energy| energy.0),
+            })
+            .collect();
+        Snapshot { boids }
+    }
+
+    /// Checks that `scenario` can spawn every boid in the snapshot.
+    pub fn validate(&self, scenario: &Scenario) -> Result<()> {
+        for boid in &self.boids {
+            let species = scenario.species_named(&boid.species)?;
+            scenario.path(species)?;
+        }
+        Ok(())
+    }
+
+    /// Queues every boid in the snapshot for creation as a member of its
+    /// species, then puts back the state it was saved with.
+    pub fn restore(&self, world: &World) -> Result<()> {
+        let (entities, lazy_update, sprite_registry, scenario) = world.system_data::<(
+            Entities,
+            Read<LazyUpdate>,
+            Read<SpriteRegistry>,
+            ReadExpect<Scenario>,
+        )>();
+        for boid in &self.boids {
+            let species = scenario.species_named(&boid.species)?;
+            let entity = fill_boid(
+                &entities,
+                &sprite_registry,
+                &lazy_update,
+                boid.position,
+                &scenario,
+                species,
+            )?;
+            lazy_update.insert(entity, Velocity(boid.velocity));
+            lazy_update.insert(entity, boid.boid.clone());
+            if let Some(energy) = boid.energy {
+                lazy_update.insert(entity, Energy(energy));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::Simulation;
+    use amethyst::config::Config;
+
+    fn default_scenario() -> Scenario {
+        Scenario::load_bytes(include_bytes!("../../resources/scenarios/default.ron")).unwrap()
+    }
+
+    #[test]
+    fn restoring_a_snapshot_recreates_its_boids() {
+        let scenario = default_scenario();
+        let mut original = Simulation::new(scenario.clone()).unwrap();
+        let species = scenario.species_named("grazer").unwrap().clone();
+        original
+            .spawn(&species, 5, Vector2::new(100.0, 100.0))
+            .unwrap();
+        original.run(3);
+        let snapshot = Snapshot::capture(&original.world);
+        assert_eq!(snapshot.boids.len(), 5);
+        assert!(snapshot.boids.iter().all(|boid| boid.energy.is_some()));
+
+        let mut restored = Simulation::new(scenario).unwrap();
+        snapshot.validate(&restored.world.read_resource()).unwrap();
+        snapshot.restore(&restored.world).unwrap();
+        restored.world.maintain();
+
+        let mut expected = snapshot.boids;
+        let mut actual = Snapshot::capture(&restored.world).boids;
+        for boids in [&mut expected, &mut actual] {
+            boids.sort_by(|a, b| a.position.x.partial_cmp(&b.position.x).unwrap());
+        }
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn snapshots_of_unknown_species_are_rejected() {
+        let snapshot = Snapshot {
+            boids: vec![BoidSnapshot {
+                species: "dodo".to_string(),
+                position: Vector2::new(0.0, 0.0),
+                velocity: Vector2::new(0.0, 0.0),
+                boid: default_scenario().species[0].boid.clone(),
+                energy: None,
+            }],
+        };
+        assert!(snapshot.validate(&default_scenario()).is_err());
+    }
+}
@@ -0,0 +1,418 @@
+use crate::flocking::heading;
+use anyhow::{ensure, Result};
+use nalgebra::Vector2;
+use rand::{
+    distributions::{Distribution, Uniform},
+    Rng,
+};
+use rand_distr::Normal;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// Where the boids of a spawn region are placed, uniformly over its area.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SpawnArea {
+    Rect {
+        center: Vector2<f32>,
+        half_extents: Vector2<f32>,
+    },
+    Circle {
+        center: Vector2<f32>,
+        radius: f32,
+    },
+    /// Everywhere within the world's half extents of the origin.
+    World,
+}
+
+impl SpawnArea {
+    pub fn center(&self) -> Vector2<f32> {
+        match self {
+            SpawnArea::Rect { center, .. } | SpawnArea::Circle { center, .. } => *center,
+            SpawnArea::World => Vector2::new(0.0, 0.0),
+        }
+    }
+
+    /// Same area moved so it's centred on `center`. The world can't move.
+    pub fn centered_on(&self, center: Vector2<f32>) -> SpawnArea {
+        match self {
+            SpawnArea::Rect { half_extents, .. } => SpawnArea::Rect {
+                center,
+                half_extents: *half_extents,
+            },
+            SpawnArea::Circle { radius, .. } => SpawnArea::Circle {
+                center,
+                radius: *radius,
+            },
+            SpawnArea::World => SpawnArea::World,
+        }
+    }
+
+    pub fn contains(&self, point: Vector2<f32>, world_extents: Vector2<f32>) -> bool {
+        let in_rect = |offset: Vector2<f32>, half_extents: Vector2<f32>| {
+            offset.x.abs() <= half_extents.x && offset.y.abs() <= half_extents.y
+        };
+        match self {
+            SpawnArea::Rect {
+                center,
+                half_extents,
+            } => in_rect(point - center, *half_extents),
+            SpawnArea::Circle { center, radius } => (point - center).norm() <= *radius,
+            SpawnArea::World => in_rect(point, world_extents),
+        }
+    }
+
+    /// Checks the area has a finite centre and a size boids can be placed
+    /// within.
+    pub fn validate(&self) -> Result<()> {
+        let center = self.center();
+        ensure!(
+            center.x.is_finite() && center.y.is_finite(),
+            "The spawn area's center has to be finite"
+        );
+        match self {
+            SpawnArea::Rect { half_extents, .. } => validate_half_extents(*half_extents),
+            SpawnArea::Circle { radius, .. } => {
+                ensure!(
+                    radius.is_finite() && *radius >= 0.0,
+                    "The spawn circle's radius is {}, but has to be zero or more",
+                    radius
+                );
+                Ok(())
+            }
+            SpawnArea::World => Ok(()),
+        }
+    }
+
+    pub fn sample<R: Rng>(&self, rng: &mut R, world_extents: Vector2<f32>) -> Vector2<f32> {
+        match self {
+            SpawnArea::Rect {
+                center,
+                half_extents,
+            } => center + sample_rect(rng, *half_extents),
+            SpawnArea::Circle { center, radius } => {
+                // The square root spreads boids evenly instead of bunching
+                // them up in the middle
+                let distance = radius * rng.gen::<f32>().sqrt();
+                center + distance * heading(rng.gen_range(0.0..2.0 * PI))
+            }
+            SpawnArea::World => sample_rect(rng, world_extents),
+        }
+    }
+}
+
+/// Which way the boids of a spawn region start out heading.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum HeadingDistribution {
+    /// Every direction equally likely.
+    #[default]
+    Uniform,
+    /// All roughly `angle` radians anticlockwise from the x axis, off by at
+    /// most `spread` either way.
+    Aligned {
+        angle: f32,
+        #[serde(default)]
+        spread: f32,
+    },
+    /// Circling the centre of the area, anticlockwise unless `clockwise`.
+    Vortex {
+        #[serde(default)]
+        clockwise: bool,
+    },
+}
+
+impl HeadingDistribution {
+    pub fn validate(&self) -> Result<()> {
+        if let HeadingDistribution::Aligned { angle, spread } = self {
+            ensure!(
+                angle.is_finite() && spread.is_finite(),
+                "The spawn heading's angle and spread have to be finite"
+            );
+        }
+        Ok(())
+    }
+
+    /// Unit heading of a boid spawned at `position` in an area centred on
+    /// `center`.
+    pub fn sample<R: Rng>(
+        &self,
+        rng: &mut R,
+        position: Vector2<f32>,
+        center: Vector2<f32>,
+    ) -> Vector2<f32> {
+        match self {
+            HeadingDistribution::Uniform => heading(rng.gen_range(0.0..2.0 * PI)),
+            HeadingDistribution::Aligned { angle, spread } => {
+                let offset = if *spread > 0.0 {
+                    rng.gen_range(-spread..*spread)
+                } else {
+                    0.0
+                };
+                heading(angle + offset)
+            }
+            HeadingDistribution::Vortex { clockwise } => {
+                let radial = position - center;
+                if radial.norm() < 1e-6 {
+                    return heading(rng.gen_range(0.0..2.0 * PI));
+                }
+                let tangent = Vector2::new(-radial.y, radial.x).normalize();
+                if *clockwise {
+                    -tangent
+                } else {
+                    tangent
+                }
+            }
+        }
+    }
+}
+
+/// How fast the boids of a spawn region start out, as fractions of their
+/// species' maximum speed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SpeedDistribution {
+    Uniform {
+        min: f32,
+        max: f32,
+    },
+    Constant(f32),
+    /// Never below zero.
+    Normal {
+        mean: f32,
+        std_dev: f32,
+    },
+}
+
+impl Default for SpeedDistribution {
+    fn default() -> Self {
+        SpeedDistribution::Uniform { min: 0.0, max: 1.0 }
+    }
+}
+
+impl SpeedDistribution {
+    /// Checks every speed drawn is finite and not negative.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            SpeedDistribution::Uniform { min, max } => ensure!(
+                min.is_finite() && max.is_finite() && 0.0 <= *min && min <= max,
+                "The spawn speed ranges from {} to {}, but has to be finite, not negative and \
+                 have its minimum below its maximum",
+                min,
+                max
+            ),
+            SpeedDistribution::Constant(fraction) => ensure!(
+                fraction.is_finite() && *fraction >= 0.0,
+                "The spawn speed is {}, but has to be finite and not negative",
+                fraction
+            ),
+            SpeedDistribution::Normal { mean, std_dev } => ensure!(
+                mean.is_finite() && std_dev.is_finite() && *std_dev >= 0.0,
+                "The spawn speed has a mean of {} and a standard deviation of {}, but both have \
+                 to be finite and the deviation not negative",
+                mean,
+                std_dev
+            ),
+        }
+        Ok(())
+    }
+
+    pub fn sample<R: Rng>(&self, rng: &mut R, max_speed: f32) -> f32 {
+        let fraction = match self {
+            SpeedDistribution::Uniform { min, max } => Uniform::new_inclusive(min, max).sample(rng),
+            SpeedDistribution::Constant(fraction) => *fraction,
+            SpeedDistribution::Normal { mean, std_dev } => Normal::new(*mean, *std_dev)
+                .map(|normal| normal.sample(rng))
+                .unwrap_or(*mean)
+                .max(0.0),
+        };
+        fraction * max_speed
+    }
+}
+
+/// A group of boids placed together, either when the scenario starts or
+/// around the cursor by a spawn burst.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpawnRegion {
+    pub area: SpawnArea,
+    pub count: usize,
+    /// Name of the species spawned. The scenario's first species without it.
+    #[serde(default)]
+    pub species: Option<String>,
+    #[serde(default)]
+    pub heading: HeadingDistribution,
+    #[serde(default)]
+    pub speed: SpeedDistribution,
+}
+
+impl Default for SpawnRegion {
+    fn default() -> Self {
+        SpawnRegion {
+            area: SpawnArea::Circle {
+                center: Vector2::new(0.0, 0.0),
+                radius: 40.0,
+            },
+            count: 20,
+            species: None,
+            heading: HeadingDistribution::default(),
+            speed: SpeedDistribution::default(),
+        }
+    }
+}
+
+impl SpawnRegion {
+    /// Checks every boid of the region can be placed. Regions covering the
+    /// world also need `validate_half_extents` to pass for the world.
+    pub fn validate(&self) -> Result<()> {
+        self.area.validate()?;
+        self.heading.validate()?;
+        self.speed.validate()
+    }
+
+    /// Position and velocity of a boid able to reach `max_speed`, drawn from
+    /// the region's distributions.
+    pub fn sample<R: Rng>(
+        &self,
+        rng: &mut R,
+        world_extents: Vector2<f32>,
+        max_speed: f32,
+    ) -> (Vector2<f32>, Vector2<f32>) {
+        let position = self.area.sample(rng, world_extents);
+        let heading = self.heading.sample(rng, position, self.area.center());
+        let speed = self.speed.sample(rng, max_speed);
+        (position, speed * heading)
+    }
+}
+
+/// Checks boids can be placed within `half_extents` of a point.
+pub fn validate_half_extents(half_extents: Vector2<f32>) -> Result<()> {
+    ensure!(
+        half_extents
+            .iter()
+            .all(|extent| extent.is_finite() && *extent >= 0.0),
+        "The spawn area's half extents are ({}, {}), but have to be zero or more",
+        half_extents.x,
+        half_extents.y
+    );
+    Ok(())
+}
+
+fn sample_rect<R: Rng>(rng: &mut R, half_extents: Vector2<f32>) -> Vector2<f32> {
+    Vector2::new(
+        Uniform::new_inclusive(-half_extents.x, half_extents.x).sample(rng),
+        Uniform::new_inclusive(-half_extents.y, half_extents.y).sample(rng),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn areas_contain_their_samples() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let world = Vector2::new(100.0, 50.0);
+        let rect = SpawnArea::Rect {
+            center: Vector2::new(10.0, 10.0),
+            half_extents: Vector2::new(5.0, 2.0),
+        };
+        let circle = SpawnArea::Circle {
+            center: Vector2::new(-20.0, 0.0),
+            radius: 8.0,
+        };
+        assert!(!rect.contains(Vector2::new(10.0, 13.0), world));
+        assert!(!circle.contains(Vector2::new(-20.0, 9.0), world));
+        assert!(!SpawnArea::World.contains(Vector2::new(101.0, 0.0), world));
+        for _ in 0..100 {
+            for area in [&rect, &circle, &SpawnArea::World] {
+                assert!(area.contains(area.sample(&mut rng, world), world));
+            }
+        }
+    }
+
+    #[test]
+    fn headings_follow_their_distribution() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let center = Vector2::new(0.0, 0.0);
+
+        let aligned = HeadingDistribution::Aligned {
+            angle: PI / 2.0,
+            spread: 0.0,
+        };
+        let h = aligned.sample(&mut rng, Vector2::new(3.0, 4.0), center);
+        assert!((h - Vector2::new(0.0, 1.0)).norm() < 1e-5);
+
+        let vortex = HeadingDistribution::Vortex { clockwise: false };
+        let h = vortex.sample(&mut rng, Vector2::new(10.0, 0.0), center);
+        assert!((h - Vector2::new(0.0, 1.0)).norm() < 1e-5);
+        let clockwise = HeadingDistribution::Vortex { clockwise: true };
+        let h = clockwise.sample(&mut rng, Vector2::new(10.0, 0.0), center);
+        assert!((h - Vector2::new(0.0, -1.0)).norm() < 1e-5);
+
+        let h = HeadingDistribution::Uniform.sample(&mut rng, center, center);
+        assert!((h.norm() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn speeds_are_fractions_of_max_speed() {
+        let mut rng = StdRng::seed_from_u64(3);
+        assert_eq!(
+            SpeedDistribution::Constant(0.5).sample(&mut rng, 80.0),
+            40.0
+        );
+        for _ in 0..100 {
+            let speed = SpeedDistribution::default().sample(&mut rng, 80.0);
+            assert!((0.0..=80.0).contains(&speed));
+            let normal = SpeedDistribution::Normal {
+                mean: 0.0,
+                std_dev: 1.0,
+            };
+            assert!(normal.sample(&mut rng, 80.0) >= 0.0);
+        }
+    }
+
+    #[test]
+    fn unplaceable_regions_are_rejected() {
+        assert!(SpawnRegion::default().validate().is_ok());
+        let inverted = SpawnRegion {
+            speed: SpeedDistribution::Uniform { min: 1.0, max: 0.5 },
+            ..SpawnRegion::default()
+        };
+        assert!(inverted.validate().is_err());
+        let negative = SpawnRegion {
+            area: SpawnArea::Rect {
+                center: Vector2::new(0.0, 0.0),
+                half_extents: Vector2::new(10.0, -1.0),
+            },
+            ..SpawnRegion::default()
+        };
+        assert!(negative.validate().is_err());
+        let speeds = [
+            SpeedDistribution::Uniform {
+                min: -1.0,
+                max: 0.5,
+            },
+            SpeedDistribution::Constant(f32::NAN),
+            SpeedDistribution::Constant(-0.5),
+            SpeedDistribution::Normal {
+                mean: f32::INFINITY,
+                std_dev: 0.1,
+            },
+        ];
+        for speed in speeds.iter() {
+            let region = SpawnRegion {
+                speed: speed.clone(),
+                ..SpawnRegion::default()
+            };
+            assert!(region.validate().is_err(), "{:?}", speed);
+        }
+        let lost = SpawnRegion {
+            area: SpawnArea::Circle {
+                center: Vector2::new(f32::NAN, 0.0),
+                radius: 10.0,
+            },
+            ..SpawnRegion::default()
+        };
+        assert!(lost.validate().is_err());
+        assert!(validate_half_extents(Vector2::new(0.0, 5.0)).is_ok());
+        assert!(validate_half_extents(Vector2::new(f32::NAN, 5.0)).is_err());
+    }
+}
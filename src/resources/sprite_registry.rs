@@ -0,0 +1,321 @@
+use crate::{
+    components::SpriteAnimation,
+    resources::{ObstacleConfig, Scenario},
+};
+use amethyst::{
+    assets::{AssetStorage, Handle, Loader},
+    config::Config,
+    prelude::*,
+    renderer::{
+        sprite::Sprites, ImageFormat, SpriteRender, SpriteSheet, SpriteSheetFormat, Texture,
+    },
+};
+use anyhow::{anyhow, ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path};
+
+/// Directory under the asset root that sprite textures and layouts are in.
+const SPRITE_DIRECTORY: &str = "sprites";
+
+/// Sprite used by species that don't name one.
+pub fn default_boid_sprite() -> String {
+    "boid".to_string()
+}
+
+/// Sprite used by point obstacles that don't name one.
+pub fn default_obstacle_sprite() -> String {
+    "obstacle".to_string()
+}
+
+/// A texture and the layout of the sprites in it, both relative to
+/// `resources/sprites`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SheetConfig {
+    pub texture: String,
+    pub layout: String,
+}
+
+/// Frames of a sheet that make up one named sprite.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpriteConfig {
+    pub sheet: String,
+    /// Indices into the sheet's layout. The first is the one drawn, unless
+    /// the sprite is animated.
+    #[serde(default = "default_frames")]
+    pub frames: Vec<usize>,
+    /// How boids drawn with the sprite cycle through its frames. Sprites
+    /// without it stay on the first.
+    #[serde(default)]
+    pub animation: Option<AnimationConfig>,
+}
+
+/// How fast an animated sprite's frames go by.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AnimationConfig {
+    /// Cycles through the frames per second at the boid's top speed, in
+    /// proportion to its speed.
+    #[serde(default)]
+    pub speed_rate: f32,
+    /// Cycles through the frames per second added for each radian per
+    /// second the boid turns.
+    #[serde(default)]
+    pub turn_rate: f32,
+}
+
+fn default_frames() -> Vec<usize> {
+    vec![0]
+}
+
+/// Every sprite the viewer can draw, loaded from
+/// `resources/sprites/manifest.ron`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpriteManifest {
+    pub sheets: HashMap<String, SheetConfig>,
+    pub sprites: HashMap<String, SpriteConfig>,
+    /// Sprite drawn in place of any name the manifest doesn't have.
+    pub default: String,
+}
+
+impl SpriteManifest {
+    /// Checks that every sprite is on a sheet the manifest has, with at least
+    /// one frame, and that the default sprite exists.
+    pub fn validate(&self) -> Result<()> {
+        for (name, sprite) in &self.sprites {
+            ensure!(
+                self.sheets.contains_key(&sprite.sheet),
+                "Sprite {:?} is on sheet {:?}, which the manifest does not define",
+                name,
+                sprite.sheet
+            );
+            ensure!(!sprite.frames.is_empty(), "Sprite {:?} has no frames", name);
+        }
+        ensure!(
+            self.sprites.contains_key(&self.default),
+            "The default sprite {:?} is not in the manifest",
+            self.default
+        );
+        Ok(())
+    }
+
+    /// Checks that every sprite's frames are on its sheet, reading the sheets'
+    /// layouts from `directory`. Frames past the end of a sheet would
+    /// otherwise only be found once they're drawn.
+    pub fn validate_frames(&self, directory: &Path) -> Result<()> {
+        let mut frame_counts = HashMap::new();
+        for (name, sheet) in &self.sheets {
+            let path = directory.join(&sheet.layout);
+            let layout = Sprites::load(&path)
+                .with_context(|| format!("Failed to load sprite layout {}", path.display()))?;
+            let count = match layout {
+                Sprites::List(list) => list.build_sprites().len(),
+                Sprites::Grid(grid) => grid.build_sprites().len(),
+            };
+            frame_counts.insert(name, count);
+        }
+        for (name, sprite) in &self.sprites {
+            let count = frame_counts.get(&sprite.sheet).copied().unwrap_or_default();
+            if let Some(frame) = sprite.frames.iter().find(|frame| **frame >= count) {
+                return Err(anyhow!(
+                    "Sprite {:?} uses frame {}, but sheet {:?} only has {}",
+                    name,
+                    frame,
+                    sprite.sheet,
+                    count
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sprite names `scenario` uses that the manifest doesn't have, which are
+    /// drawn with the default sprite instead.
+    pub fn missing(&self, scenario: &Scenario) -> Vec<String> {
+        let species = scenario.species.iter().map(|species| &species.sprite);
+        let obstacles = scenario
+            .obstacles
+            .iter()
+            .filter_map(|obstacle| match obstacle {
+                ObstacleConfig::Point { sprite, .. } => Some(sprite),
+                ObstacleConfig::Solid { .. } => None,
+            });
+        let mut missing = species
+            .chain(obstacles)
+            .filter(|name| !self.sprites.contains_key(*name))
+            .cloned()
+            .collect::<Vec<_>>();
+        missing.sort();
+        missing.dedup();
+        missing
+    }
+}
+
+/// A loaded sprite: the sheet it's on, and the frames of it that are its.
+#[derive(Clone, Debug)]
+pub struct Sprite {
+    pub sheet: Handle<SpriteSheet>,
+    pub frames: Vec<usize>,
+    pub animation: Option<AnimationConfig>,
+}
+
+impl Sprite {
+    /// Renders the sprite's first frame.
+    pub fn render(&self) -> SpriteRender {
+        SpriteRender {
+            sprite_sheet: self.sheet.clone(),
+            sprite_number: self.frames.first().copied().unwrap_or_default(),
+        }
+    }
+
+    /// Animates the sprite from `phase` of the way through its frames, if
+    /// it's animated and has more than one.
+    pub fn animation(&self, phase: f32) -> Option<SpriteAnimation> {
+        match self.animation {
+            Some(config) if self.frames.len() > 1 => {
+                Some(SpriteAnimation::new(self.frames.clone(), config, phase))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Sprites by name, as the `SpriteManifest` lists them. It's empty when
+/// nothing is drawn, so headless simulations spawn entities without sprites.
+#[derive(Default)]
+pub struct SpriteRegistry {
+    sprites: HashMap<String, Sprite>,
+    default: String,
+}
+
+impl SpriteRegistry {
+    pub fn new() -> SpriteRegistry {
+        SpriteRegistry::default()
+    }
+
+    /// Loads every sheet in `manifest`, and the sprites on them.
+    pub fn load(manifest: &SpriteManifest, world: &mut World) -> SpriteRegistry {
+        let sheets = manifest
+            .sheets
+            .iter()
+            .map(|(name, sheet)| (name, load_sheet(sheet, world)))
+            .collect::<HashMap<_, _>>();
+        let sprites = manifest
+            .sprites
+            .iter()
+            .filter_map(|(name, sprite)| {
+                let sheet = sheets.get(&sprite.sheet)?.clone();
+                Some((
+                    name.clone(),
+                    Sprite {
+                        sheet,
+                        frames: sprite.frames.clone(),
+                        animation: sprite.animation,
+                    },
+                ))
+            })
+            .collect();
+        SpriteRegistry {
+            sprites,
+            default: manifest.default.clone(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Sprite> {
+        self.sprites
+            .get(name)
+            .ok_or_else(|| anyhow!("There is no sprite named {:?} in the sprite manifest", name))
+    }
+
+    /// The sprite called `name`, or the default sprite if there's none by
+    /// that name. Nothing is found only when no sprites are loaded.
+    pub fn fetch_or_default(&self, name: &str) -> Option<&Sprite> {
+        self.get(name).or_else(|_| self.get(&self.default)).ok()
+    }
+}
+
+fn load_sheet(sheet: &SheetConfig, world: &World) -> Handle<SpriteSheet> {
+    let loader = world.read_resource::<Loader>();
+    let texture_handle = {
+        let texture_storage = world.read_resource::<AssetStorage<Texture>>();
+        loader.load(
+            format!("{}/{}", SPRITE_DIRECTORY, sheet.texture),
+            ImageFormat::default(),
+            (),
+            &texture_storage,
+        )
+    };
+    let sheet_storage = world.read_resource::<AssetStorage<SpriteSheet>>();
+    loader.load(
+        format!("{}/{}", SPRITE_DIRECTORY, sheet.layout),
+        SpriteSheetFormat(texture_handle),
+        (),
+        &sheet_storage,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest() -> SpriteManifest {
+        SpriteManifest::load_bytes(include_bytes!("../../resources/sprites/manifest.ron")).unwrap()
+    }
+
+    #[test]
+    fn manifests_are_validated() {
+        let mut manifest = manifest();
+        manifest.validate().unwrap();
+        assert!(manifest.sprites["boid"].animation.is_some());
+
+        manifest.default = "dragon".to_string();
+        let error = manifest.validate().unwrap_err().to_string();
+        assert!(error.contains("\"dragon\""), "{}", error);
+
+        let mut manifest = self::manifest();
+        manifest.sprites.insert(
+            "dragon".to_string(),
+            SpriteConfig {
+                sheet: "dragons".to_string(),
+                frames: vec![0],
+                animation: None,
+            },
+        );
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn frames_have_to_be_on_their_sheet() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("resources")
+            .join("sprites");
+        let mut manifest = manifest();
+        manifest.validate_frames(&directory).unwrap();
+
+        manifest.sprites.get_mut("boid").unwrap().frames.push(4);
+        let error = manifest
+            .validate_frames(&directory)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("frame 4"), "{}", error);
+
+        let mut manifest = self::manifest();
+        manifest.sheets.get_mut("boids").unwrap().layout = "missing.ron".to_string();
+        assert!(manifest.validate_frames(&directory).is_err());
+    }
+
+    #[test]
+    fn shipped_scenarios_only_use_shipped_sprites() {
+        let manifest = manifest();
+        for bytes in [
+            &include_bytes!("../../resources/scenarios/default.ron")[..],
+            &include_bytes!("../../resources/scenarios/murmuration.ron")[..],
+        ] {
+            let scenario = Scenario::load_bytes(bytes).unwrap();
+            assert_eq!(manifest.missing(&scenario), Vec::<String>::new());
+        }
+
+        let mut scenario =
+            Scenario::load_bytes(include_bytes!("../../resources/scenarios/default.ron")).unwrap();
+        scenario.species[0].sprite = "dragon".to_string();
+        assert_eq!(manifest.missing(&scenario), vec!["dragon".to_string()]);
+    }
+}
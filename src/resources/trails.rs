@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+/// When a boid's trail takes in its next position.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TrailSampling {
+    /// Once the boid is this many world units from the last position taken.
+    Distance(f32),
+    /// Once this many simulated seconds have gone by since the last position
+    /// taken.
+    Time(f32),
+}
+
+/// Whether boids keep trails of where they've been, and how long.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TrailSettings {
+    /// Whether trails are kept and drawn. Turning them off clears them.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Most positions a trail keeps.
+    #[serde(default = "default_length")]
+    pub length: usize,
+    #[serde(default = "default_sampling")]
+    pub sampling: TrailSampling,
+}
+
+impl Default for TrailSettings {
+    fn default() -> Self {
+        TrailSettings {
+            enabled: false,
+            length: default_length(),
+            sampling: default_sampling(),
+        }
+    }
+}
+
+fn default_length() -> usize {
+    30
+}
+
+fn default_sampling() -> TrailSampling {
+    TrailSampling::Distance(12.0)
+}
@@ -0,0 +1,344 @@
+//! Control and telemetry server, letting other processes watch and steer a
+//! running simulation over TCP or WebSocket. Clients send commands as lines
+//! of JSON and receive the state of every boid after each tick. Over
+//! WebSocket each message holds commands and each frame is sent as one
+//! message, text for JSON and binary otherwise.
+mod protocol;
+mod system;
+mod websocket;
+
+pub use self::protocol::{generation_of, BoidState, Command, DespawnSelector, Frame, FrameFormat};
+pub use self::system::ServerSystem;
+
+use anyhow::{anyhow, ensure, Context, Result};
+use log::{info, warn};
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+};
+
+/// Most bytes queued for a client that isn't keeping up. Frames are dropped
+/// for it rather than queued beyond this.
+const MAX_BACKLOG: usize = 4 << 20;
+
+/// Most bytes a client may send without finishing a command. Clients that
+/// send more are disconnected.
+const MAX_INCOMING: usize = 1 << 20;
+
+/// How clients talk to the server.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Transport {
+    /// Lines straight over TCP.
+    #[default]
+    Tcp,
+    /// WebSocket messages, as browsers send.
+    WebSocket,
+}
+
+/// How far a client has got through its connection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stage {
+    /// Sending lines over TCP.
+    Lines,
+    /// Yet to finish the WebSocket opening handshake.
+    Handshake,
+    /// Sending WebSocket frames.
+    Frames,
+}
+
+/// A connected client.
+struct Client {
+    stream: TcpStream,
+    address: SocketAddr,
+    stage: Stage,
+    format: FrameFormat,
+    /// Received bytes not yet making up a whole line, handshake or frame.
+    incoming: Vec<u8>,
+    /// Payloads of the frames of a WebSocket message still to be finished.
+    message: Vec<u8>,
+    /// Bytes not yet taken by the socket.
+    outgoing: Vec<u8>,
+}
+
+impl Client {
+    fn new(
+        stream: TcpStream,
+        address: SocketAddr,
+        transport: Transport,
+        format: FrameFormat,
+    ) -> Client {
+        Client {
+            stream,
+            address,
+            stage: match transport {
+                Transport::Tcp => Stage::Lines,
+                Transport::WebSocket => Stage::Handshake,
+            },
+            format,
+            incoming: vec![],
+            message: vec![],
+            outgoing: vec![],
+        }
+    }
+
+    /// Reads whatever has arrived, returning every complete command, or
+    /// `None` once the client has gone or been dropped for misbehaving.
+    fn receive(&mut self) -> Option<Vec<String>> {
+        let mut buffer = [0; 4096];
+        let mut lines = vec![];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return None,
+                Ok(read) => {
+                    self.incoming.extend_from_slice(&buffer[..read]);
+                    match self.take_commands(&mut lines) {
+                        Ok(true) => {}
+                        Ok(false) => return None,
+                        Err(e) => {
+                            warn!("Dropping client {}: {:#}", self.address, e);
+                            return None;
+                        }
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => return None,
+            }
+        }
+        Some(lines)
+    }
+
+    /// Moves every complete command out of `incoming` into `lines`,
+    /// returning false if the client closed the connection.
+    fn take_commands(&mut self, lines: &mut Vec<String>) -> Result<bool> {
+        if self.stage == Stage::Handshake {
+            let end = match self
+                .incoming
+                .windows(4)
+                .position(|window| window == b"\r\n\r\n")
+            {
+                Some(end) => end + 4,
+                None => return self.check_incoming().map(|_| true),
+            };
+            let response = websocket::handshake(&self.incoming[..end])?;
+            self.incoming.drain(..end);
+            self.outgoing.extend_from_slice(&response);
+            self.stage = Stage::Frames;
+        }
+
+        match self.stage {
+            Stage::Lines => {
+                while let Some(end) = self.incoming.iter().position(|&byte| byte == b'\n') {
+                    let line = self.incoming.drain(..=end).collect::<Vec<_>>();
+                    push_lines(lines, &line);
+                }
+            }
+            Stage::Frames => {
+                while let Some((frame, length)) = websocket::decode(&self.incoming, MAX_INCOMING)? {
+                    self.incoming.drain(..length);
+                    match frame.opcode {
+                        websocket::TEXT | websocket::BINARY | websocket::CONTINUATION => {
+                            self.message.extend_from_slice(&frame.payload);
+                            if self.message.len() > MAX_INCOMING {
+                                return Err(anyhow!(
+                                    "It sent a message of over {} bytes",
+                                    MAX_INCOMING
+                                ));
+                            }
+                            if frame.fin {
+                                push_lines(lines, &self.message);
+                                self.message.clear();
+                            }
+                        }
+                        websocket::CLOSE => return Ok(false),
+                        websocket::PING => self
+                            .outgoing
+                            .extend_from_slice(&websocket::encode(websocket::PONG, &frame.payload)),
+                        websocket::PONG => {}
+                        opcode => return Err(anyhow!("Unknown opcode {}", opcode)),
+                    }
+                }
+            }
+            Stage::Handshake => unreachable!("the handshake was finished above"),
+        }
+        self.check_incoming().map(|_| true)
+    }
+
+    fn check_incoming(&self) -> Result<()> {
+        ensure!(
+            self.incoming.len() <= MAX_INCOMING,
+            "It sent over {} bytes without finishing a command",
+            MAX_INCOMING
+        );
+        Ok(())
+    }
+
+    fn queue(&mut self, bytes: &[u8]) {
+        let framed;
+        let bytes = match self.stage {
+            Stage::Lines => bytes,
+            // Nothing is sent until the client is connected
+            Stage::Handshake => return,
+            Stage::Frames => {
+                framed = match self.format {
+                    FrameFormat::Json => websocket::encode(
+                        websocket::TEXT,
+                        bytes.strip_suffix(b"\n").unwrap_or(bytes),
+                    ),
+                    FrameFormat::Binary => websocket::encode(websocket::BINARY, bytes),
+                };
+                &framed
+            }
+        };
+        if self.outgoing.len() + bytes.len() <= MAX_BACKLOG {
+            self.outgoing.extend_from_slice(bytes);
+        }
+    }
+
+    /// Writes as much of the queue as the socket takes without blocking,
+    /// returning false once the client has gone.
+    fn flush(&mut self) -> bool {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return false,
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => return false,
+            }
+        }
+        true
+    }
+}
+
+/// The listening socket and connected clients, polled each frame by
+/// `ServerSystem`.
+pub struct Server {
+    listener: TcpListener,
+    transport: Transport,
+    clients: Vec<Client>,
+    format: FrameFormat,
+    /// Where `load_scenario` looks for scenarios and their flow grids.
+    resources: PathBuf,
+    /// Ticks left to run before pausing again, after a step command.
+    steps_remaining: Option<u32>,
+}
+
+impl Server {
+    /// Starts listening on `address`. Scenarios are loaded from `resources`.
+    pub fn bind(
+        address: impl ToSocketAddrs,
+        transport: Transport,
+        format: FrameFormat,
+        resources: &Path,
+    ) -> Result<Server> {
+        let listener = TcpListener::bind(address).context("Failed to start the server")?;
+        listener.set_nonblocking(true)?;
+        info!("Serving {:?} on {}", transport, listener.local_addr()?);
+        Ok(Server {
+            listener,
+            transport,
+            clients: vec![],
+            format,
+            resources: resources.to_path_buf(),
+            steps_remaining: None,
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    pub fn clients(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Accepts new clients, returning the lines every client has sent since
+    /// the last poll.
+    fn poll(&mut self) -> Vec<(SocketAddr, String)> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, address)) => {
+                    if let Err(e) = stream.set_nonblocking(true) {
+                        warn!("Failed to set up client {}: {}", address, e);
+                        continue;
+                    }
+                    let _ = stream.set_nodelay(true);
+                    info!("Client {} connected", address);
+                    self.clients
+                        .push(Client::new(stream, address, self.transport, self.format));
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    warn!("Failed to accept a client: {}", e);
+                    break;
+                }
+            }
+        }
+
+        let mut lines = vec![];
+        self.clients.retain_mut(|client| match client.receive() {
+            Some(received) => {
+                lines.extend(received.into_iter().map(|line| (client.address, line)));
+                true
+            }
+            None => {
+                info!("Client {} disconnected", client.address);
+                false
+            }
+        });
+        lines
+    }
+
+    /// Queues `bytes` for every client.
+    fn broadcast(&mut self, bytes: &[u8]) {
+        for client in self.clients.iter_mut() {
+            client.queue(bytes);
+        }
+    }
+
+    /// Queues `bytes` for the client at `address` alone.
+    fn reply(&mut self, address: SocketAddr, bytes: &[u8]) {
+        if let Some(client) = self
+            .clients
+            .iter_mut()
+            .find(|client| client.address == address)
+        {
+            client.queue(bytes);
+        }
+    }
+
+    /// Tells the client at `address` why its command failed, as
+    /// `{"error": ...}`. Clients sent binary frames aren't told.
+    fn reply_error(&mut self, address: SocketAddr, error: &str) {
+        if self.format == FrameFormat::Json {
+            let mut reply = serde_json::json!({ "error": error }).to_string();
+            reply.push('\n');
+            self.reply(address, reply.as_bytes());
+        }
+    }
+
+    fn flush(&mut self) {
+        self.clients.retain_mut(|client| {
+            let open = client.flush();
+            if !open {
+                info!("Client {} disconnected", client.address);
+            }
+            open
+        });
+    }
+}
+
+/// Adds each non-empty line of `bytes` to `lines`.
+fn push_lines(lines: &mut Vec<String>, bytes: &[u8]) {
+    lines.extend(
+        String::from_utf8_lossy(bytes)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string),
+    );
+}
@@ -0,0 +1,245 @@
+use crate::{
+    components::BoidData,
+    entities::despawn::DespawnTarget,
+    resources::{SpawnArea, SpawnRegion},
+};
+use amethyst::ecs::{Entities, Entity};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// A request from a client, sent as one line of JSON such as
+/// `{"command": "step", "ticks": 10}`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    Spawn {
+        region: SpawnRegion,
+    },
+    Despawn {
+        target: DespawnSelector,
+    },
+    /// Changes `BoidData` fields of a species, both for its live boids and
+    /// any spawned later.
+    SetParameters {
+        species: String,
+        parameters: Map<String, Value>,
+    },
+    Pause,
+    Resume,
+    /// Runs the simulation for `ticks` frames, then pauses it.
+    Step {
+        #[serde(default = "one")]
+        ticks: u32,
+    },
+    /// Replaces everything in the world with a scenario from
+    /// `resources/scenarios`.
+    LoadScenario {
+        name: String,
+    },
+}
+
+fn one() -> u32 {
+    1
+}
+
+/// What a despawn command removes, as in `DespawnTarget`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DespawnSelector {
+    /// The entity with this id and generation, as given in telemetry frames.
+    /// Ids are reused once an entity is removed, so one of another
+    /// generation is left alone.
+    Entity {
+        id: u32,
+        generation: u32,
+    },
+    Region(SpawnArea),
+    Species(String),
+    Boids,
+    All,
+}
+
+impl DespawnSelector {
+    /// The target, or `None` if it's an entity that no longer exists.
+    pub fn target(self, entities: &Entities) -> Option<DespawnTarget> {
+        Some(match self {
+            DespawnSelector::Entity { id, generation } => {
+                let entity = entities.entity(id);
+                if !entities.is_alive(entity) || generation_of(entity) != generation {
+                    return None;
+                }
+                DespawnTarget::Entity(entity)
+            }
+            DespawnSelector::Region(area) => DespawnTarget::Region(area),
+            DespawnSelector::Species(name) => DespawnTarget::Species(name),
+            DespawnSelector::Boids => DespawnTarget::Boids,
+            DespawnSelector::All => DespawnTarget::All,
+        })
+    }
+}
+
+/// Generation of `entity`, which together with its id tells it apart from
+/// entities that had the same id before it.
+pub fn generation_of(entity: Entity) -> u32 {
+    // Generations of live entities are positive
+    entity.gen().id() as u32
+}
+
+/// How telemetry frames are encoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FrameFormat {
+    /// One line of JSON per frame.
+    #[default]
+    Json,
+    /// Little-endian binary: the byte length of the rest of the frame as a
+    /// `u32`, the frame number as a `u64` and the boid count as a `u32`,
+    /// followed by each boid's entity id, generation and species index as
+    /// `u32`s and its position and velocity as four `f32`s.
+    Binary,
+}
+
+/// The state of one boid in a telemetry frame.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BoidState {
+    pub id: u32,
+    pub generation: u32,
+    pub species: String,
+    /// Position of the species in the scenario's species list.
+    #[serde(skip)]
+    pub species_index: u32,
+    pub position: [f32; 2],
+    pub velocity: [f32; 2],
+}
+
+/// The state of every boid after one tick.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    pub frame: u64,
+    pub time: f64,
+    pub boids: Vec<BoidState>,
+}
+
+impl Frame {
+    pub fn encode(&self, format: FrameFormat) -> Vec<u8> {
+        match format {
+            FrameFormat::Json => {
+                let mut bytes = serde_json::to_vec(self).expect("frames are serializable");
+                bytes.push(b'\n');
+                bytes
+            }
+            FrameFormat::Binary => {
+                let length = 8 + 4 + self.boids.len() * 28;
+                let mut bytes = Vec::with_capacity(4 + length);
+                bytes.extend_from_slice(&(length as u32).to_le_bytes());
+                bytes.extend_from_slice(&self.frame.to_le_bytes());
+                bytes.extend_from_slice(&(self.boids.len() as u32).to_le_bytes());
+                for boid in &self.boids {
+                    bytes.extend_from_slice(&boid.id.to_le_bytes());
+                    bytes.extend_from_slice(&boid.generation.to_le_bytes());
+                    bytes.extend_from_slice(&boid.species_index.to_le_bytes());
+                    for value in boid.position.iter().chain(boid.velocity.iter()) {
+                        bytes.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+                bytes
+            }
+        }
+    }
+}
+
+/// `boid_data` with the fields named in `parameters` replaced.
+pub fn with_parameters(boid_data: &BoidData, parameters: &Map<String, Value>) -> Result<BoidData> {
+    let mut value = serde_json::to_value(boid_data)?;
+    let fields = value.as_object_mut().expect("BoidData is a struct");
+    for (name, parameter) in parameters {
+        if !fields.contains_key(name) {
+            return Err(anyhow!("{} is not a BoidData field", name));
+        }
+        fields.insert(name.clone(), parameter.clone());
+    }
+    let boid_data = serde_json::from_value::<BoidData>(value)?;
+    boid_data.validate()?;
+    Ok(boid_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flocking::tests::boid_data;
+    use amethyst::ecs::{Builder, World, WorldExt};
+
+    #[test]
+    fn commands_parse_from_json() {
+        let command = serde_json::from_str::<Command>(r#"{"command": "step"}"#).unwrap();
+        assert_eq!(command, Command::Step { ticks: 1 });
+        let command = serde_json::from_str::<Command>(
+            r#"{"command": "despawn", "target": {"Species": "falcon"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            command,
+            Command::Despawn {
+                target: DespawnSelector::Species("falcon".to_string())
+            }
+        );
+        assert!(serde_json::from_str::<Command>(r#"{"command": "fly"}"#).is_err());
+    }
+
+    #[test]
+    fn despawns_skip_entities_of_another_generation() {
+        let mut world = World::new();
+        let removed = world.create_entity().build();
+        world.delete_entity(removed).unwrap();
+        world.maintain();
+        let reused = world.create_entity().build();
+        assert_eq!(reused.id(), removed.id());
+
+        let selector = |entity: Entity| DespawnSelector::Entity {
+            id: entity.id(),
+            generation: generation_of(entity),
+        };
+        let entities = world.entities();
+        assert!(selector(removed).target(&entities).is_none());
+        assert_eq!(
+            selector(reused).target(&entities),
+            Some(DespawnTarget::Entity(reused))
+        );
+    }
+
+    #[test]
+    fn parameters_replace_boid_data_fields() {
+        let parameters =
+            serde_json::json!({"max_speed": 20.0, "neighbourhood": {"Topological": 4}});
+        let data = with_parameters(&boid_data(), parameters.as_object().unwrap()).unwrap();
+        assert_eq!(data.max_speed, 20.0);
+        assert_eq!(data.cohesion_weight, boid_data().cohesion_weight);
+
+        let unknown = serde_json::json!({"wingspan": 3.0});
+        assert!(with_parameters(&boid_data(), unknown.as_object().unwrap()).is_err());
+        let still = serde_json::json!({"max_speed": 0});
+        assert!(with_parameters(&boid_data(), still.as_object().unwrap()).is_err());
+    }
+
+    #[test]
+    fn binary_frames_are_length_prefixed() {
+        let frame = Frame {
+            frame: 7,
+            time: 0.5,
+            boids: vec![BoidState {
+                id: 3,
+                generation: 2,
+                species: "boid".to_string(),
+                species_index: 1,
+                position: [1.0, 2.0],
+                velocity: [3.0, 4.0],
+            }],
+        };
+        let bytes = frame.encode(FrameFormat::Binary);
+        assert_eq!(bytes.len(), 4 + 8 + 4 + 28);
+        assert_eq!(&bytes[..4], &40u32.to_le_bytes());
+        assert_eq!(&bytes[4..12], &7u64.to_le_bytes());
+        assert_eq!(&bytes[16..20], &3u32.to_le_bytes());
+        assert_eq!(&bytes[20..24], &2u32.to_le_bytes());
+        assert_eq!(&bytes[40..44], &4.0f32.to_le_bytes());
+    }
+}
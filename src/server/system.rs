@@ -0,0 +1,506 @@
+use super::{
+    protocol::{generation_of, with_parameters, BoidState, Command, Frame},
+    Server,
+};
+use crate::{
+    components::{BoidData, Position, Species, Velocity},
+    entities::{
+        boids::spawn_region,
+        despawn::despawn,
+        scenario::{replace_scenario, DEFAULT_WORLD_EXTENTS},
+    },
+    resources::{Scenario, SimulationState, SpriteRegistry},
+};
+use amethyst::{
+    config::Config,
+    core::Time,
+    ecs::{prelude::*, Entities, LazyUpdate, Read, ReadStorage, System, WriteExpect},
+    window::ScreenDimensions,
+};
+use anyhow::{anyhow, ensure, Context, Result};
+use log::warn;
+use nalgebra::Vector2;
+use serde_json::{Map, Value};
+use std::path::{Component, Path};
+
+/// Serves the `Server` resource: sends every client the state of the boids
+/// after each tick, and queues the commands they send to be applied when the
+/// world is next maintained.
+pub struct ServerSystem;
+
+impl<'s> System<'s> for ServerSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, BoidData>,
+        ReadStorage<'s, Position>,
+        ReadStorage<'s, Velocity>,
+        ReadStorage<'s, Species>,
+        WriteExpect<'s, Scenario>,
+        Read<'s, SpriteRegistry>,
+        Option<Read<'s, ScreenDimensions>>,
+        Read<'s, Time>,
+        Read<'s, SimulationState>,
+        Read<'s, LazyUpdate>,
+        WriteExpect<'s, Server>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            boid_datas,
+            positions,
+            velocities,
+            species,
+            mut scenario,
+            sprite_registry,
+            screen_dimensions,
+            time,
+            state,
+            updater,
+            mut server,
+        ): Self::SystemData,
+    ) {
+        let world_extents = screen_dimensions
+            .map(|dimensions| Vector2::new(dimensions.width(), dimensions.height()) / 2.0)
+            .unwrap_or_else(|| Vector2::new(DEFAULT_WORLD_EXTENTS.0, DEFAULT_WORLD_EXTENTS.1));
+
+        for (address, line) in server.poll() {
+            let command = match serde_json::from_str::<Command>(&line) {
+                Ok(command) => command,
+                Err(e) => {
+                    warn!("Invalid command from {}: {}", address, e);
+                    server.reply_error(address, &e.to_string());
+                    continue;
+                }
+            };
+
+            let queued = match command {
+                Command::Spawn { region } => spawn_region(
+                    &entities,
+                    &sprite_registry,
+                    &updater,
+                    &scenario,
+                    &region,
+                    world_extents,
+                ),
+                Command::Despawn { target } => target
+                    .target(&entities)
+                    .map(|target| despawn(&updater, target))
+                    .ok_or_else(|| anyhow!("The entity does not exist")),
+                Command::LoadScenario { name } => {
+                    load_scenario(&server.resources, &name).and_then(|new_scenario| {
+                        // Fixtures are replaced before the boids are given
+                        // their components, so the boids aren't removed with
+                        // the old ones
+                        let fixtures = new_scenario.clone();
+                        updater.exec_mut(move |world| replace_scenario(world, fixtures));
+                        for region in &new_scenario.spawns {
+                            spawn_region(
+                                &entities,
+                                &sprite_registry,
+                                &updater,
+                                &new_scenario,
+                                region,
+                                world_extents,
+                            )?;
+                        }
+                        Ok(())
+                    })
+                }
+                // Later spawns get the new parameters straight away, while
+                // boids already queued get them along with the live ones
+                Command::SetParameters {
+                    species: name,
+                    parameters,
+                } => scenario
+                    .species
+                    .iter_mut()
+                    .find(|config| config.name == name)
+                    .ok_or_else(|| anyhow!("The scenario does not define species {}", name))
+                    .and_then(|config| {
+                        config.boid = with_parameters(&config.boid, &parameters)?;
+                        updater.exec_mut(move |world| {
+                            if let Err(e) = set_parameters(world, &name, &parameters) {
+                                warn!("Command from {} failed: {:#}", address, e);
+                                world
+                                    .write_resource::<Server>()
+                                    .reply_error(address, &format!("{:#}", e));
+                            }
+                        });
+                        Ok(())
+                    }),
+                Command::Pause => {
+                    updater.exec_mut(|world| set_state(world, SimulationState::Paused, None));
+                    Ok(())
+                }
+                Command::Resume => {
+                    updater.exec_mut(|world| set_state(world, SimulationState::Running, None));
+                    Ok(())
+                }
+                Command::Step { ticks } => {
+                    if ticks > 0 {
+                        updater.exec_mut(move |world| {
+                            set_state(world, SimulationState::Running, Some(ticks))
+                        });
+                    }
+                    Ok(())
+                }
+            };
+            if let Err(e) = queued {
+                warn!("Command from {} failed: {:#}", address, e);
+                server.reply_error(address, &format!("{:#}", e));
+            }
+        }
+
+        if *state == SimulationState::Running {
+            if let Some(remaining) = server.steps_remaining {
+                let remaining = remaining.saturating_sub(1);
+                server.steps_remaining = Some(remaining);
+                if remaining == 0 {
+                    updater.exec_mut(|world| set_state(world, SimulationState::Paused, None));
+                }
+            }
+
+            let boids = (&entities, &boid_datas, &positions, &velocities, &species)
+                .join()
+                .map(|(entity, _, position, velocity, species)| BoidState {
+                    id: entity.id(),
+                    generation: generation_of(entity),
+                    species: species.0.clone(),
+                    species_index: scenario
+                        .species
+                        .iter()
+                        .position(|config| config.name == species.0)
+                        .unwrap_or_default() as u32,
+                    position: [position.0.x, position.0.y],
+                    velocity: [velocity.0.x, velocity.0.y],
+                })
+                .collect();
+            let frame = Frame {
+                frame: time.frame_number(),
+                time: time.absolute_time_seconds(),
+                boids,
+            };
+            let bytes = frame.encode(server.format);
+            server.broadcast(&bytes);
+        }
+        server.flush();
+    }
+}
+
+/// Loads the scenario called `name` from `resources/scenarios`. Names are
+/// file names alone, so clients can't read files from anywhere else.
+fn load_scenario(resources: &Path, name: &str) -> Result<Scenario> {
+    let mut components = Path::new(name).components();
+    ensure!(
+        matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        ),
+        "{} is not the name of a file in the scenario directory",
+        name
+    );
+    let path = resources.join("scenarios").join(name);
+    let mut scenario = Scenario::load(&path)
+        .with_context(|| format!("Failed to load scenario {}", path.display()))?;
+    scenario.flow.load_grids(&resources.join("flows"))?;
+    scenario.validate()?;
+    Ok(scenario)
+}
+
+/// Replaces `BoidData` fields of every live boid of the species called `name`.
+fn set_parameters(world: &mut World, name: &str, parameters: &Map<String, Value>) -> Result<()> {
+    let (species, mut boid_datas) =
+        world.system_data::<(ReadStorage<Species>, WriteStorage<BoidData>)>();
+    for (species, boid_data) in (&species, &mut boid_datas).join() {
+        if species.0 == name {
+            *boid_data = with_parameters(boid_data, parameters)?;
+        }
+    }
+    Ok(())
+}
+
+/// Sets whether the simulation runs, and for how many ticks before pausing.
+fn set_state(world: &mut World, state: SimulationState, steps_remaining: Option<u32>) {
+    world.insert(state);
+    world.write_resource::<Server>().steps_remaining = steps_remaining;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        server::{websocket, FrameFormat, Transport, MAX_INCOMING},
+        simulation::Simulation,
+    };
+    use amethyst::ecs::RunNow;
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpStream,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Simulation serving over `transport` on a free loopback port, with a
+    /// client connected.
+    fn serve(transport: Transport) -> (Simulation<'static, 'static>, BufReader<TcpStream>) {
+        let scenario =
+            Scenario::load_bytes(include_bytes!("../../resources/scenarios/default.ron")).unwrap();
+        let mut simulation = Simulation::new(scenario).unwrap();
+        let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+        let server = Server::bind("127.0.0.1:0", transport, FrameFormat::Json, &resources).unwrap();
+        let client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        client.set_read_timeout(Some(TIMEOUT)).unwrap();
+        simulation.world.insert(server);
+        RunNow::setup(&mut ServerSystem, &mut simulation.world);
+
+        let deadline = Instant::now() + TIMEOUT;
+        while simulation.world.read_resource::<Server>().clients() == 0 {
+            assert!(Instant::now() < deadline, "the client was never accepted");
+            ServerSystem.run_now(&simulation.world);
+        }
+        (simulation, BufReader::new(client))
+    }
+
+    /// Runs the server and applies what it queued.
+    fn poll(simulation: &mut Simulation) {
+        ServerSystem.run_now(&simulation.world);
+        simulation.world.maintain();
+    }
+
+    /// Sends `bytes`, waiting until the server can read all of them.
+    fn send_bytes(simulation: &Simulation, client: &mut BufReader<TcpStream>, bytes: &[u8]) {
+        client.get_mut().write_all(bytes).unwrap();
+        let server = simulation.world.read_resource::<Server>();
+        let mut buffer = vec![0; bytes.len()];
+        let deadline = Instant::now() + TIMEOUT;
+        while server.clients[0].stream.peek(&mut buffer).unwrap_or(0) < bytes.len() {
+            assert!(
+                Instant::now() < deadline,
+                "the server never received {:?}",
+                bytes
+            );
+            thread::yield_now();
+        }
+    }
+
+    /// Sends `commands` as lines, waiting until the server can read them.
+    fn send(simulation: &Simulation, client: &mut BufReader<TcpStream>, commands: &[&str]) {
+        let lines = commands.iter().map(|command| format!("{}\n", command));
+        send_bytes(simulation, client, lines.collect::<String>().as_bytes());
+    }
+
+    /// The last frame the client has been sent.
+    fn last_frame(client: &mut BufReader<TcpStream>) -> Frame {
+        let mut line = String::new();
+        client.read_line(&mut line).unwrap();
+        let mut last = serde_json::from_str::<Frame>(&line).unwrap();
+
+        client.get_ref().set_nonblocking(true).unwrap();
+        line.clear();
+        while client.read_line(&mut line).is_ok() && line.ends_with('\n') {
+            last = serde_json::from_str::<Frame>(&line).unwrap();
+            line.clear();
+        }
+        client.get_ref().set_nonblocking(false).unwrap();
+        last
+    }
+
+    /// The next error the client is sent, skipping any frames before it.
+    fn next_error(client: &mut BufReader<TcpStream>) -> String {
+        loop {
+            let mut line = String::new();
+            client.read_line(&mut line).unwrap();
+            if line.starts_with(r#"{"error""#) {
+                return line;
+            }
+        }
+    }
+
+    /// The opcode and payload of the next WebSocket message from the server.
+    fn read_message(client: &mut BufReader<TcpStream>) -> (u8, Vec<u8>) {
+        let mut header = [0; 2];
+        client.read_exact(&mut header).unwrap();
+        let length = match header[1] {
+            126 => {
+                let mut length = [0; 2];
+                client.read_exact(&mut length).unwrap();
+                u16::from_be_bytes(length) as usize
+            }
+            127 => {
+                let mut length = [0; 8];
+                client.read_exact(&mut length).unwrap();
+                u64::from_be_bytes(length) as usize
+            }
+            length => length as usize,
+        };
+        let mut payload = vec![0; length];
+        client.read_exact(&mut payload).unwrap();
+        (header[0] & 0x0f, payload)
+    }
+
+    #[test]
+    fn clients_receive_frames_and_steer_the_simulation() {
+        let (mut simulation, mut client) = serve(Transport::Tcp);
+        poll(&mut simulation);
+        let frame = last_frame(&mut client);
+        assert_eq!(frame.boids.len(), simulation.metrics().boids);
+
+        send(
+            &simulation,
+            &mut client,
+            &[
+                r#"{"command": "despawn", "target": "Boids"}"#,
+                r#"{"command": "set_parameters", "species": "boid", "parameters": {"max_speed": 1.0}}"#,
+                r#"{"command": "spawn", "region": {"area": "World", "count": 5, "species": "boid"}}"#,
+            ],
+        );
+        poll(&mut simulation);
+        poll(&mut simulation);
+        let frame = last_frame(&mut client);
+        assert_eq!(frame.boids.len(), 5);
+        assert!(frame.boids.iter().all(|boid| boid.species == "boid"));
+        let boid_datas = simulation.world.read_storage::<BoidData>();
+        assert!(boid_datas.join().all(|data| data.max_speed == 1.0));
+    }
+
+    #[test]
+    fn steps_pause_afterwards_and_bad_commands_are_answered() {
+        let (mut simulation, mut client) = serve(Transport::Tcp);
+        send(
+            &simulation,
+            &mut client,
+            &[
+                r#"{"command": "step", "ticks": 2}"#,
+                r#"{"command": "fly"}"#,
+            ],
+        );
+        poll(&mut simulation);
+        next_error(&mut client);
+
+        // Two ticks run, after which the simulation pauses
+        for _ in 0..2 {
+            assert_eq!(
+                *simulation.world.read_resource::<SimulationState>(),
+                SimulationState::Running
+            );
+            poll(&mut simulation);
+        }
+        assert_eq!(
+            *simulation.world.read_resource::<SimulationState>(),
+            SimulationState::Paused
+        );
+
+        // Regions boids can't be placed in are turned down rather than
+        // taking the server with them
+        let boids = simulation.metrics().boids;
+        send(
+            &simulation,
+            &mut client,
+            &[
+                r#"{"command": "spawn", "region": {"area": {"Rect": {"center": [0, 0], "half_extents": [-5, 5]}}, "count": 5}}"#,
+            ],
+        );
+        poll(&mut simulation);
+        assert!(next_error(&mut client).contains("half extents"));
+        assert_eq!(simulation.metrics().boids, boids);
+
+        // Scenarios are only loaded from the scenario directory
+        for name in ["../scenarios/murmuration.ron", "/etc/passwd", ".."] {
+            send(
+                &simulation,
+                &mut client,
+                &[&format!(
+                    r#"{{"command": "load_scenario", "name": "{}"}}"#,
+                    name
+                )],
+            );
+            poll(&mut simulation);
+            assert!(next_error(&mut client).contains("scenario directory"));
+        }
+        assert_eq!(
+            simulation.world.read_resource::<Scenario>().species[0].name,
+            "boid"
+        );
+
+        send(
+            &simulation,
+            &mut client,
+            &[r#"{"command": "load_scenario", "name": "murmuration.ron"}"#],
+        );
+        poll(&mut simulation);
+        assert_eq!(
+            simulation.world.read_resource::<Scenario>().species[0].name,
+            "starling"
+        );
+        assert_eq!(simulation.count("boid"), 0);
+        assert!(simulation.count("starling") > 0);
+    }
+
+    #[test]
+    fn clients_sending_endless_lines_are_dropped() {
+        let (mut simulation, mut client) = serve(Transport::Tcp);
+        let chunk = vec![b'x'; 64 << 10];
+        for _ in 0..2 * MAX_INCOMING / chunk.len() {
+            send_bytes(&simulation, &mut client, &chunk);
+            poll(&mut simulation);
+            if simulation.world.read_resource::<Server>().clients() == 0 {
+                return;
+            }
+        }
+        panic!("the client was never dropped");
+    }
+
+    #[test]
+    fn websocket_clients_are_served() {
+        let (mut simulation, mut client) = serve(Transport::WebSocket);
+        send_bytes(
+            &simulation,
+            &mut client,
+            b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+              Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+        );
+        poll(&mut simulation);
+        let mut response = String::new();
+        while !response.ends_with("\r\n\r\n") {
+            client.read_line(&mut response).unwrap();
+        }
+        assert!(response.starts_with("HTTP/1.1 101"), "{}", response);
+
+        let (opcode, payload) = read_message(&mut client);
+        assert_eq!(opcode, websocket::TEXT);
+        let frame = serde_json::from_slice::<Frame>(&payload).unwrap();
+        assert_eq!(frame.boids.len(), simulation.metrics().boids);
+
+        // Commands may be split across frames
+        let command = br#"{"command": "pause"}"#;
+        let mut first = websocket::masked(websocket::TEXT, &command[..5], [7, 1, 2, 3]);
+        first[0] &= 0x7f;
+        let rest = websocket::masked(websocket::CONTINUATION, &command[5..], [9, 8, 7, 6]);
+        let fly = websocket::masked(websocket::TEXT, br#"{"command": "fly"}"#, [1, 1, 1, 1]);
+        send_bytes(&simulation, &mut client, &[first, rest, fly].concat());
+        poll(&mut simulation);
+        assert_eq!(
+            *simulation.world.read_resource::<SimulationState>(),
+            SimulationState::Paused
+        );
+        loop {
+            let (opcode, payload) = read_message(&mut client);
+            assert_eq!(opcode, websocket::TEXT);
+            if payload.starts_with(br#"{"error""#) {
+                break;
+            }
+        }
+
+        send_bytes(
+            &simulation,
+            &mut client,
+            &websocket::masked(websocket::CLOSE, &[], [0; 4]),
+        );
+        poll(&mut simulation);
+        assert_eq!(simulation.world.read_resource::<Server>().clients(), 0);
+    }
+}
@@ -0,0 +1,178 @@
+//! Just enough of WebSocket (RFC 6455) for browsers to connect: the opening
+//! handshake and the framing of messages either way. Extensions and
+//! subprotocols aren't negotiated.
+use anyhow::{anyhow, ensure, Result};
+use std::convert::TryInto;
+
+/// Appended to a client's key before hashing it, as fixed by the RFC.
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+pub const CONTINUATION: u8 = 0x0;
+pub const TEXT: u8 = 0x1;
+pub const BINARY: u8 = 0x2;
+pub const CLOSE: u8 = 0x8;
+pub const PING: u8 = 0x9;
+pub const PONG: u8 = 0xa;
+
+/// The response accepting the opening handshake `request`, which runs up to
+/// and including the blank line ending its headers.
+pub fn handshake(request: &[u8]) -> Result<Vec<u8>> {
+    let request = std::str::from_utf8(request)?;
+    let mut lines = request.split("\r\n");
+    let request_line = lines.next().unwrap_or_default();
+    ensure!(
+        request_line.starts_with("GET "),
+        "Expected a GET request, got {}",
+        request_line
+    );
+    let key = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("Sec-WebSocket-Key"))
+        .map(|(_, key)| key.trim())
+        .ok_or_else(|| anyhow!("The handshake has no Sec-WebSocket-Key"))?;
+    Ok(format!(
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key)
+    )
+    .into_bytes())
+}
+
+fn accept_key(key: &str) -> String {
+    let mut hash = sha1_smol::Sha1::new();
+    hash.update(key.as_bytes());
+    hash.update(GUID.as_bytes());
+    base64::encode(hash.digest().bytes())
+}
+
+/// A frame sent by a client, with its payload unmasked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientFrame {
+    /// Whether this is the last frame of its message.
+    pub fin: bool,
+    pub opcode: u8,
+    pub payload: Vec<u8>,
+}
+
+/// Decodes the frame at the start of `bytes`, returning it along with how
+/// many bytes it took, or `None` if it hasn't all arrived yet. Frames that
+/// aren't masked or have payloads longer than `max_length` are refused.
+pub fn decode(bytes: &[u8], max_length: usize) -> Result<Option<(ClientFrame, usize)>> {
+    if bytes.len() < 2 {
+        return Ok(None);
+    }
+    ensure!(bytes[1] & 0x80 != 0, "Frames from clients must be masked");
+    let (length, header) = match bytes[1] & 0x7f {
+        126 if bytes.len() < 4 => return Ok(None),
+        126 => (u16::from_be_bytes([bytes[2], bytes[3]]) as u64, 4),
+        127 if bytes.len() < 10 => return Ok(None),
+        127 => (u64::from_be_bytes(bytes[2..10].try_into()?), 10),
+        length => (length as u64, 2),
+    };
+    ensure!(
+        length <= max_length as u64,
+        "A frame of {} bytes is over the limit of {}",
+        length,
+        max_length
+    );
+    let start = header + 4;
+    let end = start + length as usize;
+    if bytes.len() < end {
+        return Ok(None);
+    }
+
+    let mask = &bytes[header..start];
+    let payload = bytes[start..end]
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| byte ^ mask[i % 4])
+        .collect();
+    let frame = ClientFrame {
+        fin: bytes[0] & 0x80 != 0,
+        opcode: bytes[0] & 0x0f,
+        payload,
+    };
+    Ok(Some((frame, end)))
+}
+
+/// A whole, unmasked message as servers send them.
+pub fn encode(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(payload.len() + 10);
+    bytes.push(0x80 | opcode);
+    match payload.len() {
+        length @ 0..=125 => bytes.push(length as u8),
+        length @ 126..=0xffff => {
+            bytes.push(126);
+            bytes.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            bytes.push(127);
+            bytes.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+/// `payload` framed as a client would, masked with `mask`.
+#[cfg(test)]
+pub fn masked(opcode: u8, payload: &[u8], mask: [u8; 4]) -> Vec<u8> {
+    let mut bytes = encode(opcode, payload);
+    let header = bytes.len() - payload.len();
+    bytes[1] |= 0x80;
+    for (i, byte) in bytes[header..].iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+    bytes.splice(header..header, mask);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handshakes_are_accepted_with_the_hashed_key() {
+        // The example from RFC 6455
+        let response = handshake(
+            b"GET /chat HTTP/1.1\r\nHost: server.example.com\r\nUpgrade: websocket\r\n\
+              Connection: Upgrade\r\nsec-websocket-key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+        )
+        .unwrap();
+        let response = String::from_utf8(response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 101"), "{}", response);
+        assert!(
+            response.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"),
+            "{}",
+            response
+        );
+
+        assert!(handshake(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").is_err());
+        assert!(handshake(b"POST / HTTP/1.1\r\nSec-WebSocket-Key: a\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn frames_are_decoded_once_they_have_all_arrived() {
+        for length in [5, 300, 70_000] {
+            let payload = vec![b'x'; length];
+            let bytes = masked(TEXT, &payload, [1, 2, 3, 4]);
+            assert_eq!(decode(&bytes[..bytes.len() - 1], 1 << 20).unwrap(), None);
+            let (frame, used) = decode(&bytes, 1 << 20).unwrap().unwrap();
+            assert_eq!(used, bytes.len());
+            assert_eq!(
+                frame,
+                ClientFrame {
+                    fin: true,
+                    opcode: TEXT,
+                    payload,
+                }
+            );
+        }
+
+        // Unmasked and oversized frames are refused
+        assert!(decode(&encode(TEXT, b"hi"), 1 << 20).is_err());
+        assert!(decode(&masked(TEXT, &[0; 300], [0; 4]), 100).is_err());
+    }
+}
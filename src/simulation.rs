@@ -0,0 +1,272 @@
+use crate::{
+    components::{BoidData, Position, Species, Velocity},
+    entities::{boids::spawn_boids, scenario::load_scenario},
+    resources::{PopulationHistory, Scenario, SpeciesConfig, SpriteRegistry},
+    spatial::SpatialGrid,
+    systems::{BoidSystem, LifecycleSystem, PhysicsSystem, TrailSystem},
+};
+use amethyst::{
+    core::{Time, Transform},
+    ecs::{prelude::*, Dispatcher, DispatcherBuilder},
+    renderer::{SpriteRender, Transparent},
+};
+use anyhow::Result;
+use nalgebra::Vector2;
+
+/// Summary of the state of every boid at one point in time.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metrics {
+    pub boids: usize,
+    /// Length of the average heading, from 0 when boids head every which way
+    /// to 1 when they all head the same way.
+    pub polarization: f32,
+    /// Length of the average angular momentum of the boids' headings around
+    /// their centre, reaching 1 when they all circle it the same way.
+    pub milling: f32,
+    pub mean_speed: f32,
+    /// Average distance from each boid to its nearest neighbour.
+    pub nearest_neighbour: f32,
+}
+
+/// The simulation systems on their own, without a window, renderer or input,
+/// for experiments that only care about the numbers. Every tick advances
+/// time by the fixed time step, however long it takes to compute.
+pub struct Simulation<'a, 'b> {
+    pub world: World,
+    dispatcher: Dispatcher<'a, 'b>,
+}
+
+impl<'a, 'b> Simulation<'a, 'b> {
+    /// Sets up a world with the obstacles, attractors, food and boids of
+    /// `scenario`. Flow grids have to be loaded beforehand.
+    pub fn new(scenario: Scenario) -> Result<Simulation<'a, 'b>> {
+        scenario.validate()?;
+        let mut world = World::new();
+        let mut dispatcher = DispatcherBuilder::new()
+            .with(BoidSystem, "boid_system", &[])
+            .with(PhysicsSystem, "physics_system", &["boid_system"])
+            .with(LifecycleSystem, "lifecycle_system", &["physics_system"])
+            .with(TrailSystem, "trail_system", &["physics_system"])
+            .build();
+
+        // Written by `fill_boid` but read by none of the systems above
+        world.register::<Species>();
+        world.register::<Transform>();
+        world.register::<Transparent>();
+        world.register::<SpriteRender>();
+
+        world.insert(scenario.flow.clone());
+        world.insert(scenario.trails.clone());
+        world.insert(scenario);
+        world.insert(SpriteRegistry::new());
+        world.insert(Time::default());
+        world.insert(PopulationHistory::default());
+        dispatcher.setup(&mut world);
+        load_scenario(&mut world)?;
+        world.maintain();
+
+        Ok(Simulation { world, dispatcher })
+    }
+
+    /// Places `count` boids of `species` uniformly within `half_extents` of
+    /// the origin.
+    pub fn spawn(
+        &mut self,
+        species: &SpeciesConfig,
+        count: usize,
+        half_extents: Vector2<f32>,
+    ) -> Result<()> {
+        spawn_boids(&self.world, species, count, half_extents)?;
+        self.world.maintain();
+        Ok(())
+    }
+
+    pub fn step(&mut self) {
+        {
+            let mut time = self.world.write_resource::<Time>();
+            let fixed_time = time.fixed_time();
+            time.set_delta_time(fixed_time);
+            time.increment_frame_number();
+        }
+        self.dispatcher.dispatch_seq(&self.world);
+        self.world.maintain();
+    }
+
+    pub fn run(&mut self, ticks: usize) {
+        for _ in 0..ticks {
+            self.step();
+        }
+    }
+
+    /// Live boids of the species called `species`.
+    pub fn count(&self, species: &str) -> usize {
+        let species_storage = self.world.read_storage::<Species>();
+        species_storage
+            .join()
+            .filter(|other| other.0 == species)
+            .count()
+    }
+
+    pub fn polarization(&self) -> f32 {
+        self.metrics().polarization
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        let (boid_datas, positions, velocities) = self.world.system_data::<(
+            ReadStorage<BoidData>,
+            ReadStorage<Position>,
+            ReadStorage<Velocity>,
+        )>();
+        let boids = (&boid_datas, &positions, &velocities)
+            .join()
+            .map(|(_, position, velocity)| (position.0, velocity.0))
+            .collect::<Vec<_>>();
+        if boids.is_empty() {
+            return Metrics::default();
+        }
+        let count = boids.len() as f32;
+
+        let centre = boids.iter().map(|(p, _)| p).sum::<Vector2<f32>>() / count;
+        let (heading_sum, momentum_sum, speed_sum) = boids.iter().fold(
+            (Vector2::new(0.0, 0.0), 0.0, 0.0),
+            |(headings, momentum, speeds), (position, velocity)| {
+                let heading = velocity.try_normalize(0.0).unwrap_or_else(Vector2::zeros);
+                let radial = (position - centre)
+                    .try_normalize(0.0)
+                    .unwrap_or_else(Vector2::zeros);
+                (
+                    headings + heading,
+                    momentum + radial.perp(&heading),
+                    speeds + velocity.norm(),
+                )
+            },
+        );
+
+        let positions = boids.iter().map(|(p, _)| *p).collect::<Vec<_>>();
+        let grid = SpatialGrid::new(100.0, positions.clone());
+        let nearest_sum = positions
+            .iter()
+            .enumerate()
+            .filter_map(|(i, position)| {
+                grid.nearest(*position, 1, Some(i))
+                    .first()
+                    .map(|&j| (positions[j] - position).norm())
+            })
+            .sum::<f32>();
+
+        Metrics {
+            boids: boids.len(),
+            polarization: (heading_sum / count).norm(),
+            milling: (momentum_sum / count).abs(),
+            mean_speed: speed_sum / count,
+            nearest_neighbour: nearest_sum / count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        components::{ObstacleShape, Position},
+        random,
+    };
+    use amethyst::config::Config;
+
+    fn default_scenario() -> Scenario {
+        Scenario::load_bytes(include_bytes!("../resources/scenarios/default.ron")).unwrap()
+    }
+
+    #[test]
+    fn runs_without_rendering() {
+        let scenario = default_scenario();
+        let species = scenario.default_species().unwrap().clone();
+        let mut simulation = Simulation::new(scenario).unwrap();
+        assert!(simulation.world.read_storage::<ObstacleShape>().count() > 0);
+
+        simulation
+            .spawn(&species, 20, Vector2::new(300.0, 200.0))
+            .unwrap();
+        assert_eq!(simulation.count(&species.name), 20);
+        let start = simulation
+            .world
+            .read_storage::<Position>()
+            .join()
+            .map(|p| p.0)
+            .collect::<Vec<_>>();
+
+        simulation.run(10);
+        let positions = simulation.world.read_storage::<Position>();
+        let moved = positions
+            .join()
+            .zip(start.iter())
+            .filter(|(now, start)| now.0 != **start)
+            .count();
+        assert!(moved > 0);
+        assert_eq!(simulation.world.read_resource::<Time>().frame_number(), 10);
+
+        let polarization = simulation.polarization();
+        assert!((0.0..=1.0 + 1e-4).contains(&polarization));
+    }
+
+    #[test]
+    fn scenarios_spawn_their_regions() {
+        let scenario =
+            Scenario::load_bytes(include_bytes!("../resources/scenarios/murmuration.ron")).unwrap();
+        let simulation = Simulation::new(scenario).unwrap();
+        assert_eq!(simulation.count("starling"), 200);
+        assert_eq!(simulation.count("falcon"), 3);
+
+        // Boids of the circle all start out circling its centre
+        let positions = simulation.world.read_storage::<Position>();
+        let velocities = simulation.world.read_storage::<Velocity>();
+        let circling = (&positions, &velocities)
+            .join()
+            .filter(|(p, v)| (p.0 - Vector2::new(450.0, 0.0)).norm() <= 150.0 && v.0.norm() > 0.0)
+            .filter(|(p, v)| (p.0 - Vector2::new(450.0, 0.0)).dot(&v.0).abs() < 1e-2)
+            .count();
+        assert!(circling >= 80);
+    }
+
+    #[test]
+    fn metrics_of_a_milling_square() {
+        let mut simulation = Simulation::new(default_scenario()).unwrap();
+        let boid = default_scenario().default_species().unwrap().boid.clone();
+        let corners = [(1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0), (1.0, -1.0)];
+        for &(x, y) in corners.iter() {
+            simulation
+                .world
+                .create_entity()
+                .with(boid.clone())
+                .with(Position(Vector2::new(x * 10.0, y * 10.0)))
+                // Anticlockwise around the origin
+                .with(Velocity(Vector2::new(-y * 5.0, x * 5.0)))
+                .build();
+        }
+
+        let metrics = simulation.metrics();
+        assert_eq!(metrics.boids, 4);
+        assert!(metrics.polarization < 1e-4);
+        assert!((metrics.milling - 1.0).abs() < 1e-4);
+        assert!((metrics.mean_speed - 50.0f32.sqrt()).abs() < 1e-4);
+        assert!((metrics.nearest_neighbour - 20.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn seeded_runs_repeat_exactly() {
+        let run = |seed| {
+            random::seed(seed);
+            let scenario = default_scenario();
+            let species = scenario.default_species().unwrap().clone();
+            let mut simulation = Simulation::new(scenario).unwrap();
+            simulation
+                .spawn(&species, 10, Vector2::new(300.0, 200.0))
+                .unwrap();
+            simulation.run(20);
+            simulation.metrics()
+        };
+
+        assert_eq!(run(5), run(5));
+        assert_ne!(run(5), run(6));
+    }
+}
@@ -0,0 +1,69 @@
+use delaunator::{triangulate, Point};
+use nalgebra::Vector2;
+
+/// For each point, the indices of the points sharing an edge with it in the
+/// Delaunay triangulation, i.e. its Voronoi-adjacent neighbours. Collinear
+/// sets are linked along the line, and exact duplicates get no neighbours.
+pub fn delaunay_neighbours(positions: &[Vector2<f32>]) -> Vec<Vec<usize>> {
+    let points = positions
+        .iter()
+        .map(|p| Point {
+            x: p.x as f64,
+            y: p.y as f64,
+        })
+        .collect::<Vec<_>>();
+    let triangulation = triangulate(&points);
+    let mut neighbours = vec![Vec::new(); positions.len()];
+
+    if triangulation.triangles.is_empty() {
+        for pair in triangulation.hull.windows(2) {
+            neighbours[pair[0]].push(pair[1]);
+            neighbours[pair[1]].push(pair[0]);
+        }
+    } else {
+        for triangle in triangulation.triangles.chunks(3) {
+            for (a, b) in [(0, 1), (1, 2), (2, 0)].iter() {
+                neighbours[triangle[*a]].push(triangle[*b]);
+                neighbours[triangle[*b]].push(triangle[*a]);
+            }
+        }
+    }
+
+    for list in neighbours.iter_mut() {
+        list.sort_unstable();
+        list.dedup();
+    }
+    neighbours
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square_with_center() {
+        let neighbours = delaunay_neighbours(&[
+            Vector2::new(-10.0, -10.0),
+            Vector2::new(10.0, -10.0),
+            Vector2::new(10.0, 10.0),
+            Vector2::new(-10.0, 10.0),
+            Vector2::new(0.0, 0.0),
+        ]);
+
+        // The center touches every corner, corners only touch their sides
+        assert_eq!(neighbours[4], vec![0, 1, 2, 3]);
+        assert_eq!(neighbours[0], vec![1, 3, 4]);
+        assert_eq!(neighbours[2], vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn collinear_points_link_along_the_line() {
+        let neighbours = delaunay_neighbours(&[
+            Vector2::new(20.0, 0.0),
+            Vector2::new(0.0, 0.0),
+            Vector2::new(10.0, 0.0),
+        ]);
+
+        assert_eq!(neighbours, vec![vec![2], vec![2], vec![0, 1]]);
+    }
+}
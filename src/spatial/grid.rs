@@ -0,0 +1,239 @@
+use nalgebra::Vector2;
+use std::collections::HashMap;
+
+/// Uniform grid bucketing a fixed set of points by cell, used to answer radius
+/// and k-nearest queries without comparing against every point.
+pub struct SpatialGrid {
+    cell_size: f32,
+    positions: Vec<Vector2<f32>>,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    min_cell: (i32, i32),
+    max_cell: (i32, i32),
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32, positions: Vec<Vector2<f32>>) -> SpatialGrid {
+        let cell_size = cell_size.max(1.0);
+        let mut cells: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+        let mut min_cell = (i32::MAX, i32::MAX);
+        let mut max_cell = (i32::MIN, i32::MIN);
+
+        for (i, position) in positions.iter().enumerate() {
+            let cell = cell_of(cell_size, *position);
+            min_cell = (min_cell.0.min(cell.0), min_cell.1.min(cell.1));
+            max_cell = (max_cell.0.max(cell.0), max_cell.1.max(cell.1));
+            cells.entry(cell).or_default().push(i);
+        }
+
+        SpatialGrid {
+            cell_size,
+            positions,
+            cells,
+            min_cell,
+            max_cell,
+        }
+    }
+
+    /// Indices of every point strictly closer than `radius` to `position`.
+    pub fn within_radius(&self, position: Vector2<f32>, radius: f32) -> Vec<usize> {
+        let (min_x, min_y) = cell_of(self.cell_size, position - Vector2::new(radius, radius));
+        let (max_x, max_y) = cell_of(self.cell_size, position + Vector2::new(radius, radius));
+
+        let mut found = Vec::new();
+        for x in min_x.max(self.min_cell.0)..=max_x.min(self.max_cell.0) {
+            for y in min_y.max(self.min_cell.1)..=max_y.min(self.max_cell.1) {
+                if let Some(cell) = self.cells.get(&(x, y)) {
+                    found.extend(
+                        cell.iter()
+                            .filter(|&&i| (self.positions[i] - position).norm() < radius),
+                    );
+                }
+            }
+        }
+        found.sort_unstable();
+        found
+    }
+
+    /// Indices of the `k` points closest to `position`, nearest first,
+    /// leaving out `exclude` (usually the querying point itself).
+    pub fn nearest(&self, position: Vector2<f32>, k: usize, exclude: Option<usize>) -> Vec<usize> {
+        if k == 0 || self.positions.is_empty() {
+            return Vec::new();
+        }
+
+        let mut best: Vec<(f32, usize)> = Vec::with_capacity(k + 1);
+        let center = cell_of(self.cell_size, position);
+        let mut ring = 0;
+        loop {
+            for cell in ring_cells(center, ring) {
+                let indices = match self.cells.get(&cell) {
+                    Some(indices) => indices,
+                    None => continue,
+                };
+                for &i in indices {
+                    if Some(i) == exclude {
+                        continue;
+                    }
+                    let distance = (self.positions[i] - position).norm();
+                    if best.len() < k || distance < best[best.len() - 1].0 {
+                        let at = best
+                            .iter()
+                            .position(|(d, j)| (distance, i) < (*d, *j))
+                            .unwrap_or(best.len());
+                        best.insert(at, (distance, i));
+                        best.truncate(k);
+                    }
+                }
+            }
+
+            // Anything in the next ring is at least `ring` cells away, so stop
+            // once the k-th best is closer than that or the grid is exhausted
+            let full = best.len() == k && best[k - 1].0 <= ring as f32 * self.cell_size;
+            let exhausted = center.0 - ring <= self.min_cell.0
+                && center.1 - ring <= self.min_cell.1
+                && center.0 + ring >= self.max_cell.0
+                && center.1 + ring >= self.max_cell.1;
+            if full || exhausted {
+                break;
+            }
+            ring += 1;
+        }
+
+        best.into_iter().map(|(_, i)| i).collect()
+    }
+
+    /// Number of groups the points fall into, where points closer than
+    /// `distance` to any point of a group belong to it.
+    pub fn clusters(&self, distance: f32) -> usize {
+        self.cluster_labels(distance)
+            .into_iter()
+            .max()
+            .map_or(0, |label| label + 1)
+    }
+
+    /// Group each point falls into, as in `clusters`, numbered from 0.
+    pub fn cluster_labels(&self, distance: f32) -> Vec<usize> {
+        let mut labels = vec![None; self.positions.len()];
+        let mut clusters = 0;
+        for start in 0..self.positions.len() {
+            if labels[start].is_some() {
+                continue;
+            }
+            labels[start] = Some(clusters);
+            let mut frontier = vec![start];
+            while let Some(i) = frontier.pop() {
+                for j in self.within_radius(self.positions[i], distance) {
+                    if labels[j].is_none() {
+                        labels[j] = Some(clusters);
+                        frontier.push(j);
+                    }
+                }
+            }
+            clusters += 1;
+        }
+        labels
+            .into_iter()
+            .map(|label| label.expect("every point is labelled"))
+            .collect()
+    }
+}
+
+fn cell_of(cell_size: f32, position: Vector2<f32>) -> (i32, i32) {
+    (
+        (position.x / cell_size).floor() as i32,
+        (position.y / cell_size).floor() as i32,
+    )
+}
+
+/// Cells at exactly `ring` steps (Chebyshev distance) from `center`.
+fn ring_cells(center: (i32, i32), ring: i32) -> Vec<(i32, i32)> {
+    if ring == 0 {
+        return vec![center];
+    }
+
+    let mut cells = Vec::with_capacity(8 * ring as usize);
+    for d in -ring..=ring {
+        cells.push((center.0 + d, center.1 - ring));
+        cells.push((center.0 + d, center.1 + ring));
+    }
+    for d in (-ring + 1)..ring {
+        cells.push((center.0 - ring, center.1 + d));
+        cells.push((center.0 + ring, center.1 + d));
+    }
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn random_points(count: usize) -> Vec<Vector2<f32>> {
+        let mut rng = StdRng::seed_from_u64(7);
+        (0..count)
+            .map(|_| Vector2::new(rng.gen_range(-500.0..500.0), rng.gen_range(-500.0..500.0)))
+            .collect()
+    }
+
+    #[test]
+    fn within_radius_matches_brute_force() {
+        let points = random_points(300);
+        let grid = SpatialGrid::new(40.0, points.clone());
+
+        for query in random_points(20) {
+            let expected = (0..points.len())
+                .filter(|&i| (points[i] - query).norm() < 75.0)
+                .collect::<Vec<_>>();
+            assert_eq!(grid.within_radius(query, 75.0), expected);
+        }
+    }
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let points = random_points(300);
+        let grid = SpatialGrid::new(40.0, points.clone());
+
+        for (q, query) in points.iter().enumerate().take(30) {
+            let mut expected = (0..points.len())
+                .filter(|&i| i != q)
+                .map(|i| ((points[i] - query).norm(), i))
+                .collect::<Vec<_>>();
+            expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let expected = expected
+                .into_iter()
+                .take(7)
+                .map(|(_, i)| i)
+                .collect::<Vec<_>>();
+
+            assert_eq!(grid.nearest(*query, 7, Some(q)), expected);
+        }
+    }
+
+    #[test]
+    fn nearest_returns_everything_when_k_exceeds_points() {
+        let points = vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(1000.0, 0.0),
+            Vector2::new(0.0, -3000.0),
+        ];
+        let grid = SpatialGrid::new(10.0, points);
+
+        assert_eq!(grid.nearest(Vector2::new(0.0, 0.0), 7, Some(0)), vec![1, 2]);
+    }
+
+    #[test]
+    fn clusters_chain_through_close_points() {
+        let points = vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(8.0, 0.0),
+            Vector2::new(16.0, 0.0),
+            Vector2::new(100.0, 0.0),
+            Vector2::new(-100.0, 50.0),
+        ];
+        let grid = SpatialGrid::new(10.0, points);
+        assert_eq!(grid.clusters(10.0), 3);
+        assert_eq!(grid.cluster_labels(10.0), vec![0, 0, 0, 1, 2]);
+        assert_eq!(grid.clusters(5.0), 5);
+        assert_eq!(SpatialGrid::new(10.0, vec![]).clusters(10.0), 0);
+    }
+}
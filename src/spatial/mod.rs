@@ -0,0 +1,9 @@
+mod delaunay;
+mod grid;
+mod noise;
+mod shapes;
+
+pub use self::delaunay::delaunay_neighbours;
+pub use self::grid::SpatialGrid;
+pub use self::noise::perlin;
+pub use self::shapes::{RayHit, Shape};
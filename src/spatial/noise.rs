@@ -0,0 +1,102 @@
+/// Ken Perlin's improved gradient noise in three dimensions, roughly in
+/// `[-1, 1]` and zero on every integer lattice point. Lattice gradients come
+/// from hashing the cell coordinates with `seed`, so no permutation table has
+/// to be kept around.
+pub fn perlin(seed: u32, x: f32, y: f32, z: f32) -> f32 {
+    let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+    let (fx, fy, fz) = (x - x0, y - y0, z - z0);
+    let (ix, iy, iz) = (x0 as i32, y0 as i32, z0 as i32);
+    let (u, v, w) = (fade(fx), fade(fy), fade(fz));
+
+    let corner = |dx: i32, dy: i32, dz: i32| {
+        gradient(
+            hash(seed, ix + dx, iy + dy, iz + dz),
+            fx - dx as f32,
+            fy - dy as f32,
+            fz - dz as f32,
+        )
+    };
+
+    lerp(
+        w,
+        lerp(
+            v,
+            lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+            lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+        ),
+        lerp(
+            v,
+            lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+            lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+        ),
+    )
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+fn hash(seed: u32, x: i32, y: i32, z: i32) -> u32 {
+    let mut h = seed
+        ^ (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^ (h >> 16)
+}
+
+/// Dot product of the offset with one of the twelve cube edge directions.
+fn gradient(hash: u32, x: f32, y: f32, z: f32) -> f32 {
+    match hash % 12 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x + z,
+        5 => -x + z,
+        6 => x - z,
+        7 => -x - z,
+        8 => y + z,
+        9 => -y + z,
+        10 => y - z,
+        _ => -y - z,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_on_lattice_points() {
+        for &(x, y, z) in [(0.0, 0.0, 0.0), (3.0, -2.0, 1.0), (-7.0, 5.0, -4.0)].iter() {
+            assert_eq!(perlin(1, x, y, z), 0.0);
+        }
+    }
+
+    #[test]
+    fn bounded_continuous_and_seeded() {
+        let samples = (0..1000)
+            .map(|i| {
+                let t = i as f32 * 0.037;
+                (t, t * 0.7 + 0.3, t * 0.13)
+            })
+            .collect::<Vec<_>>();
+
+        let mut differs = false;
+        for &(x, y, z) in samples.iter() {
+            let value = perlin(7, x, y, z);
+            assert!(value.abs() <= 1.5);
+            assert!((value - perlin(7, x + 1e-3, y, z)).abs() < 1e-2);
+            differs |= (value - perlin(8, x, y, z)).abs() > 1e-3;
+        }
+        assert!(differs);
+    }
+}
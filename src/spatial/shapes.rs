@@ -0,0 +1,339 @@
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+
+/// Solid geometry, relative to whatever position it is placed at.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Shape {
+    Circle {
+        radius: f32,
+    },
+    /// A wall that can't be crossed from either side.
+    Segment {
+        start: Vector2<f32>,
+        end: Vector2<f32>,
+    },
+    /// A closed polygon, with its points given in order.
+    Polygon {
+        points: Vec<Vector2<f32>>,
+    },
+    /// An axis-aligned box centered on the shape's position.
+    Box {
+        half_extents: Vector2<f32>,
+    },
+}
+
+/// Where a ray first touched a shape.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    pub distance: f32,
+    /// Unit surface normal facing back towards the ray's origin.
+    pub normal: Vector2<f32>,
+}
+
+impl Shape {
+    /// Edges of the shape placed at `center`, or `None` for circles.
+    pub fn edges(&self, center: Vector2<f32>) -> Option<Vec<(Vector2<f32>, Vector2<f32>)>> {
+        let corners = match self {
+            Shape::Circle { .. } => return None,
+            Shape::Segment { start, end } => return Some(vec![(center + start, center + end)]),
+            Shape::Polygon { points } => points.iter().map(|p| center + p).collect::<Vec<_>>(),
+            Shape::Box { half_extents } => vec![
+                center + Vector2::new(-half_extents.x, -half_extents.y),
+                center + Vector2::new(half_extents.x, -half_extents.y),
+                center + Vector2::new(half_extents.x, half_extents.y),
+                center + Vector2::new(-half_extents.x, half_extents.y),
+            ],
+        };
+
+        Some(
+            corners
+                .iter()
+                .zip(corners.iter().cycle().skip(1))
+                .map(|(a, b)| (*a, *b))
+                .collect(),
+        )
+    }
+
+    /// First point along the ray from `origin` in the unit `direction` where
+    /// it touches the shape placed at `center`, if within `max_distance`.
+    pub fn ray_cast(
+        &self,
+        center: Vector2<f32>,
+        origin: Vector2<f32>,
+        direction: Vector2<f32>,
+        max_distance: f32,
+    ) -> Option<RayHit> {
+        let hit = match (self, self.edges(center)) {
+            (Shape::Circle { radius }, _) => ray_circle(center, *radius, origin, direction),
+            (_, Some(edges)) => edges
+                .iter()
+                .filter_map(|(a, b)| ray_segment(*a, *b, origin, direction))
+                .min_by(|a, b| a.distance.total_cmp(&b.distance)),
+            _ => None,
+        };
+
+        hit.filter(|hit| hit.distance <= max_distance)
+    }
+
+    /// If `point` is inside the shape placed at `center`, the closest point on
+    /// its boundary and the outward normal there. Segments have no inside.
+    pub fn push_out(
+        &self,
+        center: Vector2<f32>,
+        point: Vector2<f32>,
+    ) -> Option<(Vector2<f32>, Vector2<f32>)> {
+        match self {
+            Shape::Circle { radius } => {
+                let offset = point - center;
+                let distance = offset.norm();
+                if distance >= *radius {
+                    return None;
+                }
+                let normal = if distance > 0.0 {
+                    offset / distance
+                } else {
+                    Vector2::new(1.0, 0.0)
+                };
+                Some((center + normal * *radius, normal))
+            }
+            Shape::Segment { .. } => None,
+            Shape::Polygon { .. } | Shape::Box { .. } => {
+                let edges = self.edges(center)?;
+                if !contains(&edges, point) {
+                    return None;
+                }
+
+                let (closest, _) = edges
+                    .iter()
+                    .map(|(a, b)| {
+                        let closest = closest_on_segment(*a, *b, point);
+                        (closest, (closest - point).norm())
+                    })
+                    .filter(|(_, distance)| distance.is_finite())
+                    .min_by(|a, b| a.1.total_cmp(&b.1))?;
+                let offset = closest - point;
+                let normal = if offset.norm() > 0.0 {
+                    offset.normalize()
+                } else {
+                    Vector2::new(1.0, 0.0)
+                };
+                Some((closest, normal))
+            }
+        }
+    }
+}
+
+fn ray_circle(
+    center: Vector2<f32>,
+    radius: f32,
+    origin: Vector2<f32>,
+    direction: Vector2<f32>,
+) -> Option<RayHit> {
+    let offset = origin - center;
+    let b = offset.dot(&direction);
+    let c = offset.norm_squared() - radius * radius;
+    let discriminant = b * b - c;
+    // Starting inside the circle is handled by `push_out`, not ray casts
+    if c < 0.0 || discriminant < 0.0 {
+        return None;
+    }
+
+    let distance = -b - discriminant.sqrt();
+    if !distance.is_finite() || distance < 0.0 {
+        return None;
+    }
+    let normal = (origin + direction * distance - center) / radius;
+    Some(RayHit { distance, normal })
+}
+
+fn ray_segment(
+    a: Vector2<f32>,
+    b: Vector2<f32>,
+    origin: Vector2<f32>,
+    direction: Vector2<f32>,
+) -> Option<RayHit> {
+    let edge = b - a;
+    let denominator = cross(direction, edge);
+    if denominator == 0.0 {
+        return None;
+    }
+
+    let to_start = a - origin;
+    let distance = cross(to_start, edge) / denominator;
+    let along = cross(to_start, direction) / denominator;
+    if !distance.is_finite() || distance < 0.0 || !(0.0..=1.0).contains(&along) {
+        return None;
+    }
+
+    let mut normal = Vector2::new(-edge.y, edge.x).normalize();
+    if normal.dot(&direction) > 0.0 {
+        normal = -normal;
+    }
+    Some(RayHit { distance, normal })
+}
+
+fn closest_on_segment(a: Vector2<f32>, b: Vector2<f32>, point: Vector2<f32>) -> Vector2<f32> {
+    let edge = b - a;
+    let length_squared = edge.norm_squared();
+    if length_squared == 0.0 {
+        return a;
+    }
+    let along = ((point - a).dot(&edge) / length_squared).max(0.0).min(1.0);
+    a + edge * along
+}
+
+/// Even-odd test for whether `point` lies inside the closed loop of `edges`.
+fn contains(edges: &[(Vector2<f32>, Vector2<f32>)], point: Vector2<f32>) -> bool {
+    edges
+        .iter()
+        .filter(|(a, b)| {
+            (a.y > point.y) != (b.y > point.y)
+                && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        })
+        .count()
+        % 2
+        == 1
+}
+
+fn cross(a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vector2<f32>, b: Vector2<f32>) -> bool {
+        (a - b).norm() < 1e-4
+    }
+
+    #[test]
+    fn ray_hits_circle_front() {
+        let circle = Shape::Circle { radius: 10.0 };
+        let hit = circle
+            .ray_cast(
+                Vector2::new(50.0, 0.0),
+                Vector2::new(0.0, 0.0),
+                Vector2::new(1.0, 0.0),
+                100.0,
+            )
+            .unwrap();
+
+        assert!((hit.distance - 40.0).abs() < 1e-4);
+        assert!(close(hit.normal, Vector2::new(-1.0, 0.0)));
+        assert!(circle
+            .ray_cast(
+                Vector2::new(50.0, 0.0),
+                Vector2::new(0.0, 0.0),
+                Vector2::new(1.0, 0.0),
+                30.0,
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn segment_normal_faces_the_ray_from_either_side() {
+        let wall = Shape::Segment {
+            start: Vector2::new(0.0, -10.0),
+            end: Vector2::new(0.0, 10.0),
+        };
+
+        let from_left = wall
+            .ray_cast(
+                Vector2::new(5.0, 0.0),
+                Vector2::new(0.0, 0.0),
+                Vector2::new(1.0, 0.0),
+                10.0,
+            )
+            .unwrap();
+        assert!((from_left.distance - 5.0).abs() < 1e-4);
+        assert!(close(from_left.normal, Vector2::new(-1.0, 0.0)));
+
+        let from_right = wall
+            .ray_cast(
+                Vector2::new(5.0, 0.0),
+                Vector2::new(10.0, 0.0),
+                Vector2::new(-1.0, 0.0),
+                10.0,
+            )
+            .unwrap();
+        assert!(close(from_right.normal, Vector2::new(1.0, 0.0)));
+
+        // Passes beside the end of the wall
+        assert!(wall
+            .ray_cast(
+                Vector2::new(5.0, 20.0),
+                Vector2::new(0.0, 0.0),
+                Vector2::new(1.0, 0.0),
+                10.0,
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn ray_hits_nearest_box_edge() {
+        let square = Shape::Box {
+            half_extents: Vector2::new(10.0, 10.0),
+        };
+        let hit = square
+            .ray_cast(
+                Vector2::new(0.0, 0.0),
+                Vector2::new(0.0, -50.0),
+                Vector2::new(0.0, 1.0),
+                100.0,
+            )
+            .unwrap();
+
+        assert!((hit.distance - 40.0).abs() < 1e-4);
+        assert!(close(hit.normal, Vector2::new(0.0, -1.0)));
+    }
+
+    #[test]
+    fn push_out_of_polygon_and_circle() {
+        let triangle = Shape::Polygon {
+            points: vec![
+                Vector2::new(0.0, 0.0),
+                Vector2::new(20.0, 0.0),
+                Vector2::new(0.0, 20.0),
+            ],
+        };
+        let (point, normal) = triangle
+            .push_out(Vector2::new(100.0, 100.0), Vector2::new(105.0, 102.0))
+            .unwrap();
+        assert!(close(point, Vector2::new(105.0, 100.0)));
+        assert!(close(normal, Vector2::new(0.0, -1.0)));
+        assert!(triangle
+            .push_out(Vector2::new(100.0, 100.0), Vector2::new(115.0, 115.0))
+            .is_none());
+
+        let circle = Shape::Circle { radius: 10.0 };
+        let (point, normal) = circle
+            .push_out(Vector2::new(0.0, 0.0), Vector2::new(0.0, 3.0))
+            .unwrap();
+        assert!(close(point, Vector2::new(0.0, 10.0)));
+        assert!(close(normal, Vector2::new(0.0, 1.0)));
+    }
+
+    #[test]
+    fn non_finite_distances_are_skipped() {
+        // The edge out to infinity has a NaN closest point
+        let spike = Shape::Polygon {
+            points: vec![
+                Vector2::new(0.0, 0.0),
+                Vector2::new(20.0, 0.0),
+                Vector2::new(0.0, 20.0),
+                Vector2::new(0.0, f32::INFINITY),
+            ],
+        };
+        let (point, _) = spike
+            .push_out(Vector2::new(0.0, 0.0), Vector2::new(1.0, 1.0))
+            .unwrap();
+        assert!(point.x.is_finite() && point.y.is_finite(), "{:?}", point);
+        let _ = spike.ray_cast(
+            Vector2::new(0.0, 0.0),
+            Vector2::new(-50.0, 10.0),
+            Vector2::new(1.0, 0.0),
+            100.0,
+        );
+    }
+}
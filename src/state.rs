@@ -1,17 +1,36 @@
-use crate::{entities, resources};
+use crate::{components, entities, export, resources};
 use amethyst::{
-    core::transform::Transform,
+    config::Config,
+    core::{transform::Transform, Time},
     input::{get_key, is_close_requested, is_key_down, VirtualKeyCode},
     prelude::*,
     renderer::Camera,
     window::ScreenDimensions,
 };
-use anyhow::Result;
-use nalgebra::{Vector2, Vector3};
-
-use log::info;
-
-pub struct MyState;
+use nalgebra::Vector3;
+
+use log::{error, info};
+
+/// File the F5 key saves a snapshot of every boid to, in the working
+/// directory.
+const SNAPSHOT_FILE: &str = "snapshot.ron";
+/// File the F6 key exports the world to as vector graphics, in the working
+/// directory.
+const EXPORT_FILE: &str = "flock.svg";
+
+/// The interactive viewer, set up from the command line.
+pub struct MyState {
+    /// Boids of the scenario's first species scattered over the window on
+    /// start.
+    pub initial_boids: usize,
+    /// Simulated seconds that pass per second of real time.
+    pub time_scale: f32,
+    pub paused: bool,
+    /// Boids restored on start, on top of the scenario's.
+    pub snapshot: Option<resources::Snapshot>,
+    /// Sprites that species and obstacles are drawn with.
+    pub sprites: resources::SpriteManifest,
+}
 
 impl SimpleState for MyState {
     // On start will run when this state is initialized. For more
@@ -28,26 +47,81 @@ impl SimpleState for MyState {
         // Place the camera
         init_camera(world, &dimensions);
 
+        // Components only ever written through `LazyUpdate` aren't registered
+        // by any system, so do it here
+        world.register::<components::Species>();
+
         // Load our sprites and display them
-        load_sprites(world);
+        load_sprites(world, &self.sprites);
 
         // Load in boundaries and other world elements
-        load_world(world).unwrap();
+        if let Err(e) = entities::scenario::load_scenario(world) {
+            error!("Failed to load the scenario: {:#}", e);
+        }
+
+        // Every simulation system steps by the fixed time each frame, so
+        // scaling it scales how fast the simulation runs
+        {
+            let mut time = world.write_resource::<Time>();
+            let step = time.fixed_seconds() * self.time_scale;
+            time.set_fixed_seconds(step);
+        }
+        if self.paused {
+            world.insert(resources::SimulationState::Paused);
+        }
+
+        if let Err(e) = self.spawn_boids(world) {
+            error!("Failed to place the starting boids: {:#}", e);
+        }
     }
 
     fn handle_event(
         &mut self,
-        mut _data: StateData<'_, GameData<'_, '_>>,
+        data: StateData<'_, GameData<'_, '_>>,
         event: StateEvent,
     ) -> SimpleTrans {
         if let StateEvent::Window(event) = &event {
             // Check if the window should be closed
-            if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+            if is_close_requested(event) || is_key_down(event, VirtualKeyCode::Escape) {
                 return Trans::Quit;
             }
 
+            if is_key_down(event, VirtualKeyCode::Space) {
+                let mut state = data.world.write_resource::<resources::SimulationState>();
+                *state = state.toggled();
+                info!("Simulation {:?}", *state);
+            }
+
+            if is_key_down(event, VirtualKeyCode::T) {
+                let mut trails = data.world.write_resource::<resources::TrailSettings>();
+                trails.enabled = !trails.enabled;
+                info!("Trails {}", if trails.enabled { "on" } else { "off" });
+            }
+
+            if is_key_down(event, VirtualKeyCode::V) {
+                let mut mode = data.world.write_resource::<resources::ColourMode>();
+                *mode = mode.next();
+                info!("Colouring boids by {:?}", *mode);
+            }
+
+            if is_key_down(event, VirtualKeyCode::F5) {
+                match resources::Snapshot::capture(data.world).write(SNAPSHOT_FILE) {
+                    Ok(()) => info!("Saved snapshot to {}", SNAPSHOT_FILE),
+                    Err(e) => error!("Failed to save snapshot: {}", e),
+                }
+            }
+
+            if is_key_down(event, VirtualKeyCode::F6) {
+                let scene = export::Scene::capture(data.world);
+                let options = export::SvgOptions::default();
+                match export::write_svg(&scene, &options, EXPORT_FILE.as_ref()) {
+                    Ok(()) => info!("Exported the world to {}", EXPORT_FILE),
+                    Err(e) => error!("Failed to export the world: {:#}", e),
+                }
+            }
+
             // Listen to any key events
-            if let Some(event) = get_key(&event) {
+            if let Some(event) = get_key(event) {
                 info!("handling key event: {:?}", event);
             }
 
@@ -61,6 +135,24 @@ impl SimpleState for MyState {
     }
 }
 
+impl MyState {
+    fn spawn_boids(&self, world: &World) -> anyhow::Result<()> {
+        if let Some(snapshot) = &self.snapshot {
+            snapshot.restore(world)?;
+        }
+        if self.initial_boids > 0 {
+            let scenario = world.read_resource::<resources::Scenario>();
+            entities::boids::spawn_boids(
+                world,
+                scenario.default_species()?,
+                self.initial_boids,
+                entities::scenario::world_extents(world),
+            )?;
+        }
+        Ok(())
+    }
+}
+
 fn init_camera(world: &mut World, dimensions: &ScreenDimensions) {
     // Center the camera in the middle of the screen, and let it cover
     // the entire screen
@@ -75,26 +167,7 @@ fn init_camera(world: &mut World, dimensions: &ScreenDimensions) {
         .build();
 }
 
-fn load_sprites(world: &mut World) {
-    let mut sprite_cache = resources::SpriteCache::new();
-    sprite_cache.load(resources::SpriteKey::Boid, world);
-    sprite_cache.load(resources::SpriteKey::Obstacle, world);
-    world.insert(sprite_cache);
-}
-
-fn load_world(world: &mut World) -> Result<()> {
-    let num_obstacles_x = 1770 / 120;
-    let num_obstacles_y = 1000 / 120;
-
-    for i in 0..num_obstacles_x {
-        entities::boids::new_obstacle(world, Vector2::new((i * 120 - 835) as f32, -430.))?;
-        entities::boids::new_obstacle(world, Vector2::new((i * 120 - 835) as f32, 430.))?;
-    }
-
-    for i in 0..num_obstacles_y {
-        entities::boids::new_obstacle(world, Vector2::new(-815., (i * 120 - 450) as f32))?;
-        entities::boids::new_obstacle(world, Vector2::new(815., (i * 120 - 450) as f32))?;
-    }
-
-    Ok(())
+fn load_sprites(world: &mut World, manifest: &resources::SpriteManifest) {
+    let sprite_registry = resources::SpriteRegistry::load(manifest, world);
+    world.insert(sprite_registry);
 }
@@ -0,0 +1,36 @@
+use crate::components::{BoidData, SpriteAnimation, Velocity};
+use amethyst::{
+    core::Time,
+    derive::SystemDesc,
+    ecs::prelude::*,
+    ecs::{Read, ReadStorage, System, WriteStorage},
+    renderer::SpriteRender,
+};
+
+/// Moves every animated boid's sprite on to the frame its `SpriteAnimation`
+/// is at, by how fast the boid flies and turns.
+#[derive(SystemDesc)]
+pub struct SpriteAnimationSystem;
+
+impl<'s> System<'s> for SpriteAnimationSystem {
+    type SystemData = (
+        ReadStorage<'s, BoidData>,
+        ReadStorage<'s, Velocity>,
+        WriteStorage<'s, SpriteAnimation>,
+        WriteStorage<'s, SpriteRender>,
+        Read<'s, Time>,
+    );
+
+    fn run(
+        &mut self,
+        (boid_datas, velocities, mut animations, mut renders, time): Self::SystemData,
+    ) {
+        let delta_seconds = time.fixed_time().as_secs_f32();
+        for (boid_data, velocity, animation, render) in
+            (&boid_datas, &velocities, &mut animations, &mut renders).join()
+        {
+            render.sprite_number =
+                animation.advance(velocity.0, boid_data.max_speed, delta_seconds);
+        }
+    }
+}
@@ -1,251 +1,1039 @@
-use crate::components::{BoidData, ObstacleData, Position, Velocity};
+use crate::{
+    components::{
+        Attractor, BoidData, Falloff, Flocking, FlowResponse, FollowerData, GoalData, Leader,
+        Neighbourhood, ObstacleData, ObstacleShape, PathFollower, Position, ScriptedSteering,
+        Velocity,
+    },
+    flocking::{
+        arrive, evade, follow, repulsion, seek, stacked_direction, Neighbour, Neighbourhoods,
+    },
+    resources::{FlowField, ScriptBoid, ScriptLibrary},
+};
 use amethyst::{
+    core::Time,
     derive::SystemDesc,
     ecs::prelude::*,
-    ecs::{Entities, Entity, ReadStorage, System, WriteStorage},
+    ecs::{Entities, Entity, Read, ReadStorage, System, Write, WriteStorage},
 };
-use itertools::izip;
 use nalgebra::Vector2;
-use rand::{
-    distributions::{Distribution, Uniform},
-    thread_rng,
-};
 use std::collections::HashMap;
 
 #[derive(SystemDesc)]
 pub struct BoidSystem;
 
+/// Output of every steering rule, keyed by the boid entity it applies to.
+#[derive(Debug, Default)]
+struct RuleOutputs {
+    /// Steering from each boid's flocking model.
+    model: HashMap<Entity, Vector2<f32>>,
+    obstacle: HashMap<Entity, Vector2<f32>>,
+    /// Look-ahead steering away from solid obstacles.
+    avoidance: HashMap<Entity, Vector2<f32>>,
+    /// Seeking attractors and following paths.
+    goal: HashMap<Entity, Vector2<f32>>,
+    /// Following the nearest leader.
+    follow: HashMap<Entity, Vector2<f32>>,
+    /// Turning to face into the flow.
+    upwind: HashMap<Entity, Vector2<f32>>,
+    /// Rules defined by scripts.
+    script: HashMap<Entity, Vector2<f32>>,
+}
+
 impl<'s> System<'s> for BoidSystem {
     type SystemData = (
         ReadStorage<'s, BoidData>,
+        ReadStorage<'s, Flocking>,
         ReadStorage<'s, ObstacleData>,
+        ReadStorage<'s, ObstacleShape>,
+        ReadStorage<'s, GoalData>,
+        ReadStorage<'s, Attractor>,
+        WriteStorage<'s, PathFollower>,
+        ReadStorage<'s, Leader>,
+        ReadStorage<'s, FollowerData>,
+        ReadStorage<'s, FlowResponse>,
+        ReadStorage<'s, ScriptedSteering>,
         ReadStorage<'s, Position>,
         WriteStorage<'s, Velocity>,
         Entities<'s>,
+        Read<'s, Time>,
+        Read<'s, FlowField>,
+        Write<'s, ScriptLibrary>,
     );
 
     fn run(
         &mut self,
-        (boid_datas, obstacle_datas, positions, mut velocities, entities): Self::SystemData,
+        (
+            boid_datas,
+            flockings,
+            obstacle_datas,
+            obstacle_shapes,
+            goal_datas,
+            attractors,
+            mut path_followers,
+            leaders,
+            follower_datas,
+            flow_responses,
+            scripted_steerings,
+            positions,
+            mut velocities,
+            entities,
+            time,
+            flow_field,
+            mut script_library,
+        ): Self::SystemData,
     ) {
+        for (path_follower, position) in (&mut path_followers, &positions).join() {
+            path_follower.advance(position.0);
+        }
+
         // List of boid position and velocities used for determining new velocities
         let all_boids = (&boid_datas, &positions, &velocities, &entities)
             .join()
             .map(|(_, p, v, e)| (e, p.0, v.0))
             .collect::<Vec<_>>();
-
-        // Calculate the different vectors
-        let separation_map = (&boid_datas, &positions, &velocities, &entities)
+        let point_obstacles = (&obstacle_datas, &positions)
             .join()
-            .map(|(boid_data, position, velocity, entity)| {
-                (
-                    entity,
-                    self.separation(
-                        boid_data,
-                        position.0,
-                        velocity.0,
-                        &self.neighbour_boids(
-                            Some(entity),
-                            position.0,
-                            boid_data.separation_radius,
-                            &all_boids,
-                        ),
-                    ),
-                )
-            })
+            .map(|(o, p)| (o, p.0))
             .collect::<Vec<_>>();
-        let alignment_map = (&boid_datas, &positions, &velocities, &entities)
+        let solid_obstacles = (&obstacle_shapes, &positions)
             .join()
-            .map(|(boid_data, position, velocity, entity)| {
-                (
-                    entity,
-                    self.alignment(
-                        boid_data,
-                        position.0,
-                        velocity.0,
-                        &self.neighbour_boids(
-                            Some(entity),
-                            position.0,
-                            boid_data.alignment_radius,
-                            &all_boids,
-                        ),
-                    ),
-                )
-            })
-            .collect::<HashMap<_, _>>();
-        let cohesion_map = (&boid_datas, &positions, &velocities, &entities)
+            .map(|(o, p)| (o, p.0))
+            .collect::<Vec<_>>();
+        let attractor_positions = (&attractors, &positions)
             .join()
-            .map(|(boid_data, position, velocity, entity)| {
-                (
-                    entity,
-                    self.cohesion(
-                        boid_data,
-                        position.0,
-                        velocity.0,
-                        &self.neighbour_boids(
-                            Some(entity),
-                            position.0,
-                            boid_data.cohesion_radius,
-                            &all_boids,
-                        ),
-                    ),
-                )
-            })
+            .map(|(_, p)| p.0)
             .collect::<Vec<_>>();
-        let noise_map = (&boid_datas, &positions, &velocities, &entities)
+        let leader_boids = (&leaders, &positions, &velocities, &entities)
             .join()
-            .map(|(boid_data, position, velocity, entity)| (entity, self.noise(boid_data)))
+            .map(|(_, p, v, e)| (e, p.0, v.0))
             .collect::<Vec<_>>();
 
-        let obstacle_map = self.calculate_obstacles(&all_boids, &obstacle_datas, &positions);
+        let neighbourhoods = Neighbourhoods::new(&all_boids, &boid_datas);
+        let mut rules = self.calculate_rules(
+            &boid_datas,
+            &flockings,
+            &point_obstacles,
+            &solid_obstacles,
+            &neighbourhoods,
+            time.fixed_time().as_secs_f32(),
+        );
+        rules.goal = self.calculate_goals(
+            &boid_datas,
+            &goal_datas,
+            &path_followers,
+            &attractor_positions,
+            &all_boids,
+        );
+        rules.follow =
+            self.calculate_following(&boid_datas, &follower_datas, &leader_boids, &neighbourhoods);
+        rules.upwind = self.calculate_upwind(
+            &boid_datas,
+            &flow_responses,
+            &flow_field,
+            &all_boids,
+            time.absolute_time_seconds() as f32,
+        );
+        script_library.reload_changed();
+        rules.script = self.calculate_scripts(
+            &boid_datas,
+            &scripted_steerings,
+            &mut script_library,
+            &neighbourhoods,
+            time.fixed_time().as_secs_f32(),
+        );
 
-        izip!(
-            separation_map,
-            alignment_map,
-            cohesion_map,
-            noise_map,
-            obstacle_map
-        )
-        .for_each(
-            |((entity, v_sep), (_, v_align), (_, v_coh), (_, v_noise), (_, v_obstacle))| {
-                let mut write_vel = velocities.get_mut(entity).unwrap();
-                let boid_data = boid_datas.get(entity).unwrap();
-
-                let weighted_vec = boid_data.separation_weight * v_sep
-                    + boid_data.alignment_weight * v_align
-                    + boid_data.cohesion_weight * v_coh
-                    + boid_data.noise_weight * v_noise
-                    // Weight is already incorporated in the obstacle
-                    + v_obstacle;
-                if !weighted_vec.x.is_nan()
-                    && !weighted_vec.y.is_nan()
-                    && weighted_vec.norm() != 0.0
-                {
-                    write_vel.0 += weighted_vec;
+        // Combine the rules in join order, looking each entity up by key so every
+        // rule's output is applied to the boid it was calculated for
+        for (boid_data, velocity, entity) in (&boid_datas, &mut velocities, &entities).join() {
+            let (v_model, v_obstacle, v_avoidance, v_goal, v_follow, v_upwind, v_script) = match (
+                rules.model.get(&entity),
+                rules.obstacle.get(&entity),
+                rules.avoidance.get(&entity),
+                rules.goal.get(&entity),
+                rules.follow.get(&entity),
+                rules.upwind.get(&entity),
+                rules.script.get(&entity),
+            ) {
+                (Some(m), Some(o), Some(a), Some(g), Some(f), Some(u), Some(s)) => {
+                    (m, o, a, g, f, u, s)
                 }
+                // Boids without a position were never part of `all_boids`
+                _ => continue,
+            };
 
-                // Cap velocity
-                if write_vel.0.norm() > boid_data.max_speed {
-                    write_vel.0 = write_vel.0.normalize() * boid_data.max_speed;
-                }
-            },
-        );
+            // Weight is already incorporated in the obstacle terms
+            let steering =
+                v_model + v_obstacle + v_avoidance + v_goal + v_follow + v_upwind + v_script;
+            // A NaN or infinite rule would stay in the boid's velocity and
+            // position for good, so steering like that is dropped
+            if steering.x.is_finite() && steering.y.is_finite() && steering.norm() != 0.0 {
+                velocity.0 += steering;
+            }
+
+            // Cap velocity
+            if velocity.0.norm() > boid_data.max_speed {
+                velocity.0 = velocity.0.normalize() * boid_data.max_speed;
+            }
+        }
     }
 }
 
 impl BoidSystem {
-    fn noise(&self, boid_data: &BoidData) -> Vector2<f32> {
-        let mut rng = thread_rng();
-        let angle_dist = Uniform::new(0., 2. * std::f32::consts::PI);
-        let speed_dist = Uniform::new(0., 1.);
-        let angle = angle_dist.sample(&mut rng);
-        let speed = speed_dist.sample(&mut rng);
-        boid_data.max_speed * speed * Vector2::new(angle.cos(), angle.sin())
-    }
-
-    fn neighbour_boids(
+    fn calculate_rules(
         &self,
-        entity: Option<Entity>,
-        position: Vector2<f32>,
-        radius: f32,
-        all_boids: &Vec<(Entity, Vector2<f32>, Vector2<f32>)>,
-    ) -> Vec<(Entity, Vector2<f32>, Vector2<f32>)> {
-        match entity {
-            Some(entity) => all_boids
-                .iter()
-                .filter(|(e, _, _)| entity != *e)
-                .filter(|(_, p, _)| (position - p).norm() < radius)
-                .cloned()
-                .collect(),
-            None => all_boids
-                .iter()
-                .filter(|(_, p, _)| (position - p).norm() < radius)
-                .cloned()
-                .collect(),
+        boid_datas: &ReadStorage<BoidData>,
+        flockings: &ReadStorage<Flocking>,
+        point_obstacles: &[(&ObstacleData, Vector2<f32>)],
+        solid_obstacles: &[(&ObstacleShape, Vector2<f32>)],
+        neighbourhoods: &Neighbourhoods,
+        delta_seconds: f32,
+    ) -> RuleOutputs {
+        let mut rules = RuleOutputs {
+            obstacle: self.calculate_obstacles(neighbourhoods, point_obstacles),
+            ..Default::default()
+        };
+
+        for (index, &(entity, position, velocity)) in neighbourhoods.all_boids.iter().enumerate() {
+            let boid_data = match boid_datas.get(entity) {
+                Some(boid_data) => boid_data,
+                None => continue,
+            };
+            let model = flockings.get(entity).unwrap_or(&Flocking::Reynolds).model();
+
+            rules.model.insert(
+                entity,
+                model.steering(
+                    boid_data,
+                    position,
+                    velocity,
+                    &neighbourhoods.query(index, boid_data.neighbourhood),
+                    delta_seconds,
+                ),
+            );
+            rules.avoidance.insert(
+                entity,
+                self.avoidance(boid_data, position, velocity, solid_obstacles),
+            );
         }
+
+        rules
     }
 
-    fn separation(
+    /// Casts a feeler along the boid's velocity and pushes it along the normal
+    /// of the first solid obstacle the feeler touches, harder the closer it is.
+    fn avoidance(
         &self,
-        _boid_data: &BoidData,
+        boid_data: &BoidData,
         position: Vector2<f32>,
-        _velocity: Vector2<f32>,
-        neighbours: &Vec<(Entity, Vector2<f32>, Vector2<f32>)>,
+        velocity: Vector2<f32>,
+        solid_obstacles: &[(&ObstacleShape, Vector2<f32>)],
     ) -> Vector2<f32> {
-        if neighbours.len() == 0 {
+        let speed = velocity.norm();
+        let feeler = speed * boid_data.look_ahead;
+        if feeler <= 0.0 {
             return Vector2::new(0.0, 0.0);
         }
 
-        let avg_position = neighbours
+        let direction = velocity / speed;
+        solid_obstacles
             .iter()
-            .map(|(_, _, p)| position - p)
-            .fold(Vector2::new(0., 0.), |prev, pos| prev + pos)
-            / (neighbours.len() as f32);
-        position - avg_position
+            .filter_map(|(obstacle, center)| {
+                obstacle
+                    .shape
+                    .ray_cast(*center, position, direction, feeler)
+                    .map(|hit| (hit, obstacle.avoidance_weight))
+            })
+            .min_by(|(a, _), (b, _)| a.distance.total_cmp(&b.distance))
+            .map(|(hit, weight)| {
+                hit.normal * boid_data.max_speed * weight * (1.0 - hit.distance / feeler)
+            })
+            .unwrap_or_else(|| Vector2::new(0.0, 0.0))
     }
 
-    fn alignment(
+    /// Steering towards the nearest attractor and along each boid's path, for
+    /// the boids that have `GoalData`.
+    fn calculate_goals(
         &self,
-        _boid_data: &BoidData,
-        _position: Vector2<f32>,
-        velocity: Vector2<f32>,
-        neighbours: &Vec<(Entity, Vector2<f32>, Vector2<f32>)>,
-    ) -> Vector2<f32> {
-        if neighbours.len() == 0 {
-            return Vector2::new(0.0, 0.0);
-        }
+        boid_datas: &ReadStorage<BoidData>,
+        goal_datas: &ReadStorage<GoalData>,
+        path_followers: &WriteStorage<PathFollower>,
+        attractors: &[Vector2<f32>],
+        all_boids: &[Neighbour],
+    ) -> HashMap<Entity, Vector2<f32>> {
+        all_boids
+            .iter()
+            .map(|&(entity, position, velocity)| {
+                let mut steering = Vector2::new(0.0, 0.0);
+                let (boid_data, goal_data) = match (boid_datas.get(entity), goal_datas.get(entity))
+                {
+                    (Some(boid_data), Some(goal_data)) => (boid_data, goal_data),
+                    _ => return (entity, steering),
+                };
+                let max_speed = boid_data.max_speed;
 
-        let avg_direction = neighbours
+                let nearest_attractor = attractors
+                    .iter()
+                    .map(|&attractor| (attractor, (attractor - position).norm()))
+                    .filter(|(_, distance)| distance.is_finite())
+                    .min_by(|(_, a), (_, b)| a.total_cmp(b));
+                if let Some((target, _)) = nearest_attractor {
+                    steering += goal_data.seek_weight * seek(position, velocity, target, max_speed)
+                        + goal_data.arrive_weight
+                            * arrive(
+                                position,
+                                velocity,
+                                target,
+                                max_speed,
+                                goal_data.slowing_radius,
+                            );
+                }
+
+                if let Some((target, is_last)) = path_followers.get(entity).and_then(|f| f.target())
+                {
+                    let slowing_radius = if is_last {
+                        goal_data.slowing_radius
+                    } else {
+                        0.0
+                    };
+                    steering += goal_data.path_weight
+                        * arrive(position, velocity, target, max_speed, slowing_radius);
+                }
+
+                (entity, steering)
+            })
+            .collect()
+    }
+
+    /// Steering of each boid with `FollowerData` into place behind the
+    /// nearest leader, out of that leader's way and apart from other
+    /// followers. Leaders never follow.
+    fn calculate_following(
+        &self,
+        boid_datas: &ReadStorage<BoidData>,
+        follower_datas: &ReadStorage<FollowerData>,
+        leaders: &[Neighbour],
+        neighbourhoods: &Neighbourhoods,
+    ) -> HashMap<Entity, Vector2<f32>> {
+        neighbourhoods
+            .all_boids
             .iter()
-            .fold(Vector2::new(0.0, 0.0), |prev, (_, _, curr)| prev + curr)
-            / neighbours.len() as f32;
-        avg_direction - velocity
+            .enumerate()
+            .map(|(index, &(entity, position, velocity))| {
+                let zero = Vector2::new(0.0, 0.0);
+                let (boid_data, follower_data) =
+                    match (boid_datas.get(entity), follower_datas.get(entity)) {
+                        (Some(boid_data), Some(follower_data)) => (boid_data, follower_data),
+                        _ => return (entity, zero),
+                    };
+                if leaders.iter().any(|(leader, _, _)| *leader == entity) {
+                    return (entity, zero);
+                }
+                let nearest_leader = leaders
+                    .iter()
+                    .map(|&(_, leader_position, leader_velocity)| {
+                        let distance = (leader_position - position).norm();
+                        (leader_position, leader_velocity, distance)
+                    })
+                    .filter(|(_, _, distance)| distance.is_finite())
+                    .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b));
+                let leader = match nearest_leader {
+                    Some((leader_position, leader_velocity, _)) => {
+                        (leader_position, leader_velocity)
+                    }
+                    None => return (entity, zero),
+                };
+
+                let separation = neighbourhoods
+                    .neighbours(
+                        Some(index),
+                        position,
+                        follower_data.separation_radius,
+                        Neighbourhood::Metric,
+                    )
+                    .into_iter()
+                    .filter(|(other, _, _)| follower_datas.contains(*other))
+                    .fold(zero, |sum, (other, other_position, _)| {
+                        sum + repulsion(
+                            position - other_position,
+                            follower_data.separation_radius,
+                            Falloff::Linear,
+                            stacked_direction(entity, other),
+                        )
+                    });
+
+                let max_speed = boid_data.max_speed;
+                let steering = follower_data.follow_weight
+                    * follow(
+                        position,
+                        velocity,
+                        leader,
+                        follower_data.behind_distance,
+                        max_speed,
+                        follower_data.slowing_radius,
+                    )
+                    + follower_data.evade_weight
+                        * evade(position, leader, follower_data.evade_distance, max_speed)
+                    + follower_data.separation_weight * separation;
+                (entity, steering)
+            })
+            .collect()
     }
 
-    fn cohesion(
+    /// Steering of each boid with a `FlowResponse` towards heading straight
+    /// into the flow at full speed.
+    fn calculate_upwind(
         &self,
-        _boid_data: &BoidData,
-        position: Vector2<f32>,
-        _velocity: Vector2<f32>,
-        neighbours: &Vec<(Entity, Vector2<f32>, Vector2<f32>)>,
-    ) -> Vector2<f32> {
-        if neighbours.len() == 0 {
-            return Vector2::new(0.0, 0.0);
-        }
+        boid_datas: &ReadStorage<BoidData>,
+        flow_responses: &ReadStorage<FlowResponse>,
+        flow_field: &FlowField,
+        all_boids: &[Neighbour],
+        time: f32,
+    ) -> HashMap<Entity, Vector2<f32>> {
+        all_boids
+            .iter()
+            .map(|&(entity, position, velocity)| {
+                let steering = match (boid_datas.get(entity), flow_responses.get(entity)) {
+                    (Some(boid_data), Some(flow_response)) => flow_field
+                        .sample(position, time)
+                        .try_normalize(0.0)
+                        .map(|direction| {
+                            flow_response.upwind_weight
+                                * (-direction * boid_data.max_speed - velocity)
+                        })
+                        .unwrap_or_else(|| Vector2::new(0.0, 0.0)),
+                    _ => Vector2::new(0.0, 0.0),
+                };
+                (entity, steering)
+            })
+            .collect()
+    }
 
-        let avg_position = neighbours
+    /// Weighted sum of the scripted rules of each boid with
+    /// `ScriptedSteering`.
+    fn calculate_scripts(
+        &self,
+        boid_datas: &ReadStorage<BoidData>,
+        scripted_steerings: &ReadStorage<ScriptedSteering>,
+        script_library: &mut ScriptLibrary,
+        neighbourhoods: &Neighbourhoods,
+        delta_seconds: f32,
+    ) -> HashMap<Entity, Vector2<f32>> {
+        neighbourhoods
+            .all_boids
             .iter()
-            .fold(Vector2::new(0.0, 0.0), |prev, (_, pos, _)| prev + pos)
-            / (neighbours.len() as f32);
-        avg_position - position
+            .enumerate()
+            .map(|(index, &(entity, position, velocity))| {
+                let mut steering = Vector2::new(0.0, 0.0);
+                let (boid_data, scripted_steering) =
+                    match (boid_datas.get(entity), scripted_steerings.get(entity)) {
+                        (Some(boid_data), Some(scripted_steering)) => {
+                            (boid_data, scripted_steering)
+                        }
+                        _ => return (entity, steering),
+                    };
+
+                for rule in &scripted_steering.0 {
+                    let neighbours = neighbourhoods.neighbours(
+                        Some(index),
+                        position,
+                        rule.radius,
+                        Neighbourhood::Metric,
+                    );
+                    let boid = ScriptBoid {
+                        position,
+                        velocity,
+                        data: boid_data,
+                        delta_seconds,
+                    };
+                    steering += rule.weight
+                        * script_library.steer(&rule.script, boid, &neighbours, &rule.params);
+                }
+                (entity, steering)
+            })
+            .collect()
     }
 
     fn calculate_obstacles(
         &self,
-        all_boids: &Vec<(Entity, Vector2<f32>, Vector2<f32>)>,
-        obstacle_datas: &ReadStorage<ObstacleData>,
-        positions: &ReadStorage<Position>,
-    ) -> Vec<(Entity, Vector2<f32>)> {
-        // Construct returned struct
-        let mut avoidance_vecs = all_boids
+        neighbourhoods: &Neighbourhoods,
+        point_obstacles: &[(&ObstacleData, Vector2<f32>)],
+    ) -> HashMap<Entity, Vector2<f32>> {
+        let mut avoidance_vecs = neighbourhoods
+            .all_boids
             .iter()
             .map(|(entity, _, _)| (*entity, Vector2::new(0., 0.)))
-            .collect::<Vec<_>>();
-        let avoidance_map = all_boids
-            .iter()
-            .enumerate()
-            .map(|(i, (entity, _, _))| (entity, i))
             .collect::<HashMap<_, _>>();
 
-        for (obstacle_data, position) in (obstacle_datas, positions).join() {
-            let neighbours =
-                self.neighbour_boids(None, position.0, obstacle_data.separation_radius, all_boids);
+        for &(obstacle_data, position) in point_obstacles {
+            let neighbours = neighbourhoods.neighbours(
+                None,
+                position,
+                obstacle_data.separation_radius,
+                Neighbourhood::Metric,
+            );
             for (boid_entity, boid_position, _) in neighbours {
-                avoidance_vecs[avoidance_map[&boid_entity]].1 +=
-                    (boid_position - position.0) * obstacle_data.separation_weight;
+                if let Some(avoidance) = avoidance_vecs.get_mut(&boid_entity) {
+                    *avoidance += repulsion(
+                        boid_position - position,
+                        obstacle_data.separation_radius,
+                        obstacle_data.separation_falloff,
+                        Vector2::x(),
+                    ) * obstacle_data.separation_weight;
+                }
             }
         }
 
         avoidance_vecs
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        components::{Path, ScriptRule},
+        flocking::{tests::assert_close, VicsekParams},
+        resources::FlowLayer,
+        spatial::Shape,
+    };
+
+    fn boid_data(separation: f32, alignment: f32, cohesion: f32, radius: f32) -> BoidData {
+        BoidData {
+            separation_weight: separation,
+            alignment_weight: alignment,
+            cohesion_weight: cohesion,
+            noise_weight: 0.0,
+            separation_radius: radius,
+            alignment_radius: radius,
+            cohesion_radius: radius,
+            separation_falloff: Falloff::Linear,
+            neighbourhood: Neighbourhood::Metric,
+            max_speed: 1000.0,
+            look_ahead: 0.0,
+        }
+    }
+
+    fn new_world() -> World {
+        let mut world = World::new();
+        world.register::<BoidData>();
+        world.register::<Flocking>();
+        world.register::<ObstacleData>();
+        world.register::<ObstacleShape>();
+        world.register::<GoalData>();
+        world.register::<Attractor>();
+        world.register::<PathFollower>();
+        world.register::<Leader>();
+        world.register::<FollowerData>();
+        world.register::<FlowResponse>();
+        world.register::<ScriptedSteering>();
+        world.register::<Position>();
+        world.register::<Velocity>();
+        world.insert(Time::default());
+        world.insert(FlowField::default());
+        world.insert(ScriptLibrary::default());
+        world
+    }
+
+    fn add_boid(
+        world: &mut World,
+        data: BoidData,
+        position: (f32, f32),
+        velocity: (f32, f32),
+    ) -> Entity {
+        world
+            .create_entity()
+            .with(data)
+            .with(Position(Vector2::new(position.0, position.1)))
+            .with(Velocity(Vector2::new(velocity.0, velocity.1)))
+            .build()
+    }
+
+    /// Two boids that see each other, a lone boid next to an obstacle, and
+    /// a third boid created in between so the storages are not in a trivial order.
+    fn populate(world: &mut World) -> (Entity, Entity, Entity) {
+        let a = add_boid(
+            world,
+            boid_data(1.0, 0.5, 0.25, 50.0),
+            (0.0, 0.0),
+            (10.0, 0.0),
+        );
+        let lone = add_boid(
+            world,
+            boid_data(1.0, 1.0, 1.0, 50.0),
+            (1000.0, 0.0),
+            (-5.0, 0.0),
+        );
+        let b = add_boid(
+            world,
+            boid_data(0.0, 2.0, 1.0, 50.0),
+            (10.0, 0.0),
+            (0.0, 10.0),
+        );
+        world
+            .create_entity()
+            .with(Position(Vector2::new(1000.0, 30.0)))
+            .with(ObstacleData {
+                separation_weight: 0.5,
+                separation_radius: 50.0,
+                separation_falloff: Falloff::Linear,
+            })
+            .build();
+        (a, lone, b)
+    }
+
+    fn rules(world: &World) -> RuleOutputs {
+        let (
+            boid_datas,
+            flockings,
+            obstacle_datas,
+            obstacle_shapes,
+            positions,
+            velocities,
+            entities,
+        ) = world.system_data::<(
+            ReadStorage<BoidData>,
+            ReadStorage<Flocking>,
+            ReadStorage<ObstacleData>,
+            ReadStorage<ObstacleShape>,
+            ReadStorage<Position>,
+            ReadStorage<Velocity>,
+            Entities,
+        )>();
+        let all_boids = (&boid_datas, &positions, &velocities, &entities)
+            .join()
+            .map(|(_, p, v, e)| (e, p.0, v.0))
+            .collect::<Vec<_>>();
+        let point_obstacles = (&obstacle_datas, &positions)
+            .join()
+            .map(|(o, p)| (o, p.0))
+            .collect::<Vec<_>>();
+        let solid_obstacles = (&obstacle_shapes, &positions)
+            .join()
+            .map(|(o, p)| (o, p.0))
+            .collect::<Vec<_>>();
+        BoidSystem.calculate_rules(
+            &boid_datas,
+            &flockings,
+            &point_obstacles,
+            &solid_obstacles,
+            &Neighbourhoods::new(&all_boids, &boid_datas),
+            1.0 / 60.0,
+        )
+    }
+
+    #[test]
+    fn rules_are_keyed_by_entity() {
+        let mut world = new_world();
+        let (a, lone, b) = populate(&mut world);
+        let rules = rules(&world);
+
+        // 1.0 * separation + 0.5 * alignment + 0.25 * cohesion
+        assert_close(rules.model[&a], Vector2::new(-42.5, 5.0));
+        // 0.0 * separation + 2.0 * alignment + 1.0 * cohesion
+        assert_close(rules.model[&b], Vector2::new(10.0, -20.0));
+        assert_eq!(rules.model[&lone], Vector2::new(0.0, 0.0));
+
+        assert_eq!(rules.obstacle[&a], Vector2::new(0.0, 0.0));
+        assert_eq!(rules.obstacle[&b], Vector2::new(0.0, 0.0));
+        assert_close(rules.obstacle[&lone], Vector2::new(0.0, -10.0));
+    }
+
+    #[test]
+    fn weights_are_applied_to_matching_entity() {
+        let mut world = new_world();
+        let (a, lone, b) = populate(&mut world);
+
+        BoidSystem.run_now(&world);
+        let velocities = world.read_storage::<Velocity>();
+
+        // v + 1.0 * sep + 0.5 * align + 0.25 * coh
+        assert_close(velocities.get(a).unwrap().0, Vector2::new(-32.5, 5.0));
+        // v + 0.0 * sep + 2.0 * align + 1.0 * coh
+        assert_eq!(velocities.get(b).unwrap().0, Vector2::new(10.0, -10.0));
+        // v + obstacle push only
+        assert_close(velocities.get(lone).unwrap().0, Vector2::new(-5.0, -10.0));
+    }
+
+    #[test]
+    fn non_finite_steering_is_dropped() {
+        let mut world = new_world();
+        let boid = add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 50.0),
+            (0.0, 0.0),
+            (10.0, 0.0),
+        );
+        world
+            .create_entity()
+            .with(Position(Vector2::new(10.0, 0.0)))
+            .with(ObstacleData {
+                separation_weight: f32::INFINITY,
+                separation_radius: 50.0,
+                separation_falloff: Falloff::Linear,
+            })
+            .build();
+
+        BoidSystem.run_now(&world);
+        let velocities = world.read_storage::<Velocity>();
+        assert_eq!(velocities.get(boid).unwrap().0, Vector2::new(10.0, 0.0));
+    }
+
+    #[test]
+    fn topological_neighbours_ignore_distance() {
+        let mut world = new_world();
+        let mut data = boid_data(0.0, 0.0, 1.0, 50.0);
+        data.neighbourhood = Neighbourhood::Topological(2);
+        let boid = add_boid(&mut world, data, (0.0, 0.0), (0.0, 0.0));
+        add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 50.0),
+            (300.0, 0.0),
+            (0.0, 0.0),
+        );
+        add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 50.0),
+            (0.0, 500.0),
+            (0.0, 0.0),
+        );
+        add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 50.0),
+            (-900.0, 0.0),
+            (0.0, 0.0),
+        );
+
+        // Only the two nearest pull on the boid, despite being outside its radius
+        assert_close(rules(&world).model[&boid], Vector2::new(150.0, 250.0));
+    }
+
+    #[test]
+    fn voronoi_neighbours_skip_hidden_boids() {
+        let mut world = new_world();
+        let mut data = boid_data(0.0, 0.0, 1.0, 50.0);
+        data.neighbourhood = Neighbourhood::Voronoi;
+        let boid = add_boid(&mut world, data, (0.0, 0.0), (0.0, 0.0));
+        add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 50.0),
+            (100.0, 0.0),
+            (0.0, 0.0),
+        );
+        add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 50.0),
+            (90.0, 90.0),
+            (0.0, 0.0),
+        );
+        add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 50.0),
+            (0.0, 100.0),
+            (0.0, 0.0),
+        );
+        // Hidden behind the boid at (100, 0) along the x axis
+        add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 50.0),
+            (300.0, 10.0),
+            (0.0, 0.0),
+        );
+
+        assert_close(
+            rules(&world).model[&boid],
+            Vector2::new(190.0 / 3.0, 190.0 / 3.0),
+        );
+    }
+
+    #[test]
+    fn model_is_chosen_per_boid() {
+        let mut world = new_world();
+        let reynolds = add_boid(
+            &mut world,
+            boid_data(0.0, 1.0, 0.0, 50.0),
+            (0.0, 0.0),
+            (10.0, 0.0),
+        );
+        let vicsek = add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 50.0),
+            (10.0, 0.0),
+            (0.0, 10.0),
+        );
+        world
+            .write_storage::<Flocking>()
+            .insert(
+                vicsek,
+                Flocking::Vicsek(VicsekParams {
+                    radius: 50.0,
+                    speed: 2.0,
+                    noise: 0.0,
+                }),
+            )
+            .unwrap();
+
+        BoidSystem.run_now(&world);
+        let velocities = world.read_storage::<Velocity>();
+
+        // Reynolds matches the neighbour's velocity through alignment, while
+        // Vicsek averages both headings at its own constant speed
+        assert_close(velocities.get(reynolds).unwrap().0, Vector2::new(0.0, 10.0));
+        assert_close(
+            velocities.get(vicsek).unwrap().0,
+            Vector2::new(2.0f32.sqrt(), 2.0f32.sqrt()),
+        );
+    }
+
+    #[test]
+    fn feeler_steers_away_from_wall_ahead() {
+        let mut world = new_world();
+        let mut data = boid_data(0.0, 0.0, 0.0, 50.0);
+        data.look_ahead = 1.0;
+        let heading_in = add_boid(&mut world, data.clone(), (0.0, 0.0), (100.0, 0.0));
+        let heading_away = add_boid(&mut world, data, (0.0, 20.0), (-100.0, 0.0));
+        world
+            .create_entity()
+            .with(Position(Vector2::new(50.0, 0.0)))
+            .with(ObstacleShape {
+                shape: Shape::Segment {
+                    start: Vector2::new(0.0, -50.0),
+                    end: Vector2::new(0.0, 50.0),
+                },
+                avoidance_weight: 0.5,
+            })
+            .build();
+
+        let rules = rules(&world);
+        // Normal * max speed * weight * (1 - 50 / 100)
+        assert_close(rules.avoidance[&heading_in], Vector2::new(-250.0, 0.0));
+        assert_eq!(rules.avoidance[&heading_away], Vector2::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn goals_pull_towards_attractor_and_waypoints() {
+        let mut world = new_world();
+        let goals = GoalData {
+            seek_weight: 0.5,
+            arrive_weight: 0.0,
+            slowing_radius: 100.0,
+            path_weight: 0.25,
+        };
+        let seeker = add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 50.0),
+            (0.0, 0.0),
+            (0.0, 0.0),
+        );
+        let follower = add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 50.0),
+            (0.0, 5000.0),
+            (0.0, 0.0),
+        );
+        let aimless = add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 50.0),
+            (0.0, -5000.0),
+            (0.0, 0.0),
+        );
+        world
+            .write_storage::<GoalData>()
+            .insert(seeker, goals.clone())
+            .unwrap();
+        let mut goals = goals;
+        goals.seek_weight = 0.0;
+        world
+            .write_storage::<GoalData>()
+            .insert(follower, goals)
+            .unwrap();
+        world
+            .write_storage::<PathFollower>()
+            .insert(
+                follower,
+                PathFollower::new(Path {
+                    waypoints: vec![Vector2::new(0.0, 5000.0), Vector2::new(-50.0, 5000.0)],
+                    looped: false,
+                    waypoint_radius: 10.0,
+                }),
+            )
+            .unwrap();
+        world
+            .create_entity()
+            .with(Position(Vector2::new(100.0, 0.0)))
+            .with(Attractor)
+            .build();
+        // One that's gone NaN is never the nearest
+        world
+            .create_entity()
+            .with(Position(Vector2::new(f32::NAN, 0.0)))
+            .with(Attractor)
+            .build();
+
+        BoidSystem.run_now(&world);
+        let velocities = world.read_storage::<Velocity>();
+
+        // Half of max speed towards the attractor
+        assert_close(velocities.get(seeker).unwrap().0, Vector2::new(500.0, 0.0));
+        // Moved on from the first waypoint, and slowing down for the last one
+        // within the slowing radius: 0.25 * 1000 * 50 / 100
+        assert_close(
+            velocities.get(follower).unwrap().0,
+            Vector2::new(-125.0, 0.0),
+        );
+        assert_eq!(velocities.get(aimless).unwrap().0, Vector2::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn followers_line_up_behind_leader() {
+        let mut world = new_world();
+        let follower_data = FollowerData {
+            follow_weight: 0.5,
+            behind_distance: 100.0,
+            slowing_radius: 0.0,
+            evade_weight: 0.0,
+            evade_distance: 0.0,
+            separation_weight: 1.0,
+            separation_radius: 20.0,
+        };
+        let leader = add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 10.0),
+            (0.0, 0.0),
+            (10.0, 0.0),
+        );
+        let follower = add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 10.0),
+            (-100.0, 100.0),
+            (0.0, 0.0),
+        );
+        let crowded = add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 10.0),
+            (-100.0, -100.0),
+            (0.0, 0.0),
+        );
+        let crowding = add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 10.0),
+            (-100.0, -110.0),
+            (0.0, 0.0),
+        );
+        // A leader that's gone NaN is never the nearest
+        let lost = add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 10.0),
+            (f32::NAN, 0.0),
+            (0.0, 0.0),
+        );
+        for entity in [leader, lost].iter() {
+            world
+                .write_storage::<Leader>()
+                .insert(*entity, Leader)
+                .unwrap();
+        }
+        for entity in [leader, follower, crowded, crowding].iter() {
+            world
+                .write_storage::<FollowerData>()
+                .insert(*entity, follower_data.clone())
+                .unwrap();
+        }
+
+        BoidSystem.run_now(&world);
+        let velocities = world.read_storage::<Velocity>();
+
+        // The leader keeps going its own way
+        assert_eq!(velocities.get(leader).unwrap().0, Vector2::new(10.0, 0.0));
+        // Half of max speed towards (-100, 0)
+        assert_close(
+            velocities.get(follower).unwrap().0,
+            Vector2::new(0.0, -500.0),
+        );
+        // The same pull, plus being pushed 20 * (1 - 10 / 20) apart
+        assert_close(velocities.get(crowded).unwrap().0, Vector2::new(0.0, 510.0));
+        assert_close(
+            velocities.get(crowding).unwrap().0,
+            Vector2::new(0.0, 490.0),
+        );
+    }
+
+    #[test]
+    fn responsive_boids_turn_into_the_flow() {
+        let mut world = new_world();
+        world.insert(FlowField {
+            layers: vec![FlowLayer::Uniform {
+                velocity: Vector2::new(50.0, 0.0),
+            }],
+            gust: None,
+        });
+        let responsive = add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 10.0),
+            (0.0, 0.0),
+            (0.0, 100.0),
+        );
+        let oblivious = add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 10.0),
+            (500.0, 0.0),
+            (0.0, 100.0),
+        );
+        world
+            .write_storage::<FlowResponse>()
+            .insert(
+                responsive,
+                FlowResponse {
+                    susceptibility: 1.0,
+                    upwind_weight: 0.1,
+                },
+            )
+            .unwrap();
+
+        BoidSystem.run_now(&world);
+        let velocities = world.read_storage::<Velocity>();
+
+        // v + 0.1 * ((-1000, 0) - v)
+        assert_close(
+            velocities.get(responsive).unwrap().0,
+            Vector2::new(-100.0, 90.0),
+        );
+        assert_eq!(
+            velocities.get(oblivious).unwrap().0,
+            Vector2::new(0.0, 100.0)
+        );
+    }
+
+    #[test]
+    fn scripted_boids_steer_by_their_scripts() {
+        let mut world = new_world();
+        let hunter = add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 10.0),
+            (0.0, 0.0),
+            (0.0, 0.0),
+        );
+        let prey = add_boid(
+            &mut world,
+            boid_data(0.0, 0.0, 0.0, 10.0),
+            (30.0, 0.0),
+            (0.0, 0.0),
+        );
+        let rule = ScriptRule {
+            script: "hunt.rhai".to_string(),
+            weight: 0.5,
+            radius: 100.0,
+            params: Default::default(),
+        };
+        world
+            .write_storage::<ScriptedSteering>()
+            .insert(hunter, ScriptedSteering(vec![rule]))
+            .unwrap();
+
+        BoidSystem.run_now(&world);
+        let velocities = world.read_storage::<Velocity>();
+
+        // Half of full speed straight at the prey
+        assert_close(velocities.get(hunter).unwrap().0, Vector2::new(500.0, 0.0));
+        assert_eq!(velocities.get(prey).unwrap().0, Vector2::new(0.0, 0.0));
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{
+    components::{BoidData, Position, Species, Velocity},
+    export::{hue_colour, palette_colour, ramp_colour, rgba_f32},
+    resources::{ColourMode, Scenario, CLUSTER_DISTANCE, CROWDED_NEIGHBOURS},
+    spatial::SpatialGrid,
+};
+use amethyst::{
+    assets::{AssetStorage, Loader},
+    derive::SystemDesc,
+    ecs::prelude::*,
+    ecs::{Entities, Read, ReadExpect, ReadStorage, System, WriteStorage},
+    renderer::{palette::Srgba, resources::Tint},
+    ui::{get_default_font, Anchor, FontAsset, FontHandle, LineMode, UiText, UiTransform},
+};
+
+/// Size of the cells boids are sorted into to count neighbours and find
+/// clusters.
+const GRID_CELL_SIZE: f32 = 100.0;
+
+/// Tints every boid's sprite as the `ColourMode` resource says, or leaves
+/// them untinted in `ColourMode::Plain`.
+#[derive(SystemDesc)]
+pub struct TintSystem;
+
+impl<'s> System<'s> for TintSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, BoidData>,
+        ReadStorage<'s, Position>,
+        ReadStorage<'s, Velocity>,
+        ReadStorage<'s, Species>,
+        WriteStorage<'s, Tint>,
+        Read<'s, ColourMode>,
+        ReadExpect<'s, Scenario>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, boid_datas, positions, velocities, species, mut tints, mode, scenario): Self::SystemData,
+    ) {
+        if *mode == ColourMode::Plain {
+            tints.clear();
+            return;
+        }
+
+        let boids = (
+            &entities,
+            &boid_datas,
+            &positions,
+            &velocities,
+            species.maybe(),
+        )
+            .join()
+            .collect::<Vec<_>>();
+        let grid = match *mode {
+            ColourMode::Neighbours | ColourMode::Cluster => Some(SpatialGrid::new(
+                GRID_CELL_SIZE,
+                boids.iter().map(|boid| boid.2 .0).collect(),
+            )),
+            _ => None,
+        };
+        let cluster_labels = match (*mode, &grid) {
+            (ColourMode::Cluster, Some(grid)) => grid.cluster_labels(CLUSTER_DISTANCE),
+            _ => vec![],
+        };
+
+        for (index, (entity, boid_data, position, velocity, species)) in boids.iter().enumerate() {
+            let colour = match *mode {
+                ColourMode::Plain | ColourMode::Species => palette_colour(
+                    species
+                        .and_then(|species| scenario.species_index(&species.0))
+                        .unwrap_or_default(),
+                ),
+                ColourMode::Speed => ramp_colour(velocity.0.norm(), boid_data.max_speed),
+                ColourMode::Heading => hue_colour(velocity.0.y.atan2(velocity.0.x)),
+                ColourMode::Neighbours => {
+                    let neighbours = grid
+                        .as_ref()
+                        .map(|grid| grid.within_radius(position.0, boid_data.cohesion_radius))
+                        .map_or(0, |found| found.len().saturating_sub(1));
+                    ramp_colour(neighbours as f32, CROWDED_NEIGHBOURS as f32)
+                }
+                ColourMode::Cluster => palette_colour(cluster_labels[index]),
+            };
+            let [red, green, blue, alpha] = rgba_f32(colour);
+            // The boid is alive, as it was just joined over
+            let _ = tints.insert(*entity, Tint(Srgba::new(red, green, blue, alpha)));
+        }
+    }
+}
+
+/// Space between the legend and the window's corner, in pixels.
+const LEGEND_MARGIN: f32 = 12.0;
+const LEGEND_LINE_HEIGHT: f32 = 22.0;
+const LEGEND_FONT_SIZE: f32 = 18.0;
+
+/// Explains the boids' colours in the top left corner of the window, one
+/// line of text for each line of `ColourMode::legend`.
+#[derive(Default)]
+pub struct LegendSystem {
+    font: Option<FontHandle>,
+    lines: Vec<Entity>,
+}
+
+impl<'s> System<'s> for LegendSystem {
+    type SystemData = (
+        Entities<'s>,
+        WriteStorage<'s, UiTransform>,
+        WriteStorage<'s, UiText>,
+        Read<'s, ColourMode>,
+        ReadExpect<'s, Scenario>,
+        ReadExpect<'s, Loader>,
+        Read<'s, AssetStorage<FontAsset>>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, mut transforms, mut texts, mode, scenario, loader, fonts): Self::SystemData,
+    ) {
+        let legend = mode.legend(&scenario);
+        let font = self
+            .font
+            .get_or_insert_with(|| get_default_font(&loader, &fonts))
+            .clone();
+
+        while self.lines.len() < legend.len() {
+            let index = self.lines.len();
+            let line = entities.create();
+            let _ = transforms.insert(
+                line,
+                UiTransform::new(
+                    format!("legend_{}", index),
+                    Anchor::TopLeft,
+                    Anchor::TopLeft,
+                    LEGEND_MARGIN,
+                    -LEGEND_MARGIN - index as f32 * LEGEND_LINE_HEIGHT,
+                    1.0,
+                    600.0,
+                    LEGEND_LINE_HEIGHT,
+                ),
+            );
+            let _ = texts.insert(
+                line,
+                UiText::new(
+                    font.clone(),
+                    String::new(),
+                    [1.0; 4],
+                    LEGEND_FONT_SIZE,
+                    LineMode::Single,
+                    Anchor::MiddleLeft,
+                ),
+            );
+            self.lines.push(line);
+        }
+
+        // Lines past the end of the legend are left blank
+        for (index, line) in self.lines.iter().enumerate() {
+            if let Some(text) = texts.get_mut(*line) {
+                let (content, colour) = legend.get(index).cloned().unwrap_or_default();
+                if text.text != content {
+                    text.text = content;
+                }
+                text.color = rgba_f32(colour);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{export::Colour, simulation::Simulation};
+    use amethyst::{config::Config, ecs::RunNow};
+    use nalgebra::Vector2;
+
+    fn tint(colour: Colour) -> Tint {
+        let [red, green, blue, alpha] = rgba_f32(colour);
+        Tint(Srgba::new(red, green, blue, alpha))
+    }
+
+    #[test]
+    fn boids_are_tinted_by_the_mode() {
+        let scenario =
+            Scenario::load_bytes(include_bytes!("../../resources/scenarios/murmuration.ron"))
+                .unwrap();
+        let mut simulation = Simulation::new(scenario).unwrap();
+        RunNow::setup(&mut TintSystem, &mut simulation.world);
+        let tint_of = |simulation: &Simulation, species: &str| {
+            let (species_storage, tints) = simulation
+                .world
+                .system_data::<(ReadStorage<Species>, ReadStorage<Tint>)>();
+            (&species_storage, &tints)
+                .join()
+                .find(|(other, _)| other.0 == species)
+                .map(|(_, tint)| tint.0)
+        };
+
+        simulation.world.insert(ColourMode::Species);
+        TintSystem.run_now(&simulation.world);
+        let [red, green, blue, alpha] = rgba_f32(palette_colour(1));
+        assert_eq!(
+            tint_of(&simulation, "falcon"),
+            Some(Srgba::new(red, green, blue, alpha))
+        );
+        assert_eq!(
+            simulation.world.read_storage::<Tint>().count(),
+            simulation.metrics().boids
+        );
+
+        simulation.world.insert(ColourMode::Cluster);
+        TintSystem.run_now(&simulation.world);
+        assert!(tint_of(&simulation, "starling").is_some());
+
+        simulation.world.insert(ColourMode::Plain);
+        TintSystem.run_now(&simulation.world);
+        assert_eq!(simulation.world.read_storage::<Tint>().count(), 0);
+    }
+    #[test]
+    fn clusters_and_crowds_are_told_apart() {
+        let scenario =
+            Scenario::load_bytes(include_bytes!("../../resources/scenarios/default.ron")).unwrap();
+        let species = scenario.species[0].clone();
+        let mut simulation = Simulation::new(scenario).unwrap();
+        simulation
+            .spawn(&species, 60, Vector2::new(10.0, 10.0))
+            .unwrap();
+        RunNow::setup(&mut TintSystem, &mut simulation.world);
+
+        // One boid on its own, and the rest in two crowds far apart
+        let boids = {
+            let (entities, boid_datas, mut positions) =
+                simulation
+                    .world
+                    .system_data::<(Entities, ReadStorage<BoidData>, WriteStorage<Position>)>();
+            let boids = (&entities, &boid_datas)
+                .join()
+                .map(|(entity, _)| entity)
+                .collect::<Vec<_>>();
+            for (index, boid) in boids.iter().enumerate() {
+                let centre = match index {
+                    0 => Vector2::new(0.0, 1000.0),
+                    _ if index % 2 == 0 => Vector2::new(-400.0, 0.0),
+                    _ => Vector2::new(400.0, 0.0),
+                };
+                positions.get_mut(*boid).unwrap().0 =
+                    centre + Vector2::new(index as f32 * 0.01, 0.0);
+            }
+            boids
+        };
+        let tints = |simulation: &Simulation| {
+            let tints = simulation.world.read_storage::<Tint>();
+            boids
+                .iter()
+                .map(|boid| *tints.get(*boid).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        simulation.world.insert(ColourMode::Cluster);
+        TintSystem.run_now(&simulation.world);
+        let clusters = tints(&simulation);
+        let (left, right) = (&clusters[2], &clusters[1]);
+        assert_ne!(left, right);
+        assert_ne!(&clusters[0], left);
+        assert_ne!(&clusters[0], right);
+        for (index, cluster) in clusters.iter().enumerate().skip(1) {
+            assert_eq!(cluster, if index % 2 == 0 { left } else { right });
+        }
+
+        simulation.world.insert(ColourMode::Neighbours);
+        TintSystem.run_now(&simulation.world);
+        let crowding = tints(&simulation);
+        let crowded = CROWDED_NEIGHBOURS as f32;
+        assert_eq!(crowding[0], tint(ramp_colour(0.0, crowded)));
+        for crowded_tint in &crowding[1..] {
+            assert_eq!(*crowded_tint, tint(ramp_colour(crowded, crowded)));
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use crate::{
+    components::{Attractor, Food, Leader, ObstacleShape, Position, Selected, Trail},
+    resources::{FlowField, Scenario},
+    spatial::Shape,
+};
+use amethyst::{
+    core::{math::Point3, Time},
+    derive::SystemDesc,
+    ecs::prelude::*,
+    ecs::{Read, ReadExpect, ReadStorage, System, Write},
+    renderer::{debug_drawing::DebugLines, palette::Srgba},
+};
+use nalgebra::Vector2;
+
+/// Outlines solid obstacles, which have no sprite of their own.
+#[derive(SystemDesc)]
+pub struct ObstacleDrawSystem;
+
+impl<'s> System<'s> for ObstacleDrawSystem {
+    type SystemData = (
+        ReadStorage<'s, ObstacleShape>,
+        ReadStorage<'s, Position>,
+        Write<'s, DebugLines>,
+    );
+
+    fn run(&mut self, (obstacle_shapes, positions, mut debug_lines): Self::SystemData) {
+        let color = Srgba::new(0.9, 0.9, 0.95, 1.0);
+
+        for (obstacle, position) in (&obstacle_shapes, &positions).join() {
+            match (&obstacle.shape, obstacle.shape.edges(position.0)) {
+                (Shape::Circle { radius }, _) => debug_lines.draw_circle(
+                    Point3::new(position.0.x, position.0.y, 0.0),
+                    *radius,
+                    32,
+                    color,
+                ),
+                (_, Some(edges)) => {
+                    for (start, end) in edges {
+                        debug_lines.draw_line(
+                            Point3::new(start.x, start.y, 0.0),
+                            Point3::new(end.x, end.y, 0.0),
+                            color,
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Spacing between the arrows showing the flow field, in world units.
+const FLOW_ARROW_SPACING: f32 = 120.0;
+/// Half the size of the area the flow field is shown over.
+const FLOW_EXTENTS: (f32, f32) = (900.0, 480.0);
+/// Seconds of drift each flow arrow is as long as.
+const FLOW_ARROW_SECONDS: f32 = 0.5;
+
+/// Marks attractors and traces the scenario's paths.
+#[derive(SystemDesc)]
+pub struct GoalDrawSystem;
+
+impl<'s> System<'s> for GoalDrawSystem {
+    type SystemData = (
+        ReadStorage<'s, Attractor>,
+        ReadStorage<'s, Position>,
+        ReadExpect<'s, Scenario>,
+        Write<'s, DebugLines>,
+    );
+
+    fn run(&mut self, (attractors, positions, scenario, mut debug_lines): Self::SystemData) {
+        let attractor_color = Srgba::new(1.0, 0.8, 0.2, 1.0);
+        let path_color = Srgba::new(0.4, 0.6, 1.0, 0.5);
+
+        for (_, position) in (&attractors, &positions).join() {
+            debug_lines.draw_circle(
+                Point3::new(position.0.x, position.0.y, 0.0),
+                12.0,
+                16,
+                attractor_color,
+            );
+        }
+
+        for path in scenario.paths.values() {
+            let closing = if path.looped {
+                path.waypoints.first()
+            } else {
+                None
+            };
+            let points = path.waypoints.iter().chain(closing).collect::<Vec<_>>();
+            for pair in points.windows(2) {
+                debug_lines.draw_line(
+                    Point3::new(pair[0].x, pair[0].y, 0.0),
+                    Point3::new(pair[1].x, pair[1].y, 0.0),
+                    path_color,
+                );
+            }
+            for waypoint in &path.waypoints {
+                debug_lines.draw_circle(
+                    Point3::new(waypoint.x, waypoint.y, 0.0),
+                    path.waypoint_radius,
+                    16,
+                    path_color,
+                );
+            }
+        }
+    }
+}
+
+/// Rings leaders and the selected boid.
+#[derive(SystemDesc)]
+pub struct SelectionDrawSystem;
+
+impl<'s> System<'s> for SelectionDrawSystem {
+    type SystemData = (
+        ReadStorage<'s, Leader>,
+        ReadStorage<'s, Selected>,
+        ReadStorage<'s, Position>,
+        Write<'s, DebugLines>,
+    );
+
+    fn run(&mut self, (leaders, selected, positions, mut debug_lines): Self::SystemData) {
+        let leader_color = Srgba::new(1.0, 0.3, 0.3, 1.0);
+        let selected_color = Srgba::new(1.0, 1.0, 1.0, 1.0);
+
+        for (_, position) in (&leaders, &positions).join() {
+            debug_lines.draw_circle(
+                Point3::new(position.0.x, position.0.y, 0.0),
+                20.0,
+                16,
+                leader_color,
+            );
+        }
+        for (_, position) in (&selected, &positions).join() {
+            debug_lines.draw_circle(
+                Point3::new(position.0.x, position.0.y, 0.0),
+                28.0,
+                16,
+                selected_color,
+            );
+        }
+    }
+}
+
+/// Draws each boid's trail as a line strip fading out towards its oldest
+/// end.
+#[derive(SystemDesc)]
+pub struct TrailDrawSystem;
+
+impl<'s> System<'s> for TrailDrawSystem {
+    type SystemData = (
+        ReadStorage<'s, Trail>,
+        ReadStorage<'s, Position>,
+        Write<'s, DebugLines>,
+    );
+
+    fn run(&mut self, (trails, positions, mut debug_lines): Self::SystemData) {
+        for (trail, position) in (&trails, &positions).join() {
+            let points = trail.points().chain(Some(&position.0)).collect::<Vec<_>>();
+            for (i, segment) in points.windows(2).enumerate() {
+                let alpha = 0.6 * (i + 1) as f32 / (points.len() - 1) as f32;
+                debug_lines.draw_line(
+                    Point3::new(segment[0].x, segment[0].y, 0.0),
+                    Point3::new(segment[1].x, segment[1].y, 0.0),
+                    Srgba::new(0.95, 0.95, 0.9, alpha),
+                );
+            }
+        }
+    }
+}
+
+/// Draws the flow field as a grid of arrows pointing downstream.
+#[derive(SystemDesc)]
+pub struct FlowDrawSystem;
+
+impl<'s> System<'s> for FlowDrawSystem {
+    type SystemData = (Read<'s, FlowField>, Read<'s, Time>, Write<'s, DebugLines>);
+
+    fn run(&mut self, (flow_field, time, mut debug_lines): Self::SystemData) {
+        if flow_field.layers.is_empty() {
+            return;
+        }
+        let color = Srgba::new(0.6, 0.9, 0.9, 0.4);
+        let now = time.absolute_time_seconds() as f32;
+
+        let columns = (FLOW_EXTENTS.0 / FLOW_ARROW_SPACING) as i32;
+        let rows = (FLOW_EXTENTS.1 / FLOW_ARROW_SPACING) as i32;
+        for column in -columns..=columns {
+            for row in -rows..=rows {
+                let start = Vector2::new(
+                    column as f32 * FLOW_ARROW_SPACING,
+                    row as f32 * FLOW_ARROW_SPACING,
+                );
+                let drift = flow_field.sample(start, now) * FLOW_ARROW_SECONDS;
+                let end = start + drift;
+                // Arrow head, angled back from the tip on both sides
+                let back = -drift * 0.25;
+                let side = Vector2::new(-back.y, back.x) * 0.5;
+                for tip in [
+                    (start, end),
+                    (end, end + back + side),
+                    (end, end + back - side),
+                ]
+                .iter()
+                {
+                    debug_lines.draw_line(
+                        Point3::new(tip.0.x, tip.0.y, 0.0),
+                        Point3::new(tip.1.x, tip.1.y, 0.0),
+                        color,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Draws food sources, more opaque the fuller they are.
+#[derive(SystemDesc)]
+pub struct FoodDrawSystem;
+
+impl<'s> System<'s> for FoodDrawSystem {
+    type SystemData = (
+        ReadStorage<'s, Food>,
+        ReadStorage<'s, Position>,
+        Write<'s, DebugLines>,
+    );
+
+    fn run(&mut self, (foods, positions, mut debug_lines): Self::SystemData) {
+        for (food, position) in (&foods, &positions).join() {
+            let fullness = if food.capacity > 0.0 {
+                food.amount / food.capacity
+            } else {
+                0.0
+            };
+            debug_lines.draw_circle(
+                Point3::new(position.0.x, position.0.y, 0.0),
+                food.radius,
+                24,
+                Srgba::new(0.3, 0.9, 0.3, 0.2 + 0.8 * fullness),
+            );
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{
+    components::{BoidData, Leader, Selected, Velocity},
+    input::{ActionBinding, AxisBinding, ControlBindingTypes},
+};
+use amethyst::{
+    derive::SystemDesc,
+    ecs::prelude::*,
+    ecs::{Entities, Read, ReadStorage, System, WriteStorage},
+    input::InputHandler,
+};
+use nalgebra::Vector2;
+
+/// Fraction of the gap between the current and the requested velocity a
+/// player-controlled leader closes every frame.
+const CONTROL_RESPONSE: f32 = 0.2;
+
+/// Toggles leadership of the selected boids, and steers selected leaders
+/// with the arrow keys.
+#[derive(SystemDesc, Default)]
+pub struct LeaderControlSystem {
+    toggle_prev_pressed: bool,
+}
+
+impl<'s> System<'s> for LeaderControlSystem {
+    type SystemData = (
+        Read<'s, InputHandler<ControlBindingTypes>>,
+        ReadStorage<'s, Selected>,
+        ReadStorage<'s, BoidData>,
+        WriteStorage<'s, Leader>,
+        WriteStorage<'s, Velocity>,
+        Entities<'s>,
+    );
+
+    fn run(
+        &mut self,
+        (input, selected, boid_datas, mut leaders, mut velocities, entities): Self::SystemData,
+    ) {
+        let toggle_pressed = input
+            .action_is_down(&ActionBinding::ToggleLeader)
+            .unwrap_or(false);
+        if !toggle_pressed && self.toggle_prev_pressed {
+            for (_, entity) in (&selected, &entities).join() {
+                if leaders.remove(entity).is_none() {
+                    leaders.insert(entity, Leader).unwrap();
+                }
+            }
+        }
+        self.toggle_prev_pressed = toggle_pressed;
+
+        let control = Vector2::new(
+            input.axis_value(&AxisBinding::Horizontal).unwrap_or(0.0),
+            input.axis_value(&AxisBinding::Vertical).unwrap_or(0.0),
+        );
+        let direction = match control.try_normalize(0.0) {
+            Some(direction) => direction,
+            None => return,
+        };
+        for (_, _, boid_data, velocity) in
+            (&selected, &leaders, &boid_datas, &mut velocities).join()
+        {
+            let desired = direction * boid_data.max_speed;
+            velocity.0 += (desired - velocity.0) * CONTROL_RESPONSE;
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use crate::{
+    components::{Age, BoidData, Energy, Food, Lifespan, Metabolism, Position, Species, Velocity},
+    entities::boids::fill_boid,
+    random,
+    resources::{PopulationHistory, PopulationSample, Scenario, SpriteRegistry},
+};
+use amethyst::{
+    core::Time,
+    derive::SystemDesc,
+    ecs::prelude::*,
+    ecs::{Entities, LazyUpdate, Read, ReadExpect, ReadStorage, System, Write, WriteStorage},
+};
+use anyhow::anyhow;
+use log::{debug, warn};
+use nalgebra::Vector2;
+use rand::distributions::{Distribution, Uniform};
+use std::collections::{BTreeMap, HashSet};
+
+/// Seconds between two samples of the population.
+const POPULATION_SAMPLE_SECONDS: f32 = 1.0;
+/// Furthest from its parent an offspring is placed.
+const OFFSPRING_SPREAD: f32 = 15.0;
+
+/// Spends energy on movement, feeds boids from nearby food, removes the
+/// starved, spawns offspring from the well fed, ages boids, removes those past
+/// their lifespan or beyond the population cap and tracks the population.
+#[derive(SystemDesc)]
+pub struct LifecycleSystem;
+
+impl<'s> System<'s> for LifecycleSystem {
+    type SystemData = (
+        WriteStorage<'s, Energy>,
+        ReadStorage<'s, Metabolism>,
+        WriteStorage<'s, Age>,
+        ReadStorage<'s, Lifespan>,
+        WriteStorage<'s, Food>,
+        ReadStorage<'s, Species>,
+        ReadStorage<'s, BoidData>,
+        ReadStorage<'s, Position>,
+        ReadStorage<'s, Velocity>,
+        Entities<'s>,
+        Read<'s, LazyUpdate>,
+        Read<'s, SpriteRegistry>,
+        ReadExpect<'s, Scenario>,
+        Read<'s, Time>,
+        Write<'s, PopulationHistory>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut energies,
+            metabolisms,
+            mut ages,
+            lifespans,
+            mut foods,
+            species,
+            boid_datas,
+            positions,
+            velocities,
+            entities,
+            lazy_update,
+            sprite_registry,
+            scenario,
+            time,
+            mut population,
+        ): Self::SystemData,
+    ) {
+        let frame_delta_s = time.fixed_time().as_secs_f32();
+        // Deleted entities stay alive until the end of the frame
+        let mut removed = HashSet::new();
+
+        for (energy, metabolism, position, velocity, entity) in (
+            &mut energies,
+            &metabolisms,
+            &positions,
+            &velocities,
+            &entities,
+        )
+            .join()
+        {
+            energy.0 -= (metabolism.idle_drain + metabolism.speed_drain * velocity.0.norm())
+                * frame_delta_s;
+
+            for (food, food_position) in (&mut foods, &positions).join() {
+                if (food_position.0 - position.0).norm() <= food.radius {
+                    let eaten = food.amount.min(metabolism.eat_rate * frame_delta_s);
+                    food.amount -= eaten;
+                    energy.0 += eaten;
+                }
+            }
+
+            if energy.0 <= 0.0 {
+                entities.delete(entity).unwrap();
+                removed.insert(entity);
+            } else if energy.0 >= metabolism.reproduction_threshold {
+                energy.0 -= metabolism.offspring_energy;
+                let result = species
+                    .get(entity)
+                    .ok_or_else(|| anyhow!("Boid {:?} has no species", entity))
+                    .and_then(|species| scenario.species_named(&species.0))
+                    .and_then(|species| {
+                        let offspring = fill_boid(
+                            &entities,
+                            &sprite_registry,
+                            &lazy_update,
+                            position.0 + offspring_offset(),
+                            &scenario,
+                            species,
+                        )?;
+                        // Inherit the parent's genome rather than the species'
+                        if let Some(parent) = boid_datas.get(entity) {
+                            let genome = match &species.mutation {
+                                Some(mutation) => mutation.mutate(parent, &mut random::rng()),
+                                None => parent.clone(),
+                            };
+                            lazy_update.insert(offspring, genome);
+                        }
+                        Ok(offspring)
+                    });
+                match result {
+                    Ok(offspring) => {
+                        lazy_update.insert(offspring, Energy(metabolism.offspring_energy))
+                    }
+                    Err(e) => warn!("Failed to spawn offspring: {:#}", e),
+                }
+            }
+        }
+
+        for (age, lifespan, entity) in (&mut ages, lifespans.maybe(), &entities).join() {
+            age.0 += frame_delta_s;
+            if lifespan.map_or(false, |lifespan| age.0 >= lifespan.0) && removed.insert(entity) {
+                entities.delete(entity).unwrap();
+            }
+        }
+
+        if let Some(max_population) = scenario.max_population {
+            let mut living = (&boid_datas, ages.maybe(), &entities)
+                .join()
+                .filter(|(_, _, entity)| !removed.contains(entity))
+                .map(|(_, age, entity)| (entity, age.map_or(0.0, |age| age.0)))
+                .collect::<Vec<_>>();
+            if living.len() > max_population {
+                // Oldest first
+                living.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+                let excess = living.len() - max_population;
+                for (entity, _) in living.into_iter().take(excess) {
+                    entities.delete(entity).unwrap();
+                    removed.insert(entity);
+                }
+            }
+        }
+
+        for food in (&mut foods).join() {
+            food.amount = (food.amount + food.regrowth * frame_delta_s).min(food.capacity);
+        }
+
+        let now = time.absolute_time_seconds() as f32;
+        if population.is_due(now, POPULATION_SAMPLE_SECONDS) {
+            let mut counts = BTreeMap::new();
+            for (species, entity) in (&species, &entities).join() {
+                if !removed.contains(&entity) {
+                    *counts.entry(species.0.clone()).or_insert(0) += 1;
+                }
+            }
+            if population.latest().map(|sample| &sample.counts) != Some(&counts) {
+                debug!("Population: {:?}", counts);
+            }
+            population
+                .samples
+                .push(PopulationSample { time: now, counts });
+        }
+    }
+}
+
+fn offspring_offset() -> Vector2<f32> {
+    let mut rng = random::rng();
+    let angle = Uniform::new(0.0, 2.0 * std::f32::consts::PI).sample(&mut rng);
+    let distance = Uniform::new(0.0, OFFSPRING_SPREAD).sample(&mut rng);
+    distance * Vector2::new(angle.cos(), angle.sin())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst::config::Config;
+
+    fn new_world() -> World {
+        let mut world = World::new();
+        world.register::<Energy>();
+        world.register::<Metabolism>();
+        world.register::<Age>();
+        world.register::<Lifespan>();
+        world.register::<Food>();
+        world.register::<Species>();
+        world.register::<BoidData>();
+        world.register::<Position>();
+        world.register::<Velocity>();
+        world.insert(Time::default());
+        world.insert(SpriteRegistry::new());
+        world.insert(PopulationHistory::default());
+        world.insert(
+            Scenario::load_bytes(include_bytes!("../../resources/scenarios/default.ron")).unwrap(),
+        );
+        world
+    }
+
+    fn metabolism() -> Metabolism {
+        Metabolism {
+            initial_energy: 10.0,
+            idle_drain: 0.0,
+            speed_drain: 1.0,
+            eat_rate: 120.0,
+            reproduction_threshold: 100.0,
+            offspring_energy: 10.0,
+        }
+    }
+
+    fn add_boid(world: &mut World, energy: f32, position: (f32, f32), speed: f32) -> Entity {
+        world
+            .create_entity()
+            .with(Energy(energy))
+            .with(metabolism())
+            .with(Species("boid".to_string()))
+            .with(Position(Vector2::new(position.0, position.1)))
+            .with(Velocity(Vector2::new(speed, 0.0)))
+            .build()
+    }
+
+    #[test]
+    fn moving_drains_energy_until_starving() {
+        let mut world = new_world();
+        let frame_delta_s = world.read_resource::<Time>().fixed_time().as_secs_f32();
+        let cruising = add_boid(&mut world, 10.0, (0.0, 0.0), 60.0);
+        let starving = add_boid(&mut world, 0.5, (0.0, 0.0), 60.0);
+
+        LifecycleSystem.run_now(&world);
+        world.maintain();
+
+        assert!(world.is_alive(cruising));
+        assert!(!world.is_alive(starving));
+        let energy = world.read_storage::<Energy>().get(cruising).unwrap().0;
+        assert!((energy - (10.0 - 60.0 * frame_delta_s)).abs() < 1e-4);
+
+        // Only the survivor is counted
+        let population = world.read_resource::<PopulationHistory>();
+        assert_eq!(population.samples.len(), 1);
+        assert_eq!(population.latest().unwrap().counts["boid"], 1);
+    }
+
+    #[test]
+    fn food_is_eaten_when_reached_and_grows_back() {
+        let mut world = new_world();
+        let frame_delta_s = world.read_resource::<Time>().fixed_time().as_secs_f32();
+        let feeding = add_boid(&mut world, 10.0, (0.0, 0.0), 0.0);
+        let hungry = add_boid(&mut world, 10.0, (100.0, 0.0), 0.0);
+        let food = world
+            .create_entity()
+            .with(Position(Vector2::new(10.0, 0.0)))
+            .with(Food {
+                amount: 50.0,
+                capacity: 50.0,
+                regrowth: 60.0,
+                radius: 20.0,
+            })
+            .build();
+
+        LifecycleSystem.run_now(&world);
+
+        let energies = world.read_storage::<Energy>();
+        let eaten = 120.0 * frame_delta_s;
+        assert!((energies.get(feeding).unwrap().0 - (10.0 + eaten)).abs() < 1e-4);
+        assert_eq!(energies.get(hungry).unwrap().0, 10.0);
+        // Half of what was eaten grows back within the frame
+        let amount = world.read_storage::<Food>().get(food).unwrap().amount;
+        assert!((amount - (50.0 - eaten / 2.0)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn boids_past_their_lifespan_or_the_cap_are_removed() {
+        let mut world = new_world();
+        world.write_resource::<Scenario>().max_population = Some(2);
+        let boid_data = world.read_resource::<Scenario>().species[0].boid.clone();
+        let mut add_aged = |age: f32, lifespan: Option<f32>| {
+            let mut builder = world
+                .create_entity()
+                .with(boid_data.clone())
+                .with(Age(age))
+                .with(Species("boid".to_string()))
+                .with(Position(Vector2::new(0.0, 0.0)))
+                .with(Velocity(Vector2::new(0.0, 0.0)));
+            if let Some(lifespan) = lifespan {
+                builder = builder.with(Lifespan(lifespan));
+            }
+            builder.build()
+        };
+        let expired = add_aged(5.0, Some(5.0));
+        let oldest = add_aged(4.0, None);
+        let survivors = [add_aged(3.0, Some(10.0)), add_aged(0.0, None)];
+
+        LifecycleSystem.run_now(&world);
+        world.maintain();
+
+        assert!(!world.is_alive(expired));
+        assert!(!world.is_alive(oldest));
+        assert!(survivors.iter().all(|boid| world.is_alive(*boid)));
+        let population = world.read_resource::<PopulationHistory>();
+        assert_eq!(population.latest().unwrap().counts["boid"], 2);
+    }
+
+    #[test]
+    fn well_fed_boids_breed_copies_of_themselves() {
+        let scenario =
+            Scenario::load_bytes(include_bytes!("../../resources/scenarios/default.ron")).unwrap();
+        let mut species = scenario.species_named("grazer").unwrap().clone();
+        species.mutation = None;
+        let mut simulation = crate::simulation::Simulation::new(scenario).unwrap();
+        simulation
+            .spawn(&species, 1, Vector2::new(0.0, 0.0))
+            .unwrap();
+
+        let metabolism = species.metabolism.unwrap();
+        let (parent, genome) = {
+            let (entities, mut boid_datas, mut energies) =
+                simulation
+                    .world
+                    .system_data::<(Entities, WriteStorage<BoidData>, WriteStorage<Energy>)>();
+            let (parent, boid_data, energy) = (&entities, &mut boid_datas, &mut energies)
+                .join()
+                .next()
+                .unwrap();
+            energy.0 = metabolism.reproduction_threshold + 10.0;
+            // Differs from the species so inheritance can be told apart
+            boid_data.cohesion_weight = 0.123;
+            (parent, boid_data.clone())
+        };
+
+        simulation.step();
+
+        assert_eq!(simulation.count("grazer"), 2);
+        let boid_datas = simulation.world.read_storage::<BoidData>();
+        let energies = simulation.world.read_storage::<Energy>();
+        for (entity, boid_data, energy) in
+            (&simulation.world.entities(), &boid_datas, &energies).join()
+        {
+            assert_eq!(boid_data.cohesion_weight, genome.cohesion_weight);
+            if entity != parent {
+                assert_eq!(energy.0, metabolism.offspring_energy);
+            }
+        }
+        assert!(energies.get(parent).unwrap().0 < metabolism.reproduction_threshold);
+    }
+}
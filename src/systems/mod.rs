@@ -1,7 +1,24 @@
+mod animation;
 mod boids;
+mod colouring;
+mod debug_draw;
+mod leaders;
+mod lifecycle;
 mod mouse;
 mod physics;
+mod recording;
+mod trails;
 
+pub use self::animation::SpriteAnimationSystem;
 pub use self::boids::BoidSystem;
+pub use self::colouring::{LegendSystem, TintSystem};
+pub use self::debug_draw::{
+    FlowDrawSystem, FoodDrawSystem, GoalDrawSystem, ObstacleDrawSystem, SelectionDrawSystem,
+    TrailDrawSystem,
+};
+pub use self::leaders::LeaderControlSystem;
+pub use self::lifecycle::LifecycleSystem;
 pub use self::mouse::MouseInputSystem;
 pub use self::physics::{PhysicsSystem, PositionSystem};
+pub use self::recording::RecordingSystem;
+pub use self::trails::TrailSystem;
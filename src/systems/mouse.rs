@@ -1,21 +1,40 @@
 use crate::{
-    entities::boids::fill_boid,
+    components::{BoidData, Position, Selected},
+    entities::{
+        boids::{fill_boid, spawn_region},
+        despawn::{despawn, DespawnTarget},
+        goals::fill_attractor,
+    },
     input::{ActionBinding, ControlBindingTypes},
-    resources::SpriteCache,
+    resources::{Scenario, SpawnArea, SpawnRegion, SpriteRegistry},
 };
 use amethyst::{
     core::{geometry::Plane, transform::Transform},
     derive::SystemDesc,
-    ecs::{prelude::*, Entities, LazyUpdate, Read, ReadExpect, ReadStorage, System},
+    ecs::{prelude::*, Entities, LazyUpdate, Read, ReadExpect, ReadStorage, System, WriteStorage},
     input::InputHandler,
     renderer::camera::{ActiveCamera, Camera},
     window::ScreenDimensions,
 };
+use log::warn;
 use nalgebra::{Point2, Vector2};
 
+/// How far from a boid a click can land and still select it.
+const SELECT_RADIUS: f32 = 30.0;
+/// Radius around the cursor that erasing removes boids and obstacles from.
+const ERASE_RADIUS: f32 = 60.0;
+
 #[derive(SystemDesc, Default)]
 pub struct MouseInputSystem {
     place_prev_pressed: bool,
+    /// Set while a spawn burst or erase shares the click that would otherwise
+    /// place a single boid.
+    place_cancelled: bool,
+    attractor_prev_pressed: bool,
+    burst_prev_pressed: bool,
+    erase_prev_pressed: bool,
+    clear_prev_pressed: bool,
+    select_prev_pressed: bool,
 }
 
 impl<'s> System<'s> for MouseInputSystem {
@@ -24,10 +43,14 @@ impl<'s> System<'s> for MouseInputSystem {
         Read<'s, LazyUpdate>,
         Read<'s, ActiveCamera>,
         Read<'s, InputHandler<ControlBindingTypes>>,
-        Read<'s, SpriteCache>,
+        Read<'s, SpriteRegistry>,
+        ReadExpect<'s, Scenario>,
         ReadExpect<'s, ScreenDimensions>,
         ReadStorage<'s, Camera>,
         ReadStorage<'s, Transform>,
+        ReadStorage<'s, BoidData>,
+        ReadStorage<'s, Position>,
+        WriteStorage<'s, Selected>,
     );
 
     fn run(
@@ -37,10 +60,14 @@ impl<'s> System<'s> for MouseInputSystem {
             lazy_update,
             active_camera,
             input,
-            sprite_cache,
+            sprite_registry,
+            scenario,
             screen_dimensions,
             cameras,
             transforms,
+            boid_datas,
+            positions,
+            mut selected,
         ): Self::SystemData,
     ) {
         let mouse = match input.mouse_position() {
@@ -48,6 +75,23 @@ impl<'s> System<'s> for MouseInputSystem {
             None => Point2::new(0.0, 0.0),
         };
         let place_pressed = input.action_is_down(&ActionBinding::Place).unwrap_or(false);
+        let attractor_pressed = input
+            .action_is_down(&ActionBinding::PlaceAttractor)
+            .unwrap_or(false);
+        let select_pressed = input
+            .action_is_down(&ActionBinding::Select)
+            .unwrap_or(false);
+        let burst_pressed = input
+            .action_is_down(&ActionBinding::SpawnBurst)
+            .unwrap_or(false);
+        let erase_pressed = input.action_is_down(&ActionBinding::Erase).unwrap_or(false);
+        let clear_pressed = input.action_is_down(&ActionBinding::Clear).unwrap_or(false);
+        if burst_pressed || erase_pressed {
+            self.place_cancelled = true;
+        }
+        if !clear_pressed && self.clear_prev_pressed {
+            despawn(&lazy_update, DespawnTarget::Boids);
+        }
         let mut camera_join = (&cameras, &transforms).join();
         if let Some((camera, camera_transform)) = active_camera
             .entity
@@ -63,17 +107,79 @@ impl<'s> System<'s> for MouseInputSystem {
             let distance = ray.intersect_plane(&Plane::with_z(0.0)).unwrap();
             let mouse_pos = ray.at_distance(distance);
 
-            if !place_pressed && self.place_prev_pressed {
-                fill_boid(
+            if !place_pressed && self.place_prev_pressed && !self.place_cancelled {
+                let placed = scenario.default_species().and_then(|species| {
+                    fill_boid(
+                        &entities,
+                        &sprite_registry,
+                        &lazy_update,
+                        Vector2::new(mouse_pos.x, mouse_pos.y),
+                        &scenario,
+                        species,
+                    )
+                });
+                if let Err(e) = placed {
+                    warn!("Failed to place boid: {:#}", e);
+                }
+            }
+            if !burst_pressed && self.burst_prev_pressed {
+                let region = SpawnRegion {
+                    area: scenario
+                        .burst
+                        .area
+                        .centered_on(Vector2::new(mouse_pos.x, mouse_pos.y)),
+                    ..scenario.burst.clone()
+                };
+                let spawned = spawn_region(
+                    &entities,
+                    &sprite_registry,
+                    &lazy_update,
+                    &scenario,
+                    &region,
+                    Vector2::new(screen_dimensions.width(), screen_dimensions.height()) / 2.0,
+                );
+                if let Err(e) = spawned {
+                    warn!("Failed to spawn burst: {:#}", e);
+                }
+            }
+            if !erase_pressed && self.erase_prev_pressed {
+                let area = SpawnArea::Circle {
+                    center: Vector2::new(mouse_pos.x, mouse_pos.y),
+                    radius: ERASE_RADIUS,
+                };
+                despawn(&lazy_update, DespawnTarget::Region(area));
+            }
+            if !attractor_pressed && self.attractor_prev_pressed {
+                fill_attractor(
                     &entities,
-                    &sprite_cache,
                     &lazy_update,
                     Vector2::new(mouse_pos.x, mouse_pos.y),
-                )
-                .unwrap();
+                );
+            }
+            if !select_pressed && self.select_prev_pressed {
+                let mouse_pos = Vector2::new(mouse_pos.x, mouse_pos.y);
+                let nearest = (&boid_datas, &positions, &entities)
+                    .join()
+                    .map(|(_, position, entity)| (entity, (position.0 - mouse_pos).norm()))
+                    .filter(|(_, distance)| *distance <= SELECT_RADIUS)
+                    .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+                // Clicking away from every boid clears the selection
+                selected.clear();
+                if let Some((entity, _)) = nearest {
+                    selected.insert(entity, Selected).unwrap();
+                }
             }
         }
 
+        if !place_pressed {
+            self.place_cancelled = false;
+        }
         self.place_prev_pressed = place_pressed;
+        self.attractor_prev_pressed = attractor_pressed;
+        self.burst_prev_pressed = burst_pressed;
+        self.erase_prev_pressed = erase_pressed;
+        self.clear_prev_pressed = clear_pressed;
+        self.select_prev_pressed = select_pressed;
     }
 }
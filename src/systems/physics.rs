@@ -1,4 +1,8 @@
-use crate::components::{Position, Velocity};
+use crate::{
+    components::{FlowResponse, ObstacleShape, Position, Velocity},
+    resources::FlowField,
+    spatial::Shape,
+};
 use amethyst::{
     core::{Time, Transform},
     derive::SystemDesc,
@@ -7,22 +11,100 @@ use amethyst::{
 };
 use nalgebra::Vector2;
 
+/// Gap kept between a boid and any solid obstacle it runs into.
+const SKIN: f32 = 0.5;
+/// Most times every obstacle is checked against a boid's move in one tick.
+const MAX_PASSES: usize = 4;
+/// Distance an obstacle can move a boid's end point by that's still taken to
+/// leave it where it was.
+const SETTLED: f32 = 1e-4;
+
 #[derive(SystemDesc)]
 pub struct PhysicsSystem;
 
 impl<'s> System<'s> for PhysicsSystem {
     type SystemData = (
-        ReadStorage<'s, Velocity>,
+        ReadStorage<'s, ObstacleShape>,
+        ReadStorage<'s, FlowResponse>,
+        WriteStorage<'s, Velocity>,
         WriteStorage<'s, Position>,
         Read<'s, Time>,
+        Read<'s, FlowField>,
     );
 
-    fn run(&mut self, (velocities, mut positions, time): Self::SystemData) {
-        for (velocity, position) in (&velocities, &mut positions).join() {
+    fn run(
+        &mut self,
+        (
+            obstacle_shapes,
+            flow_responses,
+            mut velocities,
+            mut positions,
+            time,
+            flow_field,
+        ): Self::SystemData,
+    ) {
+        let solid_obstacles = (&obstacle_shapes, &positions)
+            .join()
+            .map(|(o, p)| (o.shape.clone(), p.0))
+            .collect::<Vec<_>>();
+
+        let now = time.absolute_time_seconds() as f32;
+        for (velocity, position, flow_response) in
+            (&mut velocities, &mut positions, flow_responses.maybe()).join()
+        {
             let frame_delta_s = time.fixed_time().as_secs_f32();
-            position.0 += velocity.0 * frame_delta_s;
+            // The flow carries boids along without changing their own velocity
+            let drift = flow_response.map_or_else(Vector2::zeros, |flow_response| {
+                flow_field.sample(position.0, now) * flow_response.susceptibility
+            });
+            let target = position.0 + (velocity.0 + drift) * frame_delta_s;
+            position.0 = resolve_collisions(&solid_obstacles, position.0, target, &mut velocity.0);
+        }
+    }
+}
+
+/// Moves from `from` towards `to`, stopping short of any solid obstacle in the
+/// way and pushing back out of any the end point lands inside. The velocity
+/// loses whatever component points into a surface that was hit, so the boid
+/// slides along it instead.
+///
+/// Pushing out of one obstacle can move the end point back across another, so
+/// every obstacle is checked again until none of them moves it. If that takes
+/// more than `MAX_PASSES`, as when obstacles meet at a tight corner, the boid
+/// stays at `from`.
+fn resolve_collisions(
+    solid_obstacles: &[(Shape, Vector2<f32>)],
+    from: Vector2<f32>,
+    to: Vector2<f32>,
+    velocity: &mut Vector2<f32>,
+) -> Vector2<f32> {
+    let mut target = to;
+    for _ in 0..MAX_PASSES {
+        let mut moved = false;
+        for (shape, center) in solid_obstacles {
+            let before = target;
+            let motion = target - from;
+            let distance = motion.norm();
+            if distance > 0.0 {
+                let direction = motion / distance;
+                if let Some(hit) = shape.ray_cast(*center, from, direction, distance + SKIN) {
+                    target = from + direction * (hit.distance - SKIN).max(0.0);
+                    *velocity -= hit.normal * velocity.dot(&hit.normal).min(0.0);
+                }
+            }
+
+            if let Some((surface, normal)) = shape.push_out(*center, target) {
+                target = surface + normal * SKIN;
+                *velocity -= normal * velocity.dot(&normal).min(0.0);
+            }
+            moved |= (target - before).norm() > SETTLED;
+        }
+        if !moved {
+            return target;
         }
     }
+
+    from
 }
 
 #[derive(SystemDesc)]
@@ -49,3 +131,147 @@ impl<'s> System<'s> for PositionSystem {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flocking::tests::assert_close;
+
+    fn wall() -> Vec<(Shape, Vector2<f32>)> {
+        vec![(
+            Shape::Segment {
+                start: Vector2::new(0.0, -100.0),
+                end: Vector2::new(0.0, 100.0),
+            },
+            Vector2::new(10.0, 0.0),
+        )]
+    }
+
+    #[test]
+    fn cannot_cross_a_wall() {
+        let mut velocity = Vector2::new(600.0, 60.0);
+        let end = resolve_collisions(
+            &wall(),
+            Vector2::new(0.0, 0.0),
+            Vector2::new(60.0, 6.0),
+            &mut velocity,
+        );
+
+        assert!(end.x < 10.0, "{:?}", end);
+        // Slides along the wall rather than stopping dead
+        assert_eq!(velocity, Vector2::new(0.0, 60.0));
+    }
+
+    #[test]
+    fn moving_away_keeps_velocity() {
+        let mut velocity = Vector2::new(-60.0, 0.0);
+        let end = resolve_collisions(
+            &wall(),
+            Vector2::new(9.0, 0.0),
+            Vector2::new(3.0, 0.0),
+            &mut velocity,
+        );
+
+        assert_eq!(end, Vector2::new(3.0, 0.0));
+        assert_eq!(velocity, Vector2::new(-60.0, 0.0));
+    }
+
+    #[test]
+    fn pushed_out_of_solid_shapes() {
+        let solid_obstacles = vec![(
+            Shape::Box {
+                half_extents: Vector2::new(10.0, 10.0),
+            },
+            Vector2::new(0.0, 0.0),
+        )];
+        let mut velocity = Vector2::new(0.0, -5.0);
+        let end = resolve_collisions(
+            &solid_obstacles,
+            Vector2::new(0.0, 8.0),
+            Vector2::new(0.0, 7.0),
+            &mut velocity,
+        );
+
+        assert!(end.y >= 10.0, "{:?}", end);
+        assert_eq!(velocity, Vector2::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn cannot_leave_an_acute_corner_through_a_wall() {
+        // A wall along the x axis, and a thin wedge meeting it at the origin
+        // whose nearest side is pushed out of towards the wall
+        let solid_obstacles = vec![
+            (
+                Shape::Segment {
+                    start: Vector2::new(0.0, 0.0),
+                    end: Vector2::new(100.0, 0.0),
+                },
+                Vector2::new(0.0, 0.0),
+            ),
+            (
+                Shape::Polygon {
+                    points: vec![
+                        Vector2::new(0.0, 0.0),
+                        Vector2::new(100.0, 20.0),
+                        Vector2::new(100.0, 30.0),
+                    ],
+                },
+                Vector2::new(0.0, 0.0),
+            ),
+        ];
+        let from = Vector2::new(1.0, 0.22);
+        let mut velocity = Vector2::new(-6.0, 0.0);
+        let end = resolve_collisions(
+            &solid_obstacles,
+            from,
+            Vector2::new(0.95, 0.22),
+            &mut velocity,
+        );
+
+        assert!(end.y >= 0.0, "{:?}", end);
+    }
+
+    #[test]
+    fn flow_carries_susceptible_boids() {
+        let mut world = World::new();
+        world.register::<ObstacleShape>();
+        world.register::<FlowResponse>();
+        world.register::<Velocity>();
+        world.register::<Position>();
+        world.insert(Time::default());
+        world.insert(FlowField {
+            layers: vec![crate::resources::FlowLayer::Uniform {
+                velocity: Vector2::new(60.0, 0.0),
+            }],
+            gust: None,
+        });
+        let carried = world
+            .create_entity()
+            .with(Position(Vector2::new(0.0, 0.0)))
+            .with(Velocity(Vector2::new(0.0, 0.0)))
+            .with(FlowResponse {
+                susceptibility: 0.5,
+                upwind_weight: 0.0,
+            })
+            .build();
+        let unaffected = world
+            .create_entity()
+            .with(Position(Vector2::new(0.0, 0.0)))
+            .with(Velocity(Vector2::new(0.0, 0.0)))
+            .build();
+
+        PhysicsSystem.run_now(&world);
+        PhysicsSystem.run_now(&world);
+        let velocities = world.read_storage::<Velocity>();
+        let positions = world.read_storage::<Position>();
+        let frame_delta_s = world.read_resource::<Time>().fixed_time().as_secs_f32();
+
+        // Drifting at a steady half of the flow, without speeding up
+        assert_close(
+            positions.get(carried).unwrap().0,
+            Vector2::new(2.0 * 30.0 * frame_delta_s, 0.0),
+        );
+        assert_eq!(velocities.get(carried).unwrap().0, Vector2::new(0.0, 0.0));
+        assert_eq!(positions.get(unaffected).unwrap().0, Vector2::new(0.0, 0.0));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::components::{Position, Species, Velocity};
+use amethyst::{
+    core::Time,
+    ecs::{prelude::*, Entities, Read, ReadStorage, System},
+};
+use anyhow::{Context, Result};
+use log::warn;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+/// Appends the position and velocity of every boid to a CSV file each frame,
+/// so runs of the viewer can be plotted or replayed afterwards.
+pub struct RecordingSystem {
+    writer: BufWriter<File>,
+    /// Set once writing fails, so the error is only reported once.
+    failed: bool,
+}
+
+impl RecordingSystem {
+    pub fn create(path: &Path) -> Result<RecordingSystem> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create recording {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "frame,time,entity,species,x,y,vx,vy")?;
+        Ok(RecordingSystem {
+            writer,
+            failed: false,
+        })
+    }
+}
+
+impl<'s> System<'s> for RecordingSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, Species>,
+        ReadStorage<'s, Position>,
+        ReadStorage<'s, Velocity>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (entities, species, positions, velocities, time): Self::SystemData) {
+        if self.failed {
+            return;
+        }
+
+        let frame = time.frame_number();
+        let now = time.absolute_time_seconds();
+        for (entity, species, position, velocity) in
+            (&entities, &species, &positions, &velocities).join()
+        {
+            let written = writeln!(
+                self.writer,
+                "{},{},{},{},{},{},{},{}",
+                frame,
+                now,
+                entity.id(),
+                species.0,
+                position.0.x,
+                position.0.y,
+                velocity.0.x,
+                velocity.0.y,
+            );
+            if let Err(e) = written {
+                warn!("Stopped recording: {}", e);
+                self.failed = true;
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{
+    components::{BoidData, Position, Trail},
+    resources::TrailSettings,
+};
+use amethyst::{
+    core::Time,
+    derive::SystemDesc,
+    ecs::prelude::*,
+    ecs::{Entities, Read, ReadStorage, System, WriteStorage},
+};
+
+/// Keeps every boid's `Trail` up to date while `TrailSettings` has trails
+/// enabled, and clears them once it doesn't.
+#[derive(SystemDesc)]
+pub struct TrailSystem;
+
+impl<'s> System<'s> for TrailSystem {
+    type SystemData = (
+        Entities<'s>,
+        ReadStorage<'s, BoidData>,
+        ReadStorage<'s, Position>,
+        WriteStorage<'s, Trail>,
+        Read<'s, TrailSettings>,
+        Read<'s, Time>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, boid_datas, positions, mut trails, settings, time): Self::SystemData,
+    ) {
+        if !settings.enabled {
+            trails.clear();
+            return;
+        }
+
+        let delta_seconds = time.fixed_time().as_secs_f32();
+        for (entity, _, position) in (&entities, &boid_datas, &positions).join() {
+            if let Ok(entry) = trails.entry(entity) {
+                entry
+                    .or_insert_with(Trail::default)
+                    .sample(position.0, delta_seconds, &settings);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{export::Scene, resources::Scenario, simulation::Simulation};
+    use amethyst::config::Config;
+
+    #[test]
+    fn boids_leave_trails_while_enabled() {
+        let mut scenario =
+            Scenario::load_bytes(include_bytes!("../../resources/scenarios/murmuration.ron"))
+                .unwrap();
+        scenario.trails.enabled = true;
+        scenario.trails.length = 4;
+        let mut simulation = Simulation::new(scenario).unwrap();
+        simulation.run(20);
+
+        {
+            let trails = simulation.world.read_storage::<Trail>();
+            assert_eq!(trails.count(), simulation.metrics().boids);
+            assert!(trails.join().all(|trail| trail.len() <= 4));
+            assert!(trails.join().any(|trail| trail.len() > 1));
+        }
+        let scene = Scene::capture(&simulation.world);
+        assert!(scene.boids.iter().any(|boid| boid.trail.len() > 2));
+
+        simulation.world.write_resource::<TrailSettings>().enabled = false;
+        simulation.step();
+        assert_eq!(simulation.world.read_storage::<Trail>().count(), 0);
+    }
+}